@@ -48,6 +48,11 @@ struct Opt {
     #[structopt(short, long, parse(from_os_str))]
     config: Option<PathBuf>,
 
+    /// Accept a configuration file with missing or empty required fields
+    /// (`contributor_id`, `contributor_name` or `dataset_id`).
+    #[structopt(long)]
+    lenient: bool,
+
     /// Indicates if the input GTFS contains On-Demand Transport (ODT)
     /// information.
     #[structopt(short = "t", long = "on-demand-transport")]
@@ -103,7 +108,11 @@ fn init_logger() -> slog_scope::GlobalLoggerGuard {
 fn run(opt: Opt) -> Result<()> {
     info!("Launching gtfs2netexfr...");
 
-    let (contributor, dataset, feed_infos) = read_utils::read_config(opt.config)?;
+    let (contributor, dataset, feed_infos) = if opt.lenient {
+        read_utils::read_config(opt.config)?
+    } else {
+        read_utils::read_config_strict(opt.config)?
+    };
     let configuration = transit_model::gtfs::Configuration {
         contributor,
         dataset,
@@ -51,6 +51,11 @@ struct Opt {
     #[structopt(short, long, parse(from_os_str))]
     config: Option<PathBuf>,
 
+    /// Accept a configuration file with missing or empty required fields
+    /// (`contributor_id`, `contributor_name` or `dataset_id`).
+    #[structopt(long)]
+    lenient: bool,
+
     /// Prefix added to all the identifiers (`123` turned into `prefix:123`).
     #[structopt(short, long)]
     prefix: Option<String>,
@@ -100,7 +105,11 @@ struct Opt {
 fn run(opt: Opt) -> Result<()> {
     info!("Launching gtfs2ntfs...");
 
-    let (contributor, dataset, feed_infos) = read_utils::read_config(opt.config)?;
+    let (contributor, dataset, feed_infos) = if opt.lenient {
+        read_utils::read_config(opt.config)?
+    } else {
+        read_utils::read_config_strict(opt.config)?
+    };
     let mut prefix_conf = PrefixConfiguration::default();
     if let Some(data_prefix) = opt.prefix {
         prefix_conf.set_data_prefix(data_prefix);
@@ -116,6 +125,7 @@ fn run(opt: Opt) -> Result<()> {
         on_demand_transport: opt.odt,
         on_demand_transport_comment: opt.odt_comment,
         read_as_line: opt.read_as_line,
+        ..Default::default()
     };
 
     let model = transit_model::gtfs::Reader::new(configuration).parse(opt.input)?;
@@ -411,6 +411,29 @@ impl<'a> VehicleJourneyBuilder<'a> {
         }
         self
     }
+
+    /// Set the dataset of the vj
+    ///
+    /// ```
+    /// # fn main() {
+    /// let model = transit_model_builder::ModelBuilder::default()
+    ///        .vj("toto", |vj_builder| {
+    ///            vj_builder.dataset("d1");
+    ///        })
+    ///        .build();
+    /// # }
+    /// ```
+    pub fn dataset(self, id: &str) -> Self {
+        {
+            let vj = &mut self
+                .model
+                .collections
+                .vehicle_journeys
+                .index_mut(self.vj_idx);
+            vj.dataset_id = id.to_owned();
+        }
+        self
+    }
 }
 
 impl<'a> Drop for VehicleJourneyBuilder<'a> {
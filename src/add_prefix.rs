@@ -16,7 +16,7 @@
 
 use crate::model::Collections;
 use derivative::Derivative;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use typed_index_collection::{Collection, CollectionWithId, Id};
 
 /// Metadata for building the prefix.
@@ -34,6 +34,10 @@ pub struct PrefixConfiguration {
     /// objects (like Calendar).  Usually useful to avoid collisions when
     /// merging datasets from the same contributor.
     schedule_subprefix: Option<String>,
+    /// When set, the identifiers are not prefixed: the prefix `old` (followed
+    /// by the separator) is replaced by `new`, see
+    /// [`Collections::rename_prefix`].
+    renamed_prefix: Option<(String, String)>,
 }
 
 impl PrefixConfiguration {
@@ -61,10 +65,32 @@ impl PrefixConfiguration {
         self.schedule_subprefix = Some(schedule_subprefix.to_string());
     }
 
+    /// Configuration replacing the prefix `old` by `new` in the identifiers
+    /// already prefixed with `old`, instead of adding a new prefix.
+    pub(crate) fn renaming(old: &str, new: &str) -> Self {
+        PrefixConfiguration {
+            renamed_prefix: Some((old.to_string(), new.to_string())),
+            ..Default::default()
+        }
+    }
+
+    fn rename(&self, id: &str, old: &str, new: &str) -> String {
+        match id
+            .strip_prefix(old)
+            .and_then(|suffix| suffix.strip_prefix(self.sep.as_str()))
+        {
+            Some(suffix) => format!("{}{}{}", new, self.sep, suffix),
+            None => id.to_string(),
+        }
+    }
+
     /// Add prefix for referential-type object.
     ///
     /// Example of objects from the referential are Line or StopPoint.
     pub fn referential_prefix(&self, id: &str) -> String {
+        if let Some((old, new)) = self.renamed_prefix.as_ref() {
+            return self.rename(id, old, new);
+        }
         let mut prefix = String::new();
         if let Some(data_prefix) = self.data_prefix.as_ref() {
             prefix = prefix + data_prefix + &self.sep;
@@ -76,6 +102,9 @@ impl PrefixConfiguration {
     ///
     /// Example of objects from the schedule are VehicleJourney or StopTime.
     pub fn schedule_prefix(&self, id: &str) -> String {
+        if let Some((old, new)) = self.renamed_prefix.as_ref() {
+            return self.rename(id, old, new);
+        }
         let mut prefix = String::new();
         if let Some(data_prefix) = self.data_prefix.as_ref() {
             prefix = prefix + data_prefix + &self.sep;
@@ -95,7 +124,7 @@ pub trait AddPrefix {
         let prefix_conf = PrefixConfiguration {
             sep: String::new(),
             data_prefix: Some(prefix.to_string()),
-            schedule_subprefix: None,
+            ..Default::default()
         };
         self.prefix(&prefix_conf);
     }
@@ -107,7 +136,7 @@ pub trait AddPrefix {
         let prefix_conf = PrefixConfiguration {
             sep: String::from(sep),
             data_prefix: Some(prefix.to_string()),
-            schedule_subprefix: None,
+            ..Default::default()
         };
         self.prefix(&prefix_conf);
     }
@@ -173,45 +202,149 @@ fn add_prefix_on_vehicle_journey_ids_and_values(
 
 impl AddPrefix for Collections {
     fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
-        self.contributors.prefix(prefix_conf);
-        self.datasets.prefix(prefix_conf);
-        self.networks.prefix(prefix_conf);
-        self.lines.prefix(prefix_conf);
-        self.routes.prefix(prefix_conf);
-        self.vehicle_journeys.prefix(prefix_conf);
-        self.frequencies.prefix(prefix_conf);
-        self.stop_areas.prefix(prefix_conf);
-        self.stop_points.prefix(prefix_conf);
-        self.stop_locations.prefix(prefix_conf);
-        self.calendars.prefix(prefix_conf);
-        self.companies.prefix(prefix_conf);
-        self.comments.prefix(prefix_conf);
-        self.equipments.prefix(prefix_conf);
-        self.transfers.prefix(prefix_conf);
-        self.trip_properties.prefix(prefix_conf);
-        self.geometries.prefix(prefix_conf);
-        self.admin_stations.prefix(prefix_conf);
-        self.prices_v1.prefix(prefix_conf);
-        self.od_fares_v1.prefix(prefix_conf);
-        self.fares_v1.prefix(prefix_conf);
-        self.tickets.prefix(prefix_conf);
-        self.ticket_prices.prefix(prefix_conf);
-        self.ticket_uses.prefix(prefix_conf);
-        self.ticket_use_perimeters.prefix(prefix_conf);
-        self.ticket_use_restrictions.prefix(prefix_conf);
-        self.pathways.prefix(prefix_conf);
-        self.levels.prefix(prefix_conf);
-        self.grid_calendars.prefix(prefix_conf);
-        self.grid_exception_dates.prefix(prefix_conf);
-        self.grid_periods.prefix(prefix_conf);
-        self.grid_rel_calendar_line.prefix(prefix_conf);
-        self.stop_time_headsigns =
-            add_prefix_on_vehicle_journey_ids(&self.stop_time_headsigns, prefix_conf);
-        self.stop_time_ids =
-            add_prefix_on_vehicle_journey_ids_and_values(&self.stop_time_ids, prefix_conf);
-        self.stop_time_comments =
-            add_prefix_on_vehicle_journey_ids_and_values(&self.stop_time_comments, prefix_conf);
+        // Destructuring (without `..`) makes sure a new collection can't be
+        // added without deciding here whether it has to be prefixed.
+        let Collections {
+            contributors,
+            datasets,
+            networks,
+            // Commercial and physical modes have standardized NTFS identifiers
+            // shared by every dataset, they are never prefixed
+            commercial_modes: _,
+            lines,
+            routes,
+            vehicle_journeys,
+            frequencies,
+            physical_modes: _,
+            stop_areas,
+            stop_points,
+            stop_locations,
+            feed_infos: _,
+            calendars,
+            companies,
+            comments,
+            booking_rules,
+            equipments,
+            transfers,
+            trip_properties,
+            geometries,
+            admin_stations,
+            stop_time_headsigns,
+            stop_time_ids,
+            stop_time_comments,
+            stop_time_pickup_booking_rules,
+            stop_time_drop_off_booking_rules,
+            prices_v1,
+            od_fares_v1,
+            fares_v1,
+            tickets,
+            ticket_uses,
+            ticket_prices,
+            ticket_use_perimeters,
+            ticket_use_restrictions,
+            pathways,
+            levels,
+            grid_calendars,
+            grid_exception_dates,
+            grid_periods,
+            grid_rel_calendar_line,
+        } = self;
+        contributors.prefix(prefix_conf);
+        datasets.prefix(prefix_conf);
+        networks.prefix(prefix_conf);
+        lines.prefix(prefix_conf);
+        routes.prefix(prefix_conf);
+        vehicle_journeys.prefix(prefix_conf);
+        frequencies.prefix(prefix_conf);
+        stop_areas.prefix(prefix_conf);
+        stop_points.prefix(prefix_conf);
+        stop_locations.prefix(prefix_conf);
+        calendars.prefix(prefix_conf);
+        companies.prefix(prefix_conf);
+        comments.prefix(prefix_conf);
+        booking_rules.prefix(prefix_conf);
+        equipments.prefix(prefix_conf);
+        transfers.prefix(prefix_conf);
+        trip_properties.prefix(prefix_conf);
+        geometries.prefix(prefix_conf);
+        admin_stations.prefix(prefix_conf);
+        prices_v1.prefix(prefix_conf);
+        od_fares_v1.prefix(prefix_conf);
+        fares_v1.prefix(prefix_conf);
+        tickets.prefix(prefix_conf);
+        ticket_prices.prefix(prefix_conf);
+        ticket_uses.prefix(prefix_conf);
+        ticket_use_perimeters.prefix(prefix_conf);
+        ticket_use_restrictions.prefix(prefix_conf);
+        pathways.prefix(prefix_conf);
+        levels.prefix(prefix_conf);
+        grid_calendars.prefix(prefix_conf);
+        grid_exception_dates.prefix(prefix_conf);
+        grid_periods.prefix(prefix_conf);
+        grid_rel_calendar_line.prefix(prefix_conf);
+        *stop_time_headsigns = add_prefix_on_vehicle_journey_ids(stop_time_headsigns, prefix_conf);
+        // the vehicle journeys are keyed by index, only the identifiers are prefixed
+        for stop_time_id in stop_time_ids.values_mut() {
+            *stop_time_id = prefix_conf.schedule_prefix(stop_time_id.as_str());
+        }
+        *stop_time_comments =
+            add_prefix_on_vehicle_journey_ids_and_values(stop_time_comments, prefix_conf);
+        *stop_time_pickup_booking_rules = add_prefix_on_vehicle_journey_ids_and_values(
+            stop_time_pickup_booking_rules,
+            prefix_conf,
+        );
+        *stop_time_drop_off_booking_rules = add_prefix_on_vehicle_journey_ids_and_values(
+            stop_time_drop_off_booking_rules,
+            prefix_conf,
+        );
+    }
+}
+
+/// Count the objects with identifiers by prefix, see
+/// [Collections::distinct_prefixes].
+pub(crate) fn distinct_prefixes(collections: &Collections) -> BTreeMap<&str, usize> {
+    fn count_collection<'a, T: Id<T>>(
+        prefixes: &mut BTreeMap<&'a str, usize>,
+        collection: &'a CollectionWithId<T>,
+    ) {
+        for object in collection.values() {
+            let id = object.id();
+            let prefix = id.find(':').map(|pos| &id[..pos]).unwrap_or("");
+            *prefixes.entry(prefix).or_insert(0) += 1;
+        }
     }
+    let mut prefixes = BTreeMap::new();
+    macro_rules! count_prefixes {
+        ($($collection:expr),*) => {
+            $(count_collection(&mut prefixes, &$collection);)*
+        };
+    }
+    count_prefixes!(
+        collections.contributors,
+        collections.datasets,
+        collections.networks,
+        collections.commercial_modes,
+        collections.lines,
+        collections.routes,
+        collections.vehicle_journeys,
+        collections.physical_modes,
+        collections.stop_areas,
+        collections.stop_points,
+        collections.stop_locations,
+        collections.calendars,
+        collections.companies,
+        collections.comments,
+        collections.booking_rules,
+        collections.equipments,
+        collections.trip_properties,
+        collections.geometries,
+        collections.tickets,
+        collections.ticket_uses,
+        collections.pathways,
+        collections.levels,
+        collections.grid_calendars
+    );
+    prefixes
 }
 
 #[cfg(test)]
@@ -368,6 +501,18 @@ mod tests {
         assert_eq!(String::from("other_id"), element.0);
     }
 
+    #[test]
+    fn collection_with_id_renamed_prefix() {
+        let obj1 = Obj(String::from("OLD:some_id"));
+        let obj2 = Obj(String::from("OLDER:other_id"));
+        let obj3 = Obj(String::from("no_prefix"));
+        let mut collection = CollectionWithId::new(vec![obj1, obj2, obj3]).unwrap();
+        let prefix_conf = PrefixConfiguration::renaming("OLD", "NEW");
+        collection.prefix(&prefix_conf);
+        let ids: Vec<_> = collection.values().map(|obj| obj.0.as_str()).collect();
+        assert_eq!(vec!["NEW:some_id", "OLDER:other_id", "no_prefix"], ids);
+    }
+
     #[test]
     #[allow(deprecated)]
     fn collection_with_id_deprecated() {
@@ -381,4 +526,62 @@ mod tests {
         let element = values.next().unwrap();
         assert_eq!(String::from("pre:other_id"), element.0);
     }
+
+    mod distinct_prefixes {
+        use super::*;
+        use crate::objects::{Line, Network, PhysicalMode, StopPoint};
+        use pretty_assertions::assert_eq;
+
+        fn prefixed_collections(prefix: &str) -> Collections {
+            let mut collections = Collections {
+                networks: CollectionWithId::from(Network {
+                    id: "network".into(),
+                    ..Default::default()
+                }),
+                lines: CollectionWithId::from(Line {
+                    id: "line".into(),
+                    ..Default::default()
+                }),
+                stop_points: CollectionWithId::new(vec![
+                    StopPoint {
+                        id: "stop_point:1".into(),
+                        ..Default::default()
+                    },
+                    StopPoint {
+                        id: "stop_point:2".into(),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap(),
+                ..Default::default()
+            };
+            let mut prefix_conf = PrefixConfiguration::default();
+            prefix_conf.set_data_prefix(prefix);
+            collections.prefix(&prefix_conf);
+            collections
+        }
+
+        #[test]
+        fn count_by_prefix() {
+            let mut collections = prefixed_collections("A");
+            let mut other = prefixed_collections("B");
+            for network in other.networks.take() {
+                collections.networks.push(network).unwrap();
+            }
+            for line in other.lines.take() {
+                collections.lines.push(line).unwrap();
+            }
+            for stop_point in other.stop_points.take() {
+                collections.stop_points.push(stop_point).unwrap();
+            }
+            // physical modes are never prefixed
+            collections.physical_modes = CollectionWithId::from(PhysicalMode {
+                id: "Bus".into(),
+                ..Default::default()
+            });
+
+            let prefixes: Vec<_> = collections.distinct_prefixes().into_iter().collect();
+            assert_eq!(vec![("", 1), ("A", 4), ("B", 4)], prefixes);
+        }
+    }
 }
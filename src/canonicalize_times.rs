@@ -0,0 +1,154 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Rewriting of the stop times after midnight.
+
+use crate::{
+    model::Collections,
+    objects::{Calendar, Comment, CommentType, Time, VehicleJourney},
+    Result,
+};
+use chrono::Duration;
+use failure::format_err;
+use log::{info, warn};
+use typed_index_collection::Idx;
+
+/// Identifier of the comment linked to the vehicle journeys whose times have
+/// been moved within the day by `Collections::canonicalize_times`.
+pub const CALENDAR_SHIFT_COMMENT_ID: &str = "calendar_shift";
+
+/// How `Collections::canonicalize_times` handles the times after 24:00:00.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OvernightTimes {
+    /// Keep the times after 24:00:00 (e.g. 25:10:00).
+    KeepOvernight,
+    /// Move the vehicle journeys starting after 24:00:00 within the day
+    /// (e.g. 25:10:00 becomes 01:10:00) and their service to the next day.
+    ModuloDay,
+}
+
+impl Default for OvernightTimes {
+    fn default() -> Self {
+        OvernightTimes::KeepOvernight
+    }
+}
+
+const SECONDS_PER_DAY: u32 = 86_400;
+
+// Number of whole days after the service day before the first departure of
+// the vehicle journey, its stop times or its frequencies
+fn days_after_service_day(collections: &Collections, vj: &VehicleJourney) -> u32 {
+    let frequency_start_times = collections
+        .frequencies
+        .values()
+        .filter(|frequency| frequency.vehicle_journey_id == vj.id)
+        .map(|frequency| frequency.start_time);
+    vj.stop_times
+        .first()
+        .map(|stop_time| stop_time.arrival_time)
+        .into_iter()
+        .chain(frequency_start_times)
+        .min()
+        .map(|time| time.total_seconds() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+// Calendar running `days` days after the calendar `service_id`, created if
+// it doesn't exist yet
+fn shifted_calendar(collections: &mut Collections, service_id: &str, days: u32) -> Result<String> {
+    let shifted_id = format!("{}:shifted_{}", service_id, days);
+    if !collections.calendars.contains_id(&shifted_id) {
+        let calendar = collections
+            .calendars
+            .get(service_id)
+            .ok_or_else(|| format_err!("calendar {} not found", service_id))?;
+        let dates = calendar
+            .dates
+            .iter()
+            .map(|date| *date + Duration::days(i64::from(days)))
+            .collect();
+        collections.calendars.push(Calendar {
+            id: shifted_id.clone(),
+            dates,
+        })?;
+    }
+    Ok(shifted_id)
+}
+
+/// Rewrite the times after midnight, see [Collections::canonicalize_times].
+pub(crate) fn canonicalize_times(
+    collections: &mut Collections,
+    mode: OvernightTimes,
+) -> Result<()> {
+    if mode == OvernightTimes::KeepOvernight {
+        return Ok(());
+    }
+    let vj_shifts: Vec<(Idx<VehicleJourney>, u32)> = collections
+        .vehicle_journeys
+        .iter()
+        .map(|(idx, vj)| (idx, days_after_service_day(collections, vj)))
+        .filter(|(_, days)| *days > 0)
+        .collect();
+    if !vj_shifts.is_empty() && !collections.comments.contains_id(CALENDAR_SHIFT_COMMENT_ID) {
+        collections.comments.push(Comment {
+            id: CALENDAR_SHIFT_COMMENT_ID.to_string(),
+            comment_type: CommentType::Information,
+            label: None,
+            name: "This trip runs after midnight, on the next day of its service".to_string(),
+            url: None,
+        })?;
+    }
+    for &(vj_idx, days) in &vj_shifts {
+        let shift = Time::new(24 * days, 0, 0);
+        let service_id = collections.vehicle_journeys[vj_idx].service_id.clone();
+        let shifted_service_id = shifted_calendar(collections, &service_id, days)?;
+        let vj_id = {
+            let mut vj = collections.vehicle_journeys.index_mut(vj_idx);
+            for stop_time in &mut vj.stop_times {
+                stop_time.arrival_time = stop_time.arrival_time - shift;
+                stop_time.departure_time = stop_time.departure_time - shift;
+            }
+            vj.service_id = shifted_service_id;
+            vj.comment_links
+                .insert(CALENDAR_SHIFT_COMMENT_ID.to_string());
+            vj.id.clone()
+        };
+        for frequency in collections.frequencies.values_mut() {
+            if frequency.vehicle_journey_id == vj_id {
+                frequency.start_time = frequency.start_time - shift;
+                frequency.end_time = frequency.end_time - shift;
+            }
+        }
+    }
+    if !vj_shifts.is_empty() {
+        info!(
+            "{} vehicle journeys starting after midnight have been moved to the next day of their service",
+            vj_shifts.len()
+        );
+    }
+    // the vehicle journeys running across midnight can't be moved within
+    // a day without breaking the order of their times
+    let midnight = Time::new(24, 0, 0);
+    for vj in collections.vehicle_journeys.values() {
+        if vj.stop_times.iter().any(|stop_time| {
+            stop_time.arrival_time >= midnight || stop_time.departure_time >= midnight
+        }) {
+            warn!(
+                "vehicle journey {} runs across midnight, its times after 24:00:00 are kept",
+                vj.id
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,392 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Check of the references between the objects of the `Collections`.
+
+use crate::{model::Collections, objects::*};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use typed_index_collection::{CollectionWithId, Idx};
+
+/// A reference to an object which doesn't exist, reported by
+/// `Collections::is_consistent`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InconsistencyError {
+    /// Type of the object holding the reference (e.g. "VehicleJourney")
+    pub object_type: &'static str,
+    /// Identifier of the object holding the reference
+    pub object_id: String,
+    /// Name of the field holding the reference (e.g. "route_id")
+    pub field: &'static str,
+    /// Identifier of the referenced object, which doesn't exist
+    pub referenced_id: String,
+}
+
+impl std::fmt::Display for InconsistencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?}: {} {:?} not found",
+            self.object_type, self.object_id, self.field, self.referenced_id
+        )
+    }
+}
+
+// References of an object, checked by `Collections::is_consistent`
+struct References<'a> {
+    errors: &'a mut Vec<InconsistencyError>,
+    object_type: &'static str,
+    object_id: &'a str,
+}
+
+impl<'a> References<'a> {
+    fn new(
+        errors: &'a mut Vec<InconsistencyError>,
+        object_type: &'static str,
+        object_id: &'a str,
+    ) -> Self {
+        References {
+            errors,
+            object_type,
+            object_id,
+        }
+    }
+
+    fn check_exists(&mut self, field: &'static str, referenced_id: &str, exists: bool) {
+        if !exists {
+            self.errors.push(InconsistencyError {
+                object_type: self.object_type,
+                object_id: self.object_id.to_string(),
+                field,
+                referenced_id: referenced_id.to_string(),
+            });
+        }
+    }
+
+    fn check<T>(
+        &mut self,
+        field: &'static str,
+        referenced_id: &str,
+        collection: &CollectionWithId<T>,
+    ) {
+        self.check_exists(field, referenced_id, collection.contains_id(referenced_id));
+    }
+
+    fn check_opt<T>(
+        &mut self,
+        field: &'static str,
+        referenced_id: &Option<String>,
+        collection: &CollectionWithId<T>,
+    ) {
+        if let Some(referenced_id) = referenced_id {
+            self.check(field, referenced_id, collection);
+        }
+    }
+
+    fn check_comments(&mut self, object: &impl CommentLinks, comments: &CollectionWithId<Comment>) {
+        for comment_id in object.comment_links() {
+            self.check("comment_id", comment_id, comments);
+        }
+    }
+}
+
+/// Report the references to unknown objects, see [Collections::is_consistent].
+pub(crate) fn is_consistent(collections: &Collections) -> Vec<InconsistencyError> {
+    let mut errors = Vec::new();
+    for dataset in collections.datasets.values() {
+        let mut references = References::new(&mut errors, "Dataset", &dataset.id);
+        references.check(
+            "contributor_id",
+            &dataset.contributor_id,
+            &collections.contributors,
+        );
+    }
+    for line in collections.lines.values() {
+        let mut references = References::new(&mut errors, "Line", &line.id);
+        references.check("network_id", &line.network_id, &collections.networks);
+        references.check(
+            "commercial_mode_id",
+            &line.commercial_mode_id,
+            &collections.commercial_modes,
+        );
+        references.check_opt("geometry_id", &line.geometry_id, &collections.geometries);
+        references.check_opt(
+            "forward_direction",
+            &line.forward_direction,
+            &collections.stop_areas,
+        );
+        references.check_opt(
+            "backward_direction",
+            &line.backward_direction,
+            &collections.stop_areas,
+        );
+        references.check_comments(line, &collections.comments);
+    }
+    for route in collections.routes.values() {
+        let mut references = References::new(&mut errors, "Route", &route.id);
+        references.check("line_id", &route.line_id, &collections.lines);
+        references.check_opt("geometry_id", &route.geometry_id, &collections.geometries);
+        references.check_opt(
+            "destination_id",
+            &route.destination_id,
+            &collections.stop_areas,
+        );
+        references.check_comments(route, &collections.comments);
+    }
+    let stop_point_idxs: HashSet<Idx<StopPoint>> =
+        collections.stop_points.iter().map(|(idx, _)| idx).collect();
+    for vj in collections.vehicle_journeys.values() {
+        let mut references = References::new(&mut errors, "VehicleJourney", &vj.id);
+        references.check("route_id", &vj.route_id, &collections.routes);
+        references.check(
+            "physical_mode_id",
+            &vj.physical_mode_id,
+            &collections.physical_modes,
+        );
+        references.check("dataset_id", &vj.dataset_id, &collections.datasets);
+        references.check("service_id", &vj.service_id, &collections.calendars);
+        references.check("company_id", &vj.company_id, &collections.companies);
+        references.check_opt(
+            "trip_property_id",
+            &vj.trip_property_id,
+            &collections.trip_properties,
+        );
+        references.check_opt("geometry_id", &vj.geometry_id, &collections.geometries);
+        references.check_comments(vj, &collections.comments);
+        for stop_time in &vj.stop_times {
+            let exists = stop_point_idxs.contains(&stop_time.stop_point_idx);
+            // the stop point is referenced by index, report the sequence instead
+            references.check_exists("stop_point_idx", &stop_time.sequence.to_string(), exists);
+        }
+    }
+    for stop_area in collections.stop_areas.values() {
+        let mut references = References::new(&mut errors, "StopArea", &stop_area.id);
+        references.check_opt(
+            "geometry_id",
+            &stop_area.geometry_id,
+            &collections.geometries,
+        );
+        references.check_opt(
+            "equipment_id",
+            &stop_area.equipment_id,
+            &collections.equipments,
+        );
+        references.check_opt("level_id", &stop_area.level_id, &collections.levels);
+        references.check_comments(stop_area, &collections.comments);
+    }
+    for stop_point in collections.stop_points.values() {
+        let mut references = References::new(&mut errors, "StopPoint", &stop_point.id);
+        references.check(
+            "stop_area_id",
+            &stop_point.stop_area_id,
+            &collections.stop_areas,
+        );
+        references.check_opt(
+            "geometry_id",
+            &stop_point.geometry_id,
+            &collections.geometries,
+        );
+        references.check_opt(
+            "equipment_id",
+            &stop_point.equipment_id,
+            &collections.equipments,
+        );
+        references.check_opt("level_id", &stop_point.level_id, &collections.levels);
+        references.check_comments(stop_point, &collections.comments);
+    }
+    for stop_location in collections.stop_locations.values() {
+        let mut references = References::new(&mut errors, "StopLocation", &stop_location.id);
+        if let Some(parent_id) = &stop_location.parent_id {
+            let exists = collections.stop_points.contains_id(parent_id)
+                || collections.stop_areas.contains_id(parent_id);
+            references.check_exists("parent_id", parent_id, exists);
+        }
+        references.check_opt(
+            "equipment_id",
+            &stop_location.equipment_id,
+            &collections.equipments,
+        );
+        references.check_opt("level_id", &stop_location.level_id, &collections.levels);
+        references.check_comments(stop_location, &collections.comments);
+    }
+    for transfer in collections.transfers.values() {
+        let id = format!("{}-{}", transfer.from_stop_id, transfer.to_stop_id);
+        let mut references = References::new(&mut errors, "Transfer", &id);
+        references.check(
+            "from_stop_id",
+            &transfer.from_stop_id,
+            &collections.stop_points,
+        );
+        references.check("to_stop_id", &transfer.to_stop_id, &collections.stop_points);
+        references.check_opt(
+            "equipment_id",
+            &transfer.equipment_id,
+            &collections.equipments,
+        );
+    }
+    let stop_exists = |stop_id: &str| {
+        collections.stop_points.contains_id(stop_id)
+            || collections.stop_locations.contains_id(stop_id)
+    };
+    for pathway in collections.pathways.values() {
+        let mut references = References::new(&mut errors, "Pathway", &pathway.id);
+        let from_stop_id = &pathway.from_stop_id;
+        references.check_exists("from_stop_id", from_stop_id, stop_exists(from_stop_id));
+        let to_stop_id = &pathway.to_stop_id;
+        references.check_exists("to_stop_id", to_stop_id, stop_exists(to_stop_id));
+    }
+    for admin_station in collections.admin_stations.values() {
+        let mut references = References::new(&mut errors, "AdminStation", &admin_station.admin_id);
+        references.check("stop_id", &admin_station.stop_id, &collections.stop_areas);
+    }
+    for frequency in collections.frequencies.values() {
+        let vj_id = &frequency.vehicle_journey_id;
+        let mut references = References::new(&mut errors, "Frequency", vj_id);
+        references.check("vehicle_journey_id", vj_id, &collections.vehicle_journeys);
+    }
+    let vehicle_journeys: HashMap<Idx<VehicleJourney>, &VehicleJourney> =
+        collections.vehicle_journeys.iter().collect();
+    let mut stop_time_keys: BTreeSet<(String, u32)> = collections
+        .stop_time_headsigns
+        .keys()
+        .chain(collections.stop_time_comments.keys())
+        .chain(collections.stop_time_pickup_booking_rules.keys())
+        .chain(collections.stop_time_drop_off_booking_rules.keys())
+        .cloned()
+        .collect();
+    for (vj_idx, sequence) in collections.stop_time_ids.keys() {
+        match vehicle_journeys.get(vj_idx) {
+            Some(vj) => {
+                stop_time_keys.insert((vj.id.clone(), *sequence));
+            }
+            None => {
+                // the vehicle journey is referenced by index, report the sequence instead
+                let id = sequence.to_string();
+                References::new(&mut errors, "StopTime", &id).check_exists(
+                    "vehicle_journey_idx",
+                    &id,
+                    false,
+                );
+            }
+        }
+    }
+    for (vj_id, sequence) in &stop_time_keys {
+        let id = format!("{}-{}", vj_id, sequence);
+        let mut references = References::new(&mut errors, "StopTime", &id);
+        match collections.vehicle_journeys.get(vj_id) {
+            Some(vj) => {
+                let exists = vj.stop_times.iter().any(|st| st.sequence == *sequence);
+                references.check_exists("sequence", &sequence.to_string(), exists);
+            }
+            None => references.check_exists("vehicle_journey_id", vj_id, false),
+        }
+        if let Some(comment_id) = collections
+            .stop_time_comments
+            .get(&(vj_id.clone(), *sequence))
+        {
+            references.check("comment_id", comment_id, &collections.comments);
+        }
+        let stop_time = (vj_id.clone(), *sequence);
+        for &(field, booking_rule_ids) in &[
+            (
+                "pickup_booking_rule_id",
+                &collections.stop_time_pickup_booking_rules,
+            ),
+            (
+                "drop_off_booking_rule_id",
+                &collections.stop_time_drop_off_booking_rules,
+            ),
+        ] {
+            if let Some(booking_rule_id) = booking_rule_ids.get(&stop_time) {
+                references.check(field, booking_rule_id, &collections.booking_rules);
+            }
+        }
+    }
+    for ticket_use in collections.ticket_uses.values() {
+        let mut references = References::new(&mut errors, "TicketUse", &ticket_use.id);
+        references.check("ticket_id", &ticket_use.ticket_id, &collections.tickets);
+    }
+    for ticket_price in collections.ticket_prices.values() {
+        let ticket_id = &ticket_price.ticket_id;
+        let mut references = References::new(&mut errors, "TicketPrice", ticket_id);
+        references.check("ticket_id", ticket_id, &collections.tickets);
+    }
+    for perimeter in collections.ticket_use_perimeters.values() {
+        let ticket_use_id = &perimeter.ticket_use_id;
+        let mut references = References::new(&mut errors, "TicketUsePerimeter", ticket_use_id);
+        references.check("ticket_use_id", ticket_use_id, &collections.ticket_uses);
+        // only networks and lines can be part of a perimeter
+        let exists = match perimeter.object_type {
+            ObjectType::Network => collections.networks.contains_id(&perimeter.object_id),
+            ObjectType::Line => collections.lines.contains_id(&perimeter.object_id),
+            _ => false,
+        };
+        references.check_exists("object_id", &perimeter.object_id, exists);
+    }
+    let fare_zone_ids: HashSet<&str> = collections
+        .stop_points
+        .values()
+        .filter_map(|stop_point| stop_point.fare_zone_id.as_deref())
+        .collect();
+    for restriction in collections.ticket_use_restrictions.values() {
+        let ticket_use_id = &restriction.ticket_use_id;
+        let mut references = References::new(&mut errors, "TicketUseRestriction", ticket_use_id);
+        references.check("ticket_use_id", ticket_use_id, &collections.ticket_uses);
+        for &(field, id) in &[
+            ("use_origin", &restriction.use_origin),
+            ("use_destination", &restriction.use_destination),
+        ] {
+            let exists = match restriction.restriction_type {
+                RestrictionType::Zone => fare_zone_ids.contains(id.as_str()),
+                RestrictionType::OriginDestination => collections.stop_areas.contains_id(id),
+            };
+            references.check_exists(field, id, exists);
+        }
+    }
+    for grid_exception_date in collections.grid_exception_dates.values() {
+        let grid_calendar_id = &grid_exception_date.grid_calendar_id;
+        let mut references = References::new(&mut errors, "GridExceptionDate", grid_calendar_id);
+        references.check(
+            "grid_calendar_id",
+            grid_calendar_id,
+            &collections.grid_calendars,
+        );
+    }
+    for grid_period in collections.grid_periods.values() {
+        let grid_calendar_id = &grid_period.grid_calendar_id;
+        let mut references = References::new(&mut errors, "GridPeriod", grid_calendar_id);
+        references.check(
+            "grid_calendar_id",
+            grid_calendar_id,
+            &collections.grid_calendars,
+        );
+    }
+    for grid_rel_calendar_line in collections.grid_rel_calendar_line.values() {
+        let grid_calendar_id = &grid_rel_calendar_line.grid_calendar_id;
+        let mut references = References::new(&mut errors, "GridRelCalendarLine", grid_calendar_id);
+        references.check(
+            "grid_calendar_id",
+            grid_calendar_id,
+            &collections.grid_calendars,
+        );
+        // the line can be given by its external code instead
+        if !grid_rel_calendar_line.line_id.is_empty() {
+            references.check(
+                "line_id",
+                &grid_rel_calendar_line.line_id,
+                &collections.lines,
+            );
+        }
+    }
+    errors
+}
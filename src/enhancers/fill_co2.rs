@@ -35,9 +35,9 @@ lazy_static::lazy_static! {
     };
 }
 
-/// Physical mode should contains CO2 emissions. If the values are not present
-/// in the NTFS, some default values will be used.
-pub fn fill_co2(collections: &mut Collections) {
+/// Fill the missing CO2 emissions of the physical modes with the default
+/// values. Provided values are never overridden.
+pub fn enrich_co2_emissions(collections: &mut Collections) {
     let mut physical_modes = collections.physical_modes.take();
     for physical_mode in &mut physical_modes {
         if physical_mode.co2_emission.is_none() {
@@ -45,6 +45,12 @@ pub fn fill_co2(collections: &mut Collections) {
         }
     }
     collections.physical_modes = CollectionWithId::new(physical_modes).unwrap();
+}
+
+/// Physical mode should contains CO2 emissions. If the values are not present
+/// in the NTFS, some default values will be used.
+pub fn fill_co2(collections: &mut Collections) {
+    enrich_co2_emissions(collections);
     // Add fallback modes
     for &fallback_mode in &[
         model::BIKE_PHYSICAL_MODE,
@@ -133,4 +139,35 @@ mod test {
             .unwrap();
         assert_relative_eq!(car_mode.co2_emission.unwrap(), 184.0f32);
     }
+
+    #[test]
+    fn enrich_without_fallback_modes() {
+        let mut collections = Collections::default();
+        collections.physical_modes = CollectionWithId::new(vec![
+            PhysicalMode {
+                id: String::from(model::BUS_PHYSICAL_MODE),
+                name: String::from("Bus"),
+                ..Default::default()
+            },
+            PhysicalMode {
+                id: String::from(model::METRO_PHYSICAL_MODE),
+                name: String::from("Metro"),
+                co2_emission: Some(1.5f32),
+            },
+        ])
+        .unwrap();
+        collections.enrich_co2_emissions();
+
+        assert_eq!(2, collections.physical_modes.len());
+        let bus_mode = collections
+            .physical_modes
+            .get(model::BUS_PHYSICAL_MODE)
+            .unwrap();
+        assert_relative_eq!(bus_mode.co2_emission.unwrap(), 132f32);
+        let metro_mode = collections
+            .physical_modes
+            .get(model::METRO_PHYSICAL_MODE)
+            .unwrap();
+        assert_relative_eq!(metro_mode.co2_emission.unwrap(), 1.5f32);
+    }
 }
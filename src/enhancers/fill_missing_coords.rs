@@ -0,0 +1,124 @@
+use crate::{
+    model::{self, Collections},
+    objects::{Coord, StopPoint},
+};
+use std::collections::HashMap;
+use typed_index_collection::CollectionWithId;
+
+/// Fill the coordinates of the stop points without valid geolocation with
+/// the ones returned by `f`, and propagate them to their stop area if it
+/// contains no other stop point.
+pub fn fill_missing_coords<F>(collections: &mut Collections, f: F)
+where
+    F: Fn(&StopPoint) -> Option<Coord>,
+{
+    let mut filled_coords = HashMap::new();
+    let mut stop_points = collections.stop_points.take();
+    for stop_point in stop_points
+        .iter_mut()
+        .filter(|sp| !model::is_valid_coord(&sp.coord))
+    {
+        if let Some(coord) = f(stop_point) {
+            stop_point.coord = coord;
+            filled_coords.insert(stop_point.stop_area_id.clone(), coord);
+        }
+    }
+    collections.stop_points = CollectionWithId::new(stop_points).unwrap();
+    let mut nb_stop_points_by_stop_area: HashMap<&str, usize> = HashMap::new();
+    for stop_point in collections.stop_points.values() {
+        *nb_stop_points_by_stop_area
+            .entry(stop_point.stop_area_id.as_str())
+            .or_default() += 1;
+    }
+    let single_point_stop_areas: Vec<(String, Coord)> = filled_coords
+        .into_iter()
+        .filter(|(stop_area_id, _)| {
+            nb_stop_points_by_stop_area.get(stop_area_id.as_str()) == Some(&1)
+        })
+        .collect();
+    for (stop_area_id, coord) in single_point_stop_areas {
+        if let Some(mut stop_area) = collections.stop_areas.get_mut(&stop_area_id) {
+            stop_area.coord = coord;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::StopArea;
+    use pretty_assertions::assert_eq;
+
+    fn stop_point(id: &str, stop_area_id: &str, lon: f64, lat: f64) -> StopPoint {
+        StopPoint {
+            id: id.into(),
+            stop_area_id: stop_area_id.into(),
+            coord: Coord { lon, lat },
+            ..Default::default()
+        }
+    }
+
+    fn stop_area(id: &str) -> StopArea {
+        StopArea {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fill_invalid_coords() {
+        let mut collections = Collections {
+            stop_points: CollectionWithId::new(vec![
+                stop_point("sp1", "sa1", 2.37, 48.84),
+                stop_point("sp2", "sa2", 0.0, 0.0),
+                stop_point("sp3", "sa3", 200.0, 48.84),
+                stop_point("sp4", "sa3", 2.38, 48.85),
+                stop_point("sp5", "sa4", 0.0, 0.0),
+            ])
+            .unwrap(),
+            stop_areas: CollectionWithId::new(vec![
+                stop_area("sa1"),
+                stop_area("sa2"),
+                stop_area("sa3"),
+                stop_area("sa4"),
+            ])
+            .unwrap(),
+            ..Default::default()
+        };
+        let called = std::cell::RefCell::new(Vec::new());
+        collections.fill_missing_coords(|stop_point| {
+            called.borrow_mut().push(stop_point.id.clone());
+            match stop_point.id.as_str() {
+                "sp2" => Some(Coord {
+                    lon: 2.35,
+                    lat: 48.85,
+                }),
+                "sp3" => Some(Coord {
+                    lon: 2.36,
+                    lat: 48.86,
+                }),
+                _ => None,
+            }
+        });
+
+        assert_eq!(vec!["sp2", "sp3", "sp5"], *called.borrow());
+        let coord = |id: &str| {
+            let coord = collections.stop_points.get(id).unwrap().coord;
+            (coord.lon, coord.lat)
+        };
+        assert_eq!((2.37, 48.84), coord("sp1"));
+        assert_eq!((2.35, 48.85), coord("sp2"));
+        assert_eq!((2.36, 48.86), coord("sp3"));
+        assert_eq!((0.0, 0.0), coord("sp5"));
+        let sa_coord = |id: &str| {
+            let coord = collections.stop_areas.get(id).unwrap().coord;
+            (coord.lon, coord.lat)
+        };
+        // sa2 only contains sp2
+        assert_eq!((2.35, 48.85), sa_coord("sa2"));
+        // sa3 contains several stop points
+        assert_eq!((0.0, 0.0), sa_coord("sa3"));
+        // nothing filled for sp5
+        assert_eq!((0.0, 0.0), sa_coord("sa4"));
+    }
+}
@@ -2,6 +2,8 @@
 
 mod adjust_lines_names;
 mod fill_co2;
+mod fill_missing_coords;
 
 pub(crate) use adjust_lines_names::adjust_lines_names;
-pub(crate) use fill_co2::fill_co2;
+pub(crate) use fill_co2::{enrich_co2_emissions, fill_co2};
+pub(crate) use fill_missing_coords::fill_missing_coords;
@@ -15,24 +15,29 @@
 //! [GTFS](https://gtfs.org/reference/static) format management.
 
 mod read;
+mod realtime;
 mod write;
 
+pub use read::{gtfs_fares_to_ntfs_fares, NtfsFares};
+pub use realtime::read_vehicle_positions;
+
 use crate::{
     calendars::{manage_calendars, write_calendar_dates},
     gtfs::read::EquipmentList,
     model::{Collections, Model},
     objects::{self, Availability, Contributor, Dataset, StopPoint, StopType, Time},
-    read_utils,
+    read_utils::{self, CsvConfig},
     utils::*,
-    validity_period, AddPrefix, PrefixConfiguration, Result,
+    validity_period, AddPrefix, DefaultIdGenerator, IdGenerator, PrefixConfiguration, Result,
 };
 use chrono_tz::Tz;
 use derivative::Derivative;
 use failure::ResultExt;
 use log::info;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, fmt, path::Path};
-use typed_index_collection::{CollectionWithId, Idx};
+use typed_index_collection::{Collection, CollectionWithId, Idx};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 struct Agency {
@@ -50,6 +55,8 @@ struct Agency {
     phone: Option<String>,
     #[serde(rename = "agency_email")]
     email: Option<String>,
+    #[serde(rename = "agency_fare_url")]
+    fare_url: Option<String>,
 }
 
 impl<'a> From<&'a objects::Network> for Agency {
@@ -64,7 +71,8 @@ impl<'a> From<&'a objects::Network> for Agency {
             timezone: obj.timezone.unwrap_or(chrono_tz::Europe::Paris),
             lang: obj.lang.clone(),
             phone: obj.phone.clone(),
-            email: None,
+            email: obj.email.clone(),
+            fare_url: obj.fare_url.clone(),
         }
     }
 }
@@ -197,12 +205,18 @@ struct StopTime {
     drop_off_type: u8,
     local_zone_id: Option<u16>,
     stop_headsign: Option<String>,
+    #[serde(default, deserialize_with = "de_with_invalid_option", skip_serializing)]
+    shape_dist_traveled: Option<f64>,
     #[serde(
         deserialize_with = "de_from_u8_with_true_default",
         serialize_with = "ser_from_bool",
         default = "default_true_bool"
     )]
     timepoint: bool,
+    #[serde(default, skip_serializing)]
+    pickup_booking_rule_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    drop_off_booking_rule_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Derivative, PartialEq, Clone)]
@@ -219,6 +233,20 @@ enum TransferType {
     NotPossible,
 }
 
+/// Metadata of the feed, as described in GTFS `feed_info.txt`
+#[derive(Deserialize, Debug, Default, PartialEq, Clone)]
+struct FeedInfo {
+    feed_publisher_name: Option<String>,
+    feed_publisher_url: Option<String>,
+    feed_lang: Option<String>,
+    default_lang: Option<String>,
+    feed_start_date: Option<String>,
+    feed_end_date: Option<String>,
+    feed_version: Option<String>,
+    feed_contact_email: Option<String>,
+    feed_contact_url: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 struct Transfer {
     #[serde(deserialize_with = "de_without_slashes")]
@@ -253,6 +281,75 @@ struct Shape {
     sequence: u32,
 }
 
+/// A fare class, as described in GTFS `fare_attributes.txt`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FareAttribute {
+    /// Identifier of the fare class
+    #[serde(rename = "fare_id")]
+    pub id: String,
+    /// Fare price, in the unit specified by `currency`
+    #[serde(deserialize_with = "de_positive_decimal")]
+    pub price: Decimal,
+    /// Currency of the price (ISO 4217 alphabetical code)
+    #[serde(rename = "currency_type", deserialize_with = "de_currency_code")]
+    pub currency: String,
+    /// `0` if the fare is paid on board, `1` if it must be paid before boarding
+    pub payment_method: u8,
+    /// Number of transfers permitted on this fare, unlimited if empty
+    pub transfers: Option<u32>,
+    /// Agency for the fare, required when there are several agencies
+    pub agency_id: Option<String>,
+    /// Length of time in seconds before a transfer expires
+    pub transfer_duration: Option<u32>,
+}
+
+impl AddPrefix for FareAttribute {
+    fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.referential_prefix(self.id.as_str());
+        self.agency_id = self
+            .agency_id
+            .take()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+    }
+}
+
+/// A rule applying a fare class to itineraries, as described in GTFS `fare_rules.txt`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FareRule {
+    /// Identifier of the fare class the rule applies to
+    pub fare_id: String,
+    /// GTFS route associated with the fare class
+    pub route_id: Option<String>,
+    /// Origin zone (`zone_id` of a stop)
+    pub origin_id: Option<String>,
+    /// Destination zone (`zone_id` of a stop)
+    pub destination_id: Option<String>,
+    /// Zone that an itinerary must pass through
+    pub contains_id: Option<String>,
+}
+
+impl AddPrefix for FareRule {
+    fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.fare_id = prefix_conf.referential_prefix(self.fare_id.as_str());
+        self.route_id = self
+            .route_id
+            .take()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+        self.origin_id = self
+            .origin_id
+            .take()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+        self.destination_id = self
+            .destination_id
+            .take()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+        self.contains_id = self
+            .contains_id
+            .take()
+            .map(|id| prefix_conf.referential_prefix(id.as_str()));
+    }
+}
+
 ///parameters consolidation
 #[derive(Default)]
 pub struct Configuration {
@@ -272,9 +369,19 @@ pub struct Configuration {
     /// Else we group the routes by `agency_id` and `route_short_name`
     /// (or `route_long_name` if the short name is empty) and create a `Line` for each group.
     pub read_as_line: bool,
+    /// CO2 emissions (in gCO2/km) by physical mode identifier, overriding
+    /// the default values of the created physical modes.
+    pub co2_emissions: BTreeMap<String, f32>,
+    /// CSV dialect of the files. The GTFS requires comma separated files,
+    /// but another dialect makes it possible to read malformed files.
+    pub csv_config: CsvConfig,
 }
 
-fn read_file_handler<H>(file_handler: &mut H, configuration: Configuration) -> Result<Model>
+fn read_file_handler<H>(
+    file_handler: &mut H,
+    configuration: Configuration,
+    id_generator: &dyn IdGenerator,
+) -> Result<Model>
 where
     for<'a> &'a mut H: read_utils::FileHandler,
 {
@@ -289,20 +396,31 @@ where
         on_demand_transport,
         on_demand_transport_comment,
         read_as_line,
+        co2_emissions,
+        // already used to build the file handler
+        csv_config: _,
     } = configuration;
 
     manage_calendars(file_handler, &mut collections)?;
     validity_period::compute_dataset_validity_period(&mut dataset, &collections.calendars)?;
+    if dataset.system.is_none() {
+        dataset.system = Some("gtfs".to_string());
+    }
 
     collections.contributors = CollectionWithId::from(contributor);
     collections.datasets = CollectionWithId::from(dataset);
     collections.feed_infos = feed_infos;
+    read::manage_feed_info(&mut collections, file_handler)?;
 
     let (networks, companies) = read::read_agency(file_handler)?;
     collections.networks = networks;
     collections.companies = companies;
-    let (stop_areas, stop_points, stop_locations) =
-        read::read_stops(file_handler, &mut collections.comments, &mut equipments)?;
+    let (stop_areas, stop_points, stop_locations) = read::read_stops(
+        file_handler,
+        &mut collections.comments,
+        &mut equipments,
+        id_generator,
+    )?;
     collections.transfers = read::read_transfers(file_handler, &stop_points, &stop_areas)?;
     collections.stop_areas = stop_areas;
     collections.stop_points = stop_points;
@@ -311,6 +429,7 @@ where
     read::manage_shapes(&mut collections, file_handler)?;
 
     read::read_routes(file_handler, &mut collections, read_as_line)?;
+    read::override_co2_emissions(&mut collections, &co2_emissions);
     collections.equipments = CollectionWithId::new(equipments.into_equipments())?;
     read::manage_stop_times(
         &mut collections,
@@ -318,16 +437,37 @@ where
         on_demand_transport,
         on_demand_transport_comment,
     )?;
-    read::manage_frequencies(&mut collections, file_handler)?;
+    read::manage_frequencies(&mut collections, file_handler, id_generator)?;
     read::manage_pathways(&mut collections, file_handler)?;
+    read::manage_areas(&mut collections, file_handler, id_generator)?;
     collections.levels = read_utils::read_opt_collection(file_handler, "levels.txt")?;
+    let (mut fare_attributes, mut fare_rules) = read::read_fares(file_handler)?;
 
     //add prefixes
     if let Some(prefix_conf) = prefix_conf {
         collections.prefix(&prefix_conf);
+        fare_attributes
+            .iter_mut()
+            .for_each(|fare| fare.prefix(&prefix_conf));
+        fare_rules
+            .iter_mut()
+            .for_each(|rule| rule.prefix(&prefix_conf));
     }
 
     collections.calendar_deduplication();
+    let model = Model::new(collections)?;
+    if fare_attributes.is_empty() {
+        return Ok(model);
+    }
+    // the fares need the lines and networks kept by the model
+    let (tickets, ticket_prices, ticket_uses, ticket_use_perimeters, ticket_use_restrictions) =
+        gtfs_fares_to_ntfs_fares(fare_attributes, fare_rules, &model)?;
+    let mut collections = model.into_collections();
+    collections.tickets = CollectionWithId::new(tickets)?;
+    collections.ticket_prices = Collection::new(ticket_prices);
+    collections.ticket_uses = CollectionWithId::new(ticket_uses)?;
+    collections.ticket_use_perimeters = Collection::new(ticket_use_perimeters);
+    collections.ticket_use_restrictions = Collection::new(ticket_use_restrictions);
     Model::new(collections)
 }
 
@@ -378,15 +518,33 @@ pub fn read<P: AsRef<Path>>(p: P) -> Result<Model> {
 }
 
 /// Structure to configure the GTFS reading
-#[derive(Default)]
 pub struct Reader {
     configuration: Configuration,
+    id_generator: Box<dyn IdGenerator>,
+}
+
+impl Default for Reader {
+    fn default() -> Self {
+        Self::new(Configuration::default())
+    }
 }
 
 impl Reader {
     /// Build a Reader with a custom configuration
     pub fn new(configuration: Configuration) -> Self {
-        Self { configuration }
+        Self {
+            configuration,
+            id_generator: Box::new(DefaultIdGenerator),
+        }
+    }
+
+    /// Use another generation of the identifiers of the objects created by
+    /// the reader (stop areas of the stop points without parent station,
+    /// trips generated from the frequencies, ...), [DefaultIdGenerator] by
+    /// default.
+    pub fn with_id_generator(mut self, id_generator: impl IdGenerator + 'static) -> Self {
+        self.id_generator = Box::new(id_generator);
+        self
     }
 
     /// Imports a `Model` from the
@@ -418,15 +576,25 @@ impl Reader {
     /// [GTFS](https://gtfs.org/reference/static).
     pub fn parse_zip(self, path: impl AsRef<Path>) -> Result<Model> {
         let reader = std::fs::File::open(path.as_ref())?;
-        let mut file_handler = read_utils::ZipHandler::new(reader, path)?;
-        read_file_handler(&mut file_handler, self.configuration)
+        let mut file_handler = read_utils::ZipHandler::new(reader, path)?
+            .with_csv_config(self.configuration.csv_config);
+        read_file_handler(
+            &mut file_handler,
+            self.configuration,
+            self.id_generator.as_ref(),
+        )
     }
 
     /// Imports a `Model` from the [GTFS](https://gtfs.org/reference/static)
     /// files in the `path` directory.
     pub fn parse_dir(self, path: impl AsRef<Path>) -> Result<Model> {
-        let mut file_handler = read_utils::PathFileHandler::new(path.as_ref().to_path_buf());
-        read_file_handler(&mut file_handler, self.configuration)
+        let mut file_handler = read_utils::PathFileHandler::new(path.as_ref().to_path_buf())
+            .with_csv_config(self.configuration.csv_config);
+        read_file_handler(
+            &mut file_handler,
+            self.configuration,
+            self.id_generator.as_ref(),
+        )
     }
 
     /// Imports a `Model` from an object implementing `Read` and `Seek` and containing the
@@ -448,8 +616,13 @@ impl Reader {
     where
         R: std::io::Seek + std::io::Read,
     {
-        let mut file_handler = read_utils::ZipHandler::new(reader, source_name)?;
-        read_file_handler(&mut file_handler, self.configuration)
+        let mut file_handler = read_utils::ZipHandler::new(reader, source_name)?
+            .with_csv_config(self.configuration.csv_config);
+        read_file_handler(
+            &mut file_handler,
+            self.configuration,
+            self.id_generator.as_ref(),
+        )
     }
 }
 
@@ -517,11 +690,13 @@ fn remove_stop_zones(model: Model) -> Collections {
         .copied()
         .collect();
 
+    let stop_time_ids = collections.take_stop_time_ids();
     collections.vehicle_journeys.retain(|vj| {
         vj.stop_times
             .iter()
             .all(|st| stop_point_ids.contains(&st.stop_point_idx))
     });
+    collections.restore_stop_time_ids(stop_time_ids);
 
     collections
 }
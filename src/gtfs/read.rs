@@ -13,19 +13,22 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>
 
 use super::{
-    Agency, DirectionType, Route, RouteType, Shape, Stop, StopLocationType, StopTime, Transfer,
-    TransferType, Trip,
+    Agency, DirectionType, FareAttribute, FareRule, FeedInfo, Route, RouteType, Shape, Stop,
+    StopLocationType, StopTime, Transfer, TransferType, Trip,
 };
 use crate::{
-    model::Collections,
+    model::{Collections, Model},
     objects::{
-        self, Availability, CommentLinksT, Coord, KeysValues, Pathway, PropertiesMap, StopLocation,
-        StopPoint, StopTime as NtfsStopTime, StopTimePrecision, StopType, Time, TransportType,
-        VehicleJourney,
+        self, Availability, CommentLinksT, Coord, KeysValues, ObjectType, Pathway, PerimeterAction,
+        PropertiesMap, RestrictionType, StopLocation, StopPoint, StopTime as NtfsStopTime,
+        StopTimePrecision, StopType, Ticket, TicketPrice, TicketUse, TicketUsePerimeter,
+        TicketUseRestriction, Time, TransportType, VehicleJourney,
+    },
+    read_utils::{
+        read_collection, read_objects, read_objects_loose, read_opt_collection, FileHandler,
     },
-    read_utils::{read_collection, read_objects, read_objects_loose, FileHandler},
     utils::*,
-    Result,
+    IdGenerator, Result,
 };
 use derivative::Derivative;
 use failure::{bail, format_err, Error};
@@ -68,6 +71,8 @@ impl From<Agency> for objects::Network {
             phone: agency.phone,
             address: None,
             sort_order: None,
+            email: agency.email,
+            fare_url: agency.fare_url,
         }
     }
 }
@@ -273,10 +278,13 @@ impl Route {
         read_as_line: bool,
         idx: Idx<Route>,
     ) -> (Option<String>, String, Option<Idx<Route>>) {
+        // Routes without any name are never grouped together
         let name = if !self.short_name.is_empty() {
             self.short_name.clone()
-        } else {
+        } else if !self.long_name.is_empty() {
             self.long_name.clone()
+        } else {
+            self.id.clone()
         };
         let key = if read_as_line { Some(idx) } else { None };
         (self.agency_id.clone(), name, key)
@@ -324,6 +332,7 @@ impl Trip {
             geometry_id: self.shape_id.clone(),
             stop_times: vec![],
             journey_pattern_id: None,
+            occupancy: None,
         })
     }
 }
@@ -374,6 +383,7 @@ where
     let mut headsigns = HashMap::new();
     let mut tmp_vjs = BTreeMap::new();
     let stop_times = read_objects::<_, StopTime>(file_handler, file_name, true)?;
+    collections.booking_rules = read_opt_collection(file_handler, "booking_rules.txt")?;
 
     for mut stop_time in stop_times {
         if let Some(vj_idx) = collections.vehicle_journeys.get_idx(&stop_time.trip_id) {
@@ -400,10 +410,10 @@ where
 
     for (vj_idx, mut stop_times) in tmp_vjs {
         stop_times.sort_unstable_by_key(|st| st.stop_sequence);
-        let st_values = interpolate_undefined_stop_times(
-            &collections.vehicle_journeys[vj_idx].id,
-            &stop_times,
-        )?;
+        let st_values = skip_error_and_log!(
+            interpolate_undefined_stop_times(&collections.vehicle_journeys[vj_idx].id, &stop_times),
+            tracing::Level::WARN
+        );
 
         let company_idx = collections
             .companies
@@ -417,6 +427,7 @@ where
                     (true, true) => Some(StopTimePrecision::Estimated),
                 };
 
+                manage_booking_rules(collections, vj_idx, stop_time);
                 if let Some(message) = on_demand_transport_comment.as_ref() {
                     if stop_time.pickup_type == 2 || stop_time.drop_off_type == 2 {
                         if let Some(company_idx) = company_idx {
@@ -460,22 +471,50 @@ where
 
 fn ventilate_stop_times(
     undefined_stop_times: &[&StopTime],
-    before: &StopTimesValues,
-    after: &StopTimesValues,
+    before: (&StopTime, &StopTimesValues),
+    after: (&StopTime, &StopTimesValues),
 ) -> Vec<StopTimesValues> {
+    let (before_st, before) = before;
+    let (after_st, after) = after;
     let duration = after.arrival_time - before.departure_time;
-    let step = duration / (undefined_stop_times.len() + 1) as u32;
-    let mut res = vec![];
-    for idx in 0..undefined_stop_times.len() {
-        let num = idx as u32 + 1u32;
-        let time = before.departure_time + objects::Time::new(0, 0, num * step.total_seconds());
-        res.push(StopTimesValues {
-            departure_time: time,
-            arrival_time: time,
-            datetime_estimated: true,
-        });
-    }
-    res
+    // the times are interpolated with the distances traveled when they are
+    // all defined and consistent, else with the number of stops
+    let distances: Option<Vec<f64>> = undefined_stop_times
+        .iter()
+        .map(|st| st.shape_dist_traveled)
+        .collect();
+    let ratios: Vec<f64> = match (
+        before_st.shape_dist_traveled,
+        distances,
+        after_st.shape_dist_traveled,
+    ) {
+        (Some(before_dist), Some(distances), Some(after_dist))
+            if after_dist > before_dist
+                && distances
+                    .iter()
+                    .all(|dist| *dist >= before_dist && *dist <= after_dist) =>
+        {
+            distances
+                .iter()
+                .map(|dist| (dist - before_dist) / (after_dist - before_dist))
+                .collect()
+        }
+        _ => (1..=undefined_stop_times.len())
+            .map(|num| num as f64 / (undefined_stop_times.len() + 1) as f64)
+            .collect(),
+    };
+    ratios
+        .into_iter()
+        .map(|ratio| {
+            let offset = (f64::from(duration.total_seconds()) * ratio) as u32;
+            let time = before.departure_time + objects::Time::new(0, 0, offset);
+            StopTimesValues {
+                departure_time: time,
+                arrival_time: time,
+                datetime_estimated: true,
+            }
+        })
+        .collect()
 }
 
 // Temporary struct used by the interpolation process
@@ -493,6 +532,7 @@ fn interpolate_undefined_stop_times(
     stop_times: &[StopTime],
 ) -> Result<Vec<StopTimesValues>> {
     let mut undefined_stops_bulk = Vec::with_capacity(0);
+    let mut last_defined_stop_time = None;
     let mut res = vec![];
     for st in stop_times {
         // if only one in departure/arrival value is defined, we set it to the other value
@@ -519,15 +559,13 @@ fn interpolate_undefined_stop_times(
         };
 
         if !undefined_stops_bulk.is_empty() {
-            let values = ventilate_stop_times(
-                &undefined_stops_bulk,
-                res.last().ok_or_else(|| format_err!("the first stop time of the vj '{}' has no departure/arrival, the stop_times.txt file is not valid", vj_id))?,
-                &st_value,
-            );
+            let before = last_defined_stop_time.zip(res.last()).ok_or_else(|| format_err!("the first stop time of the vj '{}' has no departure/arrival, the stop_times.txt file is not valid", vj_id))?;
+            let values = ventilate_stop_times(&undefined_stops_bulk, before, (st, &st_value));
             res.extend(values);
             undefined_stops_bulk.clear();
         }
         res.push(st_value);
+        last_defined_stop_time = Some(st);
     }
 
     if !undefined_stops_bulk.is_empty() {
@@ -639,6 +677,63 @@ fn manage_odt_comment_from_stop_time(
         // Ok to unwrap since we already tested for existence of the identifier
         collections.comments.push(comment).unwrap();
     }
+    link_comment_to_stop_time(collections, comment_id, vj_idx, stop_time);
+}
+
+// The booking rule referenced by `field` of a stop time, if it exists
+fn find_booking_rule(
+    booking_rules: &CollectionWithId<objects::BookingRule>,
+    field: &str,
+    booking_rule_id: &Option<String>,
+) -> Option<String> {
+    let booking_rule_id = booking_rule_id.as_ref()?;
+    if booking_rules.contains_id(booking_rule_id) {
+        Some(booking_rule_id.clone())
+    } else {
+        warn!(
+            "Problem reading \"stop_times.txt\": {}={:?} not found",
+            field, booking_rule_id
+        );
+        None
+    }
+}
+
+// Reference the GTFS-Flex booking rules of the pickup and of the drop off
+fn manage_booking_rules(
+    collections: &mut Collections,
+    vj_idx: Idx<objects::VehicleJourney>,
+    stop_time: &StopTime,
+) {
+    let stop_time_key = (
+        collections.vehicle_journeys[vj_idx].id.to_string(),
+        stop_time.stop_sequence,
+    );
+    if let Some(booking_rule_id) = find_booking_rule(
+        &collections.booking_rules,
+        "pickup_booking_rule_id",
+        &stop_time.pickup_booking_rule_id,
+    ) {
+        collections
+            .stop_time_pickup_booking_rules
+            .insert(stop_time_key.clone(), booking_rule_id);
+    }
+    if let Some(booking_rule_id) = find_booking_rule(
+        &collections.booking_rules,
+        "drop_off_booking_rule_id",
+        &stop_time.drop_off_booking_rule_id,
+    ) {
+        collections
+            .stop_time_drop_off_booking_rules
+            .insert(stop_time_key, booking_rule_id);
+    }
+}
+
+fn link_comment_to_stop_time(
+    collections: &mut Collections,
+    comment_id: String,
+    vj_idx: Idx<objects::VehicleJourney>,
+    stop_time: &StopTime,
+) {
     collections.stop_time_comments.insert(
         (
             collections.vehicle_journeys[vj_idx].id.to_string(),
@@ -647,13 +742,9 @@ fn manage_odt_comment_from_stop_time(
         comment_id,
     );
     let stop_time_id = format!("{}-{}", stop_time.trip_id, stop_time.stop_sequence);
-    collections.stop_time_ids.insert(
-        (
-            collections.vehicle_journeys[vj_idx].id.to_string(),
-            stop_time.stop_sequence,
-        ),
-        stop_time_id,
-    );
+    collections
+        .stop_time_ids
+        .insert((vj_idx, stop_time.stop_sequence), stop_time_id);
 }
 
 #[derive(Default)]
@@ -711,6 +802,7 @@ pub(in crate::gtfs) fn read_stops<H>(
     file_handler: &mut H,
     comments: &mut CollectionWithId<objects::Comment>,
     equipments: &mut EquipmentList,
+    id_generator: &dyn IdGenerator,
 ) -> Result<(
     CollectionWithId<objects::StopArea>,
     CollectionWithId<objects::StopPoint>,
@@ -741,7 +833,8 @@ where
                     tracing::Level::WARN
                 );
                 if stop.parent_station.is_none() {
-                    let stop_area = objects::StopArea::from(stop_point.clone());
+                    let mut stop_area = objects::StopArea::from(stop_point.clone());
+                    stop_area.id = id_generator.stop_area_id(&stop_point.id);
                     stop_point.stop_area_id = stop_area.id.clone();
                     stop_areas.push(stop_area);
                 };
@@ -773,6 +866,148 @@ where
     Ok((stopareas, stoppoints, stoplocations))
 }
 
+/// The metadata of `feed_info.txt` are added to the NTFS feed infos, unless
+/// the key is already defined (e.g. by the configuration). The GTFS dates have
+/// the NTFS format; `default_lang` is renamed `feed_default_lang`.
+pub(in crate::gtfs) fn manage_feed_info<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "feed_info.txt";
+    let mut feed_infos = read_objects::<_, FeedInfo>(file_handler, file, false)?.into_iter();
+    let feed_info = match feed_infos.next() {
+        Some(feed_info) => feed_info,
+        None => return Ok(()),
+    };
+    if feed_infos.next().is_some() {
+        warn!(
+            "{} contains several lines, only the first one is used",
+            file
+        );
+    }
+    let values = vec![
+        ("feed_publisher_name", feed_info.feed_publisher_name),
+        ("feed_publisher_url", feed_info.feed_publisher_url),
+        ("feed_lang", feed_info.feed_lang),
+        ("feed_default_lang", feed_info.default_lang),
+        ("feed_start_date", feed_info.feed_start_date),
+        ("feed_end_date", feed_info.feed_end_date),
+        ("feed_version", feed_info.feed_version),
+        ("feed_contact_email", feed_info.feed_contact_email),
+        ("feed_contact_url", feed_info.feed_contact_url),
+    ];
+    for (key, value) in values {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            collections
+                .feed_infos
+                .entry(key.to_string())
+                .or_insert(value);
+        }
+    }
+    Ok(())
+}
+
+/// The GTFS areas (`areas.txt` and `stop_areas.txt`) are mapped to NTFS stop
+/// areas when possible: an area grouping only stop points without parent
+/// station, none of them belonging to another area, becomes the stop area of
+/// these stop points. The other areas are kept as object codes on the
+/// corresponding stop points or stop areas.
+pub(in crate::gtfs) fn manage_areas<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    id_generator: &dyn IdGenerator,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let areas = read_opt_collection::<_, objects::Area>(file_handler, "areas.txt")?;
+    let file = "stop_areas.txt";
+    let stop_areas2 = read_objects::<_, objects::StopArea2>(file_handler, file, false)?;
+    let mut stop_ids_by_area: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut nb_areas_by_stop: HashMap<String, usize> = HashMap::new();
+    for stop_area2 in stop_areas2 {
+        let area = match areas.get(&stop_area2.area_id) {
+            Some(area) => area,
+            None => {
+                warn!(
+                    "Problem reading {:?}: area_id={:?} not found",
+                    file, stop_area2.area_id
+                );
+                continue;
+            }
+        };
+        if collections.stop_points.get(&stop_area2.stop_id).is_none()
+            && collections.stop_areas.get(&stop_area2.stop_id).is_none()
+        {
+            warn!(
+                "Problem reading {:?}: stop_id={:?} of area {:?} not found",
+                file,
+                stop_area2.stop_id,
+                area.area_name.as_ref().unwrap_or(&area.id)
+            );
+            continue;
+        }
+        let stop_ids = stop_ids_by_area.entry(stop_area2.area_id).or_default();
+        if stop_ids.insert(stop_area2.stop_id.clone()) {
+            *nb_areas_by_stop.entry(stop_area2.stop_id).or_default() += 1;
+        }
+    }
+
+    for (area_id, stop_ids) in stop_ids_by_area {
+        let is_stop_area = collections.stop_areas.get(&area_id).is_none()
+            && stop_ids.iter().all(|stop_id| {
+                nb_areas_by_stop[stop_id] == 1
+                    && collections.stop_points.get(stop_id).map_or(false, |sp| {
+                        sp.stop_area_id == id_generator.stop_area_id(&sp.id)
+                    })
+            });
+        if !is_stop_area {
+            for stop_id in stop_ids {
+                let code = ("gtfs_area_id".to_string(), area_id.clone());
+                if let Some(mut stop_point) = collections.stop_points.get_mut(&stop_id) {
+                    stop_point.codes.insert(code);
+                } else if let Some(mut stop_area) = collections.stop_areas.get_mut(&stop_id) {
+                    stop_area.codes.insert(code);
+                }
+            }
+            continue;
+        }
+        // the stop areas generated for the stop points are replaced by the area
+        let mut generated_stop_area_ids = BTreeSet::new();
+        let mut timezone = None;
+        for stop_id in &stop_ids {
+            let mut stop_point = collections.stop_points.get_mut(stop_id).unwrap();
+            generated_stop_area_ids.insert(std::mem::replace(
+                &mut stop_point.stop_area_id,
+                area_id.clone(),
+            ));
+            timezone = timezone.or(stop_point.timezone);
+        }
+        collections
+            .stop_areas
+            .retain(|stop_area| !generated_stop_area_ids.contains(&stop_area.id));
+        let area = areas.get(&area_id).unwrap();
+        // the coordinates are computed from the stop points by the model
+        collections.stop_areas.push(objects::StopArea {
+            id: area_id.clone(),
+            name: area.area_name.clone().unwrap_or_else(|| area_id.clone()),
+            codes: KeysValues::default(),
+            object_properties: PropertiesMap::default(),
+            comment_links: CommentLinksT::default(),
+            visible: true,
+            coord: Coord::default(),
+            timezone,
+            geometry_id: None,
+            equipment_id: None,
+            level_id: None,
+        })?;
+    }
+    Ok(())
+}
+
 pub(in crate::gtfs) fn manage_pathways<H>(
     collections: &mut Collections,
     file_handler: &mut H,
@@ -951,6 +1186,20 @@ fn get_modes_from_gtfs(
     (commercial_modes, physical_modes)
 }
 
+// Missing values are filled with the default ones by `Model::new`
+pub(in crate::gtfs) fn override_co2_emissions(
+    collections: &mut Collections,
+    co2_emissions: &BTreeMap<String, f32>,
+) {
+    let mut physical_modes = collections.physical_modes.take();
+    for physical_mode in &mut physical_modes {
+        if let Some(co2_emission) = co2_emissions.get(&physical_mode.id) {
+            physical_mode.co2_emission = Some(*co2_emission);
+        }
+    }
+    collections.physical_modes = CollectionWithId::new(physical_modes).unwrap();
+}
+
 fn get_route_with_smallest_name<'a>(routes: &'a [&Route]) -> &'a Route {
     routes.iter().min_by_key(|r| &r.id).unwrap()
 }
@@ -991,6 +1240,20 @@ fn make_lines(
         }
     };
 
+    let line_name = |r: &Route| {
+        if !r.long_name.is_empty() {
+            r.long_name.to_string()
+        } else if !r.short_name.is_empty() {
+            r.short_name.to_string()
+        } else {
+            warn!(
+                "route_id {} has no route_short_name nor route_long_name, using its id as line name",
+                r.id
+            );
+            r.id.to_string()
+        }
+    };
+
     for routes in map_line_routes.values() {
         let r = get_route_with_smallest_name(routes);
 
@@ -1000,7 +1263,7 @@ fn make_lines(
             codes: KeysValues::default(),
             object_properties: PropertiesMap::default(),
             comment_links: CommentLinksT::default(),
-            name: r.long_name.to_string(),
+            name: line_name(r),
             forward_name: None,
             forward_direction: None,
             backward_name: None,
@@ -1184,7 +1447,7 @@ struct Frequency {
     trip_id: String,
     start_time: Time,
     end_time: Time,
-    headway_secs: u32,
+    headway_secs: i64,
     #[serde(default, deserialize_with = "de_with_empty_default")]
     exact_times: FrequencyPrecision,
 }
@@ -1192,6 +1455,7 @@ struct Frequency {
 pub(in crate::gtfs) fn manage_frequencies<H>(
     collections: &mut Collections,
     file_handler: &mut H,
+    id_generator: &dyn IdGenerator,
 ) -> Result<()>
 where
     for<'a> &'a mut H: FileHandler,
@@ -1200,7 +1464,18 @@ where
     let gtfs_frequencies = read_objects::<_, Frequency>(file_handler, file, false)?;
     let mut trip_id_sequence: HashMap<String, u32> = HashMap::new();
     let mut new_vehicle_journeys: Vec<VehicleJourney> = vec![];
+    let mut stop_time_ids_by_trip_id = collections.take_stop_time_ids();
     for frequency in &gtfs_frequencies {
+        let headway_secs = match u32::try_from(frequency.headway_secs) {
+            Ok(headway_secs) if headway_secs > 0 => headway_secs,
+            _ => {
+                warn!(
+                    "frequency for trip {:?} has an invalid headway of {} seconds",
+                    frequency.trip_id, frequency.headway_secs
+                );
+                continue;
+            }
+        };
         if frequency.start_time == frequency.end_time {
             warn!(
                 "frequency for trip {:?} has same start and end time",
@@ -1208,6 +1483,17 @@ where
             );
             continue;
         }
+        // an end time before the start time means that the frequency ends
+        // after midnight, on the next day
+        let end_time = if frequency.end_time < frequency.start_time {
+            warn!(
+                "frequency for trip {:?} ends before it starts, considering it ends the next day",
+                frequency.trip_id
+            );
+            frequency.end_time + Time::new(24, 0, 0)
+        } else {
+            frequency.end_time
+        };
         let datetime_estimated = match frequency.exact_times {
             FrequencyPrecision::Exact => false,
             FrequencyPrecision::Inexact => true,
@@ -1224,7 +1510,7 @@ where
             tracing::Level::WARN
         );
         let mut start_time = frequency.start_time;
-        let mut arrival_time_delta = match corresponding_vj.stop_times.iter().min() {
+        let arrival_time_delta = match corresponding_vj.stop_times.iter().min() {
             None => {
                 warn!(
                     "frequency mapped to trip {:?} with no stop_times",
@@ -1234,27 +1520,27 @@ where
             }
             Some(st) => st.arrival_time,
         };
-        while start_time < frequency.end_time {
+        while start_time < end_time {
             trip_id_sequence
                 .entry(frequency.trip_id.clone())
                 .and_modify(|counter| *counter += 1)
                 .or_insert(0);
-            let generated_trip_id = format!(
-                "{}-{}",
-                frequency.trip_id, trip_id_sequence[&frequency.trip_id]
+            let generated_trip_id = id_generator.frequency_vehicle_journey_id(
+                &frequency.trip_id,
+                trip_id_sequence[&frequency.trip_id],
             );
             // the following handles generated trip starting after midnight, we need to generate a
-            // new service in case the next day is not covered
-            let service_id = if start_time.hours() >= 24 {
-                let nb_days = start_time.hours() / 24;
+            // new service in case the next day is not covered, and to shift the stop times of
+            // the number of days
+            let nb_days = start_time.hours() / 24;
+            let service_id = if nb_days > 0 {
                 let service = collections
                     .calendars
                     .get(&corresponding_vj.service_id)
                     .cloned()
                     .unwrap();
-                let new_service_id = format!("{}:+{}days", service.id, nb_days);
+                let new_service_id = id_generator.shifted_calendar_id(&service.id, nb_days);
                 if !collections.calendars.contains_id(&new_service_id) {
-                    arrival_time_delta = arrival_time_delta + Time::new(24, 0, 0);
                     let new_dates: BTreeSet<_> = service
                         .dates
                         .iter()
@@ -1271,14 +1557,16 @@ where
             } else {
                 corresponding_vj.service_id.clone()
             };
+            let start_time_in_day = start_time - Time::new(24 * nb_days, 0, 0);
             let stop_times: Vec<NtfsStopTime> = corresponding_vj
                 .stop_times
                 .iter()
                 .map(|stop_time| NtfsStopTime {
                     stop_point_idx: stop_time.stop_point_idx,
                     sequence: stop_time.sequence,
-                    arrival_time: stop_time.arrival_time + start_time - arrival_time_delta,
-                    departure_time: stop_time.departure_time + start_time - arrival_time_delta,
+                    arrival_time: stop_time.arrival_time + start_time_in_day - arrival_time_delta,
+                    departure_time: stop_time.departure_time + start_time_in_day
+                        - arrival_time_delta,
                     boarding_duration: stop_time.boarding_duration,
                     alighting_duration: stop_time.alighting_duration,
                     pickup_type: stop_time.pickup_type,
@@ -1288,7 +1576,7 @@ where
                     precision: stop_time.precision.clone(),
                 })
                 .collect();
-            start_time = start_time + Time::new(0, 0, frequency.headway_secs);
+            start_time = start_time + Time::new(0, 0, headway_secs);
             let generated_vj = VehicleJourney {
                 id: generated_trip_id.clone(),
                 service_id,
@@ -1322,24 +1610,230 @@ where
                 })
                 .collect();
             collections.stop_time_comments.extend(stop_time_comments);
-            collections.stop_time_ids.extend(stop_time_ids);
+            stop_time_ids_by_trip_id.extend(stop_time_ids);
+            for booking_rules in &mut [
+                &mut collections.stop_time_pickup_booking_rules,
+                &mut collections.stop_time_drop_off_booking_rules,
+            ] {
+                let generated_booking_rules: Vec<_> = corresponding_vj
+                    .stop_times
+                    .iter()
+                    .filter_map(|stop_time| {
+                        booking_rules
+                            .get(&(frequency.trip_id.clone(), stop_time.sequence))
+                            .map(|booking_rule_id| {
+                                (
+                                    (generated_trip_id.clone(), stop_time.sequence),
+                                    booking_rule_id.clone(),
+                                )
+                            })
+                    })
+                    .collect();
+                booking_rules.extend(generated_booking_rules);
+            }
         }
     }
     let mut vehicle_journeys = collections.vehicle_journeys.take();
     let trip_ids_to_remove: Vec<_> = gtfs_frequencies.iter().map(|f| &f.trip_id).collect();
     vehicle_journeys.retain(|vj| !trip_ids_to_remove.contains(&&vj.id));
     collections
-        .stop_time_ids
+        .stop_time_comments
         .retain(|(vj_id, _), _| !trip_ids_to_remove.contains(&&vj_id));
     collections
-        .stop_time_comments
+        .stop_time_pickup_booking_rules
+        .retain(|(vj_id, _), _| !trip_ids_to_remove.contains(&&vj_id));
+    collections
+        .stop_time_drop_off_booking_rules
         .retain(|(vj_id, _), _| !trip_ids_to_remove.contains(&&vj_id));
 
     vehicle_journeys.append(&mut new_vehicle_journeys);
     collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    // the identifiers of the removed trips are dropped
+    collections.restore_stop_time_ids(stop_time_ids_by_trip_id);
     Ok(())
 }
 
+pub(in crate::gtfs) fn read_fares<H>(
+    file_handler: &mut H,
+) -> Result<(Vec<FareAttribute>, Vec<FareRule>)>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let fare_attributes = read_objects(file_handler, "fare_attributes.txt", false)?;
+    let fare_rules = read_objects(file_handler, "fare_rules.txt", false)?;
+    Ok((fare_attributes, fare_rules))
+}
+
+/// NTFS fare objects generated from GTFS fares
+pub type NtfsFares = (
+    Vec<Ticket>,
+    Vec<TicketPrice>,
+    Vec<TicketUse>,
+    Vec<TicketUsePerimeter>,
+    Vec<TicketUseRestriction>,
+);
+
+// A GTFS route can produce a forward and a backward NTFS route, both on the same line
+fn get_line_id_of_gtfs_route<'a>(model: &'a Model, route_id: &str) -> Option<&'a str> {
+    model
+        .routes
+        .get(route_id)
+        .or_else(|| model.routes.get(&format!("{}_R", route_id)))
+        .map(|route| route.line_id.as_str())
+}
+
+/// Converts GTFS Fares v1 (`fare_attributes.txt` and `fare_rules.txt`) into
+/// the NTFS fare objects.
+///
+/// Each fare class generates a `Ticket`. The rules of a fare are grouped by
+/// origin and destination zones, each group generating a `TicketUse` whose
+/// perimeter is made of the lines of the routes of the group (or of the
+/// network(s) of the fare when no route is given), restricted to the zones
+/// of the group if any. This way, a route is only valid with the zones it is
+/// given with. The `TicketUse` has the identifier of the fare, suffixed with
+/// `:<n>` if the fare has several groups.
+///
+/// The price is valid for the whole validity period of the model.
+pub fn gtfs_fares_to_ntfs_fares(
+    fares: Vec<FareAttribute>,
+    rules: Vec<FareRule>,
+    model: &Model,
+) -> Result<NtfsFares> {
+    let (start_date, end_date) = model
+        .calculate_validity_period()
+        .map_err(|e| format_err!("impossible to convert the GTFS fares: {}", e))?;
+    let mut rules_by_fare: BTreeMap<String, Vec<FareRule>> = BTreeMap::new();
+    for rule in rules {
+        rules_by_fare
+            .entry(rule.fare_id.clone())
+            .or_insert_with(Vec::new)
+            .push(rule);
+    }
+    // the networks of all the lines, used when a fare gives neither a route nor an agency
+    let all_network_ids: Vec<String> = model.networks.values().map(|n| n.id.clone()).collect();
+
+    let (mut tickets, mut ticket_prices, mut ticket_uses, mut perimeters, mut restrictions) =
+        NtfsFares::default();
+    for fare in fares {
+        let fare_rules = rules_by_fare.remove(&fare.id).unwrap_or_default();
+        // the routes of the rules (`None` if a rule has no route) by pair of zones
+        let mut routes_by_zones: BTreeMap<Option<(String, String)>, BTreeSet<Option<&str>>> =
+            BTreeMap::new();
+        for rule in &fare_rules {
+            if rule.contains_id.is_some() {
+                warn!(
+                    "fare_id={:?}: contains_id is not supported, ignored",
+                    fare.id
+                );
+            }
+            let zones = match (&rule.origin_id, &rule.destination_id) {
+                (Some(origin), Some(destination)) => Some((origin.clone(), destination.clone())),
+                (None, None) => None,
+                _ => {
+                    warn!(
+                        "fare_id={:?}: a zone restriction needs both origin_id and destination_id, ignored",
+                        fare.id
+                    );
+                    None
+                }
+            };
+            routes_by_zones
+                .entry(zones)
+                .or_insert_with(BTreeSet::new)
+                .insert(rule.route_id.as_deref());
+        }
+        if routes_by_zones.is_empty() {
+            routes_by_zones.insert(None, vec![None].into_iter().collect());
+        }
+
+        let nb_ticket_uses = routes_by_zones.len();
+        let mut fare_ticket_uses = Vec::new();
+        for (zones, route_ids) in routes_by_zones {
+            // a rule without route makes the zones valid on the whole network
+            let (object_type, object_ids) = if route_ids.contains(&None) {
+                let network_ids = match &fare.agency_id {
+                    Some(agency_id) => vec![agency_id.clone()],
+                    None => all_network_ids.clone(),
+                };
+                (ObjectType::Network, network_ids)
+            } else {
+                let mut line_ids = BTreeSet::new();
+                for route_id in route_ids.into_iter().flatten() {
+                    match get_line_id_of_gtfs_route(model, route_id) {
+                        Some(line_id) => {
+                            line_ids.insert(line_id.to_string());
+                        }
+                        None => warn!(
+                            "fare_id={:?}: route_id={:?} not found, rule ignored",
+                            fare.id, route_id
+                        ),
+                    }
+                }
+                (ObjectType::Line, line_ids.into_iter().collect())
+            };
+            if object_ids.is_empty() {
+                continue;
+            }
+            let ticket_use_id = if nb_ticket_uses == 1 {
+                fare.id.clone()
+            } else {
+                format!("{}:{}", fare.id, fare_ticket_uses.len())
+            };
+            perimeters.extend(object_ids.into_iter().map(|object_id| TicketUsePerimeter {
+                ticket_use_id: ticket_use_id.clone(),
+                object_type: object_type.clone(),
+                object_id,
+                perimeter_action: PerimeterAction::Included,
+            }));
+            if let Some((origin, destination)) = zones {
+                restrictions.push(TicketUseRestriction {
+                    ticket_use_id: ticket_use_id.clone(),
+                    restriction_type: RestrictionType::Zone,
+                    use_origin: origin,
+                    use_destination: destination,
+                });
+            }
+            fare_ticket_uses.push(TicketUse {
+                id: ticket_use_id,
+                ticket_id: fare.id.clone(),
+                max_transfers: fare.transfers,
+                boarding_time_limit: fare.transfer_duration,
+                alighting_time_limit: None,
+            });
+        }
+        if fare_ticket_uses.is_empty() {
+            warn!("fare_id={:?}: no valid route found, fare ignored", fare.id);
+            continue;
+        }
+        ticket_uses.extend(fare_ticket_uses);
+        tickets.push(Ticket {
+            id: fare.id.clone(),
+            name: fare.id.clone(),
+            comment: None,
+        });
+        ticket_prices.push(TicketPrice {
+            ticket_id: fare.id,
+            price: fare.price,
+            currency: fare.currency,
+            ticket_validity_start: start_date,
+            ticket_validity_end: end_date,
+        });
+    }
+    for fare_id in rules_by_fare.keys() {
+        warn!(
+            "fare_rules.txt: fare_id={:?} not found in fare_attributes.txt, rules ignored",
+            fare_id
+        );
+    }
+    Ok((
+        tickets,
+        ticket_prices,
+        ticket_uses,
+        perimeters,
+        restrictions,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1351,7 +1845,7 @@ mod tests {
         objects::{Calendar, Comment, CommentType, Equipment, Geometry, Rgb, StopTime, Transfer},
         read_utils::{self, read_opt_collection, PathFileHandler},
         test_utils::*,
-        AddPrefix, PrefixConfiguration,
+        AddPrefix, DefaultIdGenerator, PrefixConfiguration,
     };
     use geo::line_string;
     use pretty_assertions::assert_eq;
@@ -1412,7 +1906,61 @@ mod tests {
             assert_eq!(1, networks.len());
             let network = networks.iter().next().unwrap().1;
             assert_eq!("id_1", network.id);
+            assert_eq!(Some("0123456789"), network.phone.as_deref());
+            assert_eq!(Some("my-mail@example.com"), network.email.as_deref());
+            assert_eq!(
+                Some("http://my-agency_fare_url.com"),
+                network.fare_url.as_deref()
+            );
             assert_eq!(1, companies.len());
+            let company = companies.iter().next().unwrap().1;
+            assert_eq!(Some("0123456789"), company.phone.as_deref());
+            assert_eq!(Some("my-mail@example.com"), company.mail.as_deref());
+        });
+    }
+
+    #[test]
+    fn load_feed_info() {
+        let feed_info_content =
+            "feed_publisher_name,feed_publisher_url,feed_lang,default_lang,feed_start_date,\
+             feed_end_date,feed_version,feed_contact_email,feed_contact_url\n\
+             My publisher,http://my-publisher.com,fr,en,20200101,20201231,v1,,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "feed_info.txt", feed_info_content);
+            let mut collections = Collections::default();
+            collections
+                .feed_infos
+                .insert("feed_publisher_name".to_string(), "Config".to_string());
+            super::manage_feed_info(&mut collections, &mut handler).unwrap();
+            let feed_infos: Vec<_> = collections
+                .feed_infos
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect();
+            assert_eq!(
+                vec![
+                    ("feed_default_lang", "en"),
+                    ("feed_end_date", "20201231"),
+                    ("feed_lang", "fr"),
+                    ("feed_publisher_name", "Config"),
+                    ("feed_publisher_url", "http://my-publisher.com"),
+                    ("feed_start_date", "20200101"),
+                    ("feed_version", "v1"),
+                ],
+                feed_infos
+            );
+        });
+    }
+
+    #[test]
+    fn load_without_feed_info() {
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            let mut collections = Collections::default();
+            super::manage_feed_info(&mut collections, &mut handler).unwrap();
+            assert!(collections.feed_infos.is_empty());
         });
     }
 
@@ -1465,8 +2013,13 @@ mod tests {
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
 
-            let (stop_areas, stop_points, stop_locations) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, stop_locations) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             assert_eq!(1, stop_areas.len());
             assert_eq!(1, stop_points.len());
             assert_eq!(0, stop_locations.len());
@@ -1499,8 +2052,13 @@ mod tests {
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             // let stop_file = File::open(path.join("stops.txt")).unwrap();
-            let (stop_areas, stop_points, stop_locations) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, stop_locations) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_areas = stop_areas;
             collections.stop_points = stop_points;
             collections.stop_locations = stop_locations;
@@ -1534,8 +2092,13 @@ mod tests {
             create_file_with_content(path, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, stop_points, stop_locations) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, stop_locations) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             //validate stop_point code
             assert_eq!(1, stop_points.len());
             let stop_point = stop_points.iter().next().unwrap().1;
@@ -1575,8 +2138,13 @@ mod tests {
             create_file_with_content(path, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, _, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, _, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             //validate stop_area code
             assert_eq!(1, stop_areas.len());
             let stop_area = stop_areas.iter().next().unwrap().1;
@@ -1950,6 +2518,47 @@ mod tests {
         });
     }
 
+    #[test]
+    fn gtfs_routes_with_missing_names() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                              route_1,agency_1,1,,3\n\
+                              route_2,agency_1,,My line 2,3\n\
+                              route_3,agency_1,,,3\n\
+                              route_4,agency_1,,,3";
+
+        let trips_content = "trip_id,route_id,direction_id,service_id\n\
+                             1,route_1,0,service_1\n\
+                             2,route_2,0,service_1\n\
+                             3,route_3,0,service_1\n\
+                             4,route_4,0,service_1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "routes.txt", routes_content);
+            create_file_with_content(path, "trips.txt", trips_content);
+            let mut collections = Collections::default();
+            let (contributor, dataset, _) = read_utils::read_config(None::<&str>).unwrap();
+            collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
+            collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
+            super::read_routes(&mut handler, &mut collections, false).unwrap();
+
+            let lines: Vec<_> = collections
+                .lines
+                .values()
+                .map(|l| (l.id.as_str(), l.code.as_deref(), l.name.as_str()))
+                .collect();
+            assert_eq!(
+                vec![
+                    ("route_1", Some("1"), "1"),
+                    ("route_2", None, "My line 2"),
+                    ("route_3", None, "route_3"),
+                    ("route_4", None, "route_4"),
+                ],
+                lines
+            );
+        });
+    }
+
     #[test]
     fn gtfs_routes_with_no_trips() {
         let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
@@ -2025,8 +2634,13 @@ mod tests {
             let (contributor, dataset, _) = read_utils::read_config(None::<&str>).unwrap();
             collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
             collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
-            let (stop_areas, stop_points, stop_locations) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, stop_locations) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.equipments = CollectionWithId::new(equipments.into_equipments()).unwrap();
             collections.transfers =
                 super::read_transfers(&mut handler, &stop_points, &stop_areas).unwrap();
@@ -2361,8 +2975,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (stop_areas, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             let equipments_collection =
                 CollectionWithId::new(equipments.into_equipments()).unwrap();
             assert_eq!(2, stop_areas.len());
@@ -2427,8 +3046,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             let equipments_collection =
                 CollectionWithId::new(equipments.into_equipments()).unwrap();
             assert_eq!(2, stop_points.len());
@@ -2498,8 +3122,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_points = stop_points;
 
             super::read_routes(&mut handler, &mut collections, false).unwrap();
@@ -2584,8 +3213,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_points = stop_points;
 
             super::read_routes(&mut handler, &mut collections, false).unwrap();
@@ -2653,8 +3287,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (stop_areas, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
 
             let transfers = super::read_transfers(&mut handler, &stop_points, &stop_areas).unwrap();
             assert_eq!(
@@ -2923,8 +3562,13 @@ mod tests {
             create_file_with_content(path, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (stop_areas, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (stop_areas, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             assert_eq!(1, stop_points.len());
             assert_eq!(1, stop_areas.len());
             let stop_area = stop_areas.iter().next().unwrap().1;
@@ -2946,8 +3590,13 @@ mod tests {
             create_file_with_content(path, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             assert_eq!(3, stop_points.len());
             let longitudes: Vec<f64> = stop_points
                 .values()
@@ -2964,6 +3613,117 @@ mod tests {
         });
     }
 
+    #[test]
+    fn read_stops_with_custom_id_generator() {
+        struct CustomIdGenerator;
+        impl IdGenerator for CustomIdGenerator {
+            fn stop_area_id(&self, stop_point_id: &str) -> String {
+                format!("area_of_{}", stop_point_id)
+            }
+        }
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sp:01,my stop point name,0.1,1.2,0,";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "stops.txt", stops_content);
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (stop_areas, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &CustomIdGenerator,
+            )
+            .unwrap();
+            assert_eq!(vec!["area_of_sp:01"], extract_ids(&stop_areas));
+            assert_eq!(
+                "area_of_sp:01",
+                stop_points.get("sp:01").unwrap().stop_area_id
+            );
+        });
+    }
+
+    #[test]
+    fn read_areas() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n\
+                             sa:1,my stop area,0.1,1.2,1,\n\
+                             sp:1,my stop point 1,0.1,1.2,0,sa:1\n\
+                             sp:2,my stop point 2,0.1,1.2,0,\n\
+                             sp:3,my stop point 3,0.1,1.2,0,\n\
+                             sp:4,my stop point 4,0.3,1.4,0,";
+        let areas_content = "area_id,area_name\n\
+                             zone_1,Zone 1\n\
+                             zone_2,Zone 2\n\
+                             zone_3,Zone 3";
+        let stop_areas_content = "area_id,stop_id\n\
+                                  zone_1,sp:1\n\
+                                  zone_1,sp:2\n\
+                                  zone_2,sp:2\n\
+                                  zone_2,sa:1\n\
+                                  zone_2,unknown_stop\n\
+                                  zone_3,sp:3\n\
+                                  zone_3,sp:4\n\
+                                  unknown_zone,sp:1";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "areas.txt", areas_content);
+            create_file_with_content(path, "stop_areas.txt", stop_areas_content);
+
+            let mut collections = Collections::default();
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (stop_areas, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
+            collections.stop_areas = stop_areas;
+            collections.stop_points = stop_points;
+            super::manage_areas(&mut collections, &mut handler, &DefaultIdGenerator).unwrap();
+
+            // zone_1 and zone_2 share a stop point and contain a stop point
+            // with a parent station: they are kept as object codes
+            let area_codes = |codes: &KeysValues| {
+                codes
+                    .iter()
+                    .filter(|(system, _)| system == "gtfs_area_id")
+                    .map(|(_, code)| code.clone())
+                    .collect::<Vec<_>>()
+            };
+            let stop_point_codes =
+                |id: &str| area_codes(&collections.stop_points.get(id).unwrap().codes);
+            assert_eq!(vec!["zone_1"], stop_point_codes("sp:1"));
+            assert_eq!(vec!["zone_1", "zone_2"], stop_point_codes("sp:2"));
+            assert!(stop_point_codes("sp:3").is_empty());
+            assert_eq!(
+                vec!["zone_2"],
+                area_codes(&collections.stop_areas.get("sa:1").unwrap().codes)
+            );
+
+            // zone_3 replaces the stop areas generated for its stop points
+            assert_eq!(
+                vec!["Navitia:sp:2", "sa:1", "zone_3"],
+                extract_ids(&collections.stop_areas)
+            );
+            assert_eq!("Zone 3", collections.stop_areas.get("zone_3").unwrap().name);
+            let stop_area_id = |id: &str| {
+                collections
+                    .stop_points
+                    .get(id)
+                    .unwrap()
+                    .stop_area_id
+                    .clone()
+            };
+            assert_eq!("zone_3", stop_area_id("sp:3"));
+            assert_eq!("zone_3", stop_area_id("sp:4"));
+        });
+    }
+
     #[test]
     fn gtfs_undefined_stop_times() {
         let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
@@ -3008,8 +3768,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_points = stop_points;
 
             super::read_routes(&mut handler, &mut collections, false).unwrap();
@@ -3035,6 +3800,90 @@ mod tests {
         });
     }
 
+    #[test]
+    fn gtfs_undefined_stop_times_with_distances() {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                              route_1,agency_1,1,My line 1,3";
+
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type\n\
+                             sp:01,my stop point name 1,0.1,1.2,0\n\
+                             sp:02,my stop point name 2,0.1,1.2,0\n\
+                             sp:03,my stop point name 3,0.1,1.2,0\n\
+                             sp:04,my stop point name 4,0.1,1.2,0";
+
+        let trips_content = "trip_id,route_id,service_id\n\
+                             1,route_1,service_1\n\
+                             2,route_1,service_1";
+
+        // trip 1 has distances, trip 2 only has some of them
+        let stop_times_content =
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence,shape_dist_traveled\n\
+                                  1,10:00:00,10:00:00,sp:01,1,0\n\
+                                  1,,,sp:02,2,300\n\
+                                  1,,,sp:03,3,400\n\
+                                  1,10:10:00,10:10:00,sp:04,4,1000\n\
+                                  2,10:00:00,10:00:00,sp:01,1,0\n\
+                                  2,,,sp:02,2,\n\
+                                  2,,,sp:03,3,400\n\
+                                  2,10:09:00,10:09:00,sp:04,4,1000";
+
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "routes.txt", routes_content);
+            create_file_with_content(path, "trips.txt", trips_content);
+            create_file_with_content(path, "stop_times.txt", stop_times_content);
+            create_file_with_content(path, "stops.txt", stops_content);
+
+            let mut collections = Collections::default();
+            let (contributor, dataset, _) = read_utils::read_config(None::<&str>).unwrap();
+            collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
+            collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
+            collections.stop_points = stop_points;
+
+            super::read_routes(&mut handler, &mut collections, false).unwrap();
+            super::manage_stop_times(&mut collections, &mut handler, false, None).unwrap();
+
+            let times = |vj_id: &str| {
+                collections
+                    .vehicle_journeys
+                    .get(vj_id)
+                    .unwrap()
+                    .stop_times
+                    .iter()
+                    .map(|st| (st.arrival_time, st.datetime_estimated))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                vec![
+                    (Time::new(10, 0, 0), false),
+                    (Time::new(10, 3, 0), true),
+                    (Time::new(10, 4, 0), true),
+                    (Time::new(10, 10, 0), false),
+                ],
+                times("1")
+            );
+            assert_eq!(
+                vec![
+                    (Time::new(10, 0, 0), false),
+                    (Time::new(10, 3, 0), true),
+                    (Time::new(10, 6, 0), true),
+                    (Time::new(10, 9, 0), false),
+                ],
+                times("2")
+            );
+        });
+    }
+
     #[test]
     fn gtfs_invalid_undefined_stop_times() {
         let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type,route_color,route_text_color\n\
@@ -3079,16 +3928,27 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_points = stop_points;
 
             super::read_routes(&mut handler, &mut collections, false).unwrap();
             let val = super::manage_stop_times(&mut collections, &mut handler, false, None);
 
-            // the first stop time of the vj has no departure/arrival, it's an error
-            let err = val.unwrap_err();
-            assert_eq!( "the first stop time of the vj '1' has no departure/arrival, the stop_times.txt file is not valid",format!("{}", err));
+            // the first stop time of the vj has no departure/arrival, the vj is
+            // left without stop times and will be removed by the sanitize
+            assert!(val.is_ok());
+            assert!(collections
+                .vehicle_journeys
+                .get("1")
+                .unwrap()
+                .stop_times
+                .is_empty());
         });
     }
     #[test]
@@ -3105,8 +3965,13 @@ mod tests {
             create_file_with_content(path, "stops.txt", stops_content);
             let mut equipments = EquipmentList::default();
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
-            let (_, _, stop_locations) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, _, stop_locations) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             let stop_entrance = stop_locations
                 .values()
                 .filter(|sl| sl.stop_type == StopType::StopEntrance);
@@ -3142,9 +4007,13 @@ mod tests {
             create_file_with_content(path, "pathways.txt", pathway_content);
             let mut collections = Collections::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, stop_locations) =
-                super::read_stops(&mut handler, &mut collections.comments, &mut equipments)
-                    .unwrap();
+            let (_, stop_points, stop_locations) = super::read_stops(
+                &mut handler,
+                &mut collections.comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_points = stop_points;
             collections.stop_locations = stop_locations;
 
@@ -3209,8 +4078,13 @@ mod tests {
 
             let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
             let mut equipments = EquipmentList::default();
-            let (_, stop_points, _) =
-                super::read_stops(&mut handler, &mut comments, &mut equipments).unwrap();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
             collections.stop_points = stop_points;
 
             super::read_routes(&mut handler, &mut collections, false).unwrap();
@@ -3263,6 +4137,221 @@ mod tests {
         });
     }
 
+    // Read a GTFS with the given stops, trips, stop times and frequencies, the
+    // trips being on the route "route_1" and on the service "service_1" which
+    // runs on 2020-01-01.
+    fn read_frequencies(
+        stops_content: &str,
+        trips_content: &str,
+        stop_times_content: &str,
+        frequencies_content: &str,
+    ) -> Collections {
+        let routes_content = "route_id,agency_id,route_short_name,route_long_name,route_type\n\
+                              route_1,agency_1,1,My line 1,3";
+
+        let mut collections = Collections::default();
+        test_in_tmp_dir(|path| {
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            create_file_with_content(path, "routes.txt", routes_content);
+            create_file_with_content(path, "trips.txt", trips_content);
+            create_file_with_content(path, "stop_times.txt", stop_times_content);
+            create_file_with_content(path, "stops.txt", stops_content);
+            create_file_with_content(path, "frequencies.txt", frequencies_content);
+
+            let (contributor, dataset, _) = read_utils::read_config(None::<&str>).unwrap();
+            collections.contributors = CollectionWithId::new(vec![contributor]).unwrap();
+            collections.datasets = CollectionWithId::new(vec![dataset]).unwrap();
+            collections.calendars = CollectionWithId::new(vec![Calendar {
+                id: "service_1".to_string(),
+                dates: vec![Date::from_ymd(2020, 1, 1)].into_iter().collect(),
+            }])
+            .unwrap();
+
+            let mut comments: CollectionWithId<Comment> = CollectionWithId::default();
+            let mut equipments = EquipmentList::default();
+            let (_, stop_points, _) = super::read_stops(
+                &mut handler,
+                &mut comments,
+                &mut equipments,
+                &DefaultIdGenerator,
+            )
+            .unwrap();
+            collections.stop_points = stop_points;
+
+            super::read_routes(&mut handler, &mut collections, false).unwrap();
+            super::manage_stop_times(&mut collections, &mut handler, false, None).unwrap();
+            super::manage_frequencies(&mut collections, &mut handler, &DefaultIdGenerator).unwrap();
+        });
+        collections
+    }
+
+    #[test]
+    fn gtfs_frequencies_after_midnight() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type\n\
+                             sp:01,my stop point name 1,0.1,1.2,0\n\
+                             sp:02,my stop point name 2,0.2,1.5,0";
+
+        let trips_content = "trip_id,route_id,service_id\n\
+                             straddling,route_1,service_1\n\
+                             after_midnight,route_1,service_1";
+
+        let stop_times_content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                                  straddling,06:00:00,06:00:00,sp:01,1\n\
+                                  straddling,06:20:00,06:20:00,sp:02,2\n\
+                                  after_midnight,08:00:00,08:00:00,sp:01,1\n\
+                                  after_midnight,08:15:00,08:15:00,sp:02,2";
+
+        let frequencies_content = "trip_id,start_time,end_time,headway_secs\n\
+                                   straddling,23:30:00,25:15:00,1800\n\
+                                   after_midnight,24:10:00,25:10:00,1800";
+
+        let collections = read_frequencies(
+            stops_content,
+            trips_content,
+            stop_times_content,
+            frequencies_content,
+        );
+
+        let mut vehicle_journeys: Vec<_> = collections
+            .vehicle_journeys
+            .values()
+            .map(|vj| {
+                (
+                    vj.id.as_str(),
+                    vj.service_id.as_str(),
+                    vj.stop_times[0].departure_time,
+                    vj.stop_times[1].arrival_time,
+                )
+            })
+            .collect();
+        vehicle_journeys.sort_unstable();
+        let plus_one_day = "service_1:+1days";
+        assert_eq!(
+            vec![
+                (
+                    "after_midnight-0",
+                    plus_one_day,
+                    Time::new(0, 10, 0),
+                    Time::new(0, 25, 0)
+                ),
+                (
+                    "after_midnight-1",
+                    plus_one_day,
+                    Time::new(0, 40, 0),
+                    Time::new(0, 55, 0)
+                ),
+                (
+                    "straddling-0",
+                    "service_1",
+                    Time::new(23, 30, 0),
+                    Time::new(23, 50, 0)
+                ),
+                (
+                    "straddling-1",
+                    plus_one_day,
+                    Time::new(0, 0, 0),
+                    Time::new(0, 20, 0)
+                ),
+                (
+                    "straddling-2",
+                    plus_one_day,
+                    Time::new(0, 30, 0),
+                    Time::new(0, 50, 0)
+                ),
+                (
+                    "straddling-3",
+                    plus_one_day,
+                    Time::new(1, 0, 0),
+                    Time::new(1, 20, 0)
+                ),
+            ],
+            vehicle_journeys
+        );
+        let shifted_dates: Vec<_> = collections
+            .calendars
+            .get(plus_one_day)
+            .unwrap()
+            .dates
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(vec![Date::from_ymd(2020, 1, 2)], shifted_dates);
+    }
+
+    #[test]
+    fn gtfs_frequencies_ending_before_starting() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type\n\
+                             sp:01,my stop point name 1,0.1,1.2,0";
+
+        let trips_content = "trip_id,route_id,service_id\n\
+                             trip_1,route_1,service_1";
+
+        let stop_times_content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                                  trip_1,06:00:00,06:00:00,sp:01,1";
+
+        let frequencies_content = "trip_id,start_time,end_time,headway_secs\n\
+                                   trip_1,23:30:00,00:30:00,1800";
+
+        let collections = read_frequencies(
+            stops_content,
+            trips_content,
+            stop_times_content,
+            frequencies_content,
+        );
+
+        let mut vehicle_journeys: Vec<_> = collections
+            .vehicle_journeys
+            .values()
+            .map(|vj| (vj.id.as_str(), vj.stop_times[0].departure_time))
+            .collect();
+        vehicle_journeys.sort_unstable();
+        assert_eq!(
+            vec![
+                ("trip_1-0", Time::new(23, 30, 0)),
+                ("trip_1-1", Time::new(0, 0, 0)),
+            ],
+            vehicle_journeys
+        );
+    }
+
+    #[test]
+    fn gtfs_frequencies_with_invalid_headway() {
+        let stops_content = "stop_id,stop_name,stop_lat,stop_lon,location_type\n\
+                             sp:01,my stop point name 1,0.1,1.2,0";
+
+        let trips_content = "trip_id,route_id,service_id\n\
+                             trip_1,route_1,service_1";
+
+        let stop_times_content = "trip_id,arrival_time,departure_time,stop_id,stop_sequence\n\
+                                  trip_1,06:00:00,06:00:00,sp:01,1";
+
+        let frequencies_content = "trip_id,start_time,end_time,headway_secs\n\
+                                   trip_1,08:00:00,09:00:00,0\n\
+                                   trip_1,10:00:00,11:00:00,-600\n\
+                                   trip_1,12:00:00,13:00:00,1800";
+
+        let collections = read_frequencies(
+            stops_content,
+            trips_content,
+            stop_times_content,
+            frequencies_content,
+        );
+
+        let mut vehicle_journeys: Vec<_> = collections
+            .vehicle_journeys
+            .values()
+            .map(|vj| (vj.id.as_str(), vj.stop_times[0].departure_time))
+            .collect();
+        vehicle_journeys.sort_unstable();
+        assert_eq!(
+            vec![
+                ("trip_1-0", Time::new(12, 0, 0)),
+                ("trip_1-1", Time::new(12, 30, 0)),
+            ],
+            vehicle_journeys
+        );
+    }
+
     mod read_gtfs_routes {
         use super::*;
         use crate::{
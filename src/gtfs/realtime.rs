@@ -0,0 +1,270 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Reading of the [GTFS-RT](https://gtfs.org/realtime/reference/) vehicle
+//! positions. Only the few fields needed are decoded from the protobuf wire
+//! format, the other ones are skipped.
+
+use crate::{model::Collections, objects::OccupancyStatus, Result};
+use failure::{bail, format_err, ResultExt};
+use log::warn;
+use std::{convert::TryFrom, io::Read};
+
+// Field numbers in gtfs-realtime.proto
+const FEED_MESSAGE_ENTITY: u64 = 2;
+const FEED_ENTITY_VEHICLE: u64 = 4;
+const VEHICLE_POSITION_TRIP: u64 = 1;
+const VEHICLE_POSITION_OCCUPANCY_STATUS: u64 = 9;
+const TRIP_DESCRIPTOR_TRIP_ID: u64 = 1;
+
+enum Value<'a> {
+    Varint(u64),
+    LengthDelimited(&'a [u8]),
+    // 32 and 64 bits values, not used by the fields read here
+    Fixed,
+}
+
+// Fields of a protobuf message, as (field number, value)
+struct Fields<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Fields { buffer }
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = self
+                .buffer
+                .split_first()
+                .ok_or_else(|| format_err!("truncated varint"))?;
+            self.buffer = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("varint is too long")
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buffer.len() < len {
+            bail!("truncated field");
+        }
+        let (bytes, rest) = self.buffer.split_at(len);
+        self.buffer = rest;
+        Ok(bytes)
+    }
+
+    fn field(&mut self) -> Result<(u64, Value<'a>)> {
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.bytes(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = self.varint()?;
+                Value::LengthDelimited(self.bytes(len as usize)?)
+            }
+            5 => {
+                self.bytes(4)?;
+                Value::Fixed
+            }
+            wire_type => bail!("unsupported wire type {}", wire_type),
+        };
+        Ok((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, Value<'a>)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            let field = self.field();
+            if field.is_err() {
+                // no way to find the next field after a malformed one
+                self.buffer = &[];
+            }
+            Some(field)
+        }
+    }
+}
+
+// The messages of the given field number
+fn messages(buffer: &[u8], number: u64) -> impl Iterator<Item = Result<&[u8]>> {
+    Fields::new(buffer).filter_map(move |field| match field {
+        Ok((n, Value::LengthDelimited(message))) if n == number => Some(Ok(message)),
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    })
+}
+
+// trip_id and raw occupancy_status of a VehiclePosition
+fn vehicle_position(buffer: &[u8]) -> Result<(Option<String>, Option<u64>)> {
+    let mut trip_id = None;
+    let mut occupancy_status = None;
+    for field in Fields::new(buffer) {
+        match field? {
+            (VEHICLE_POSITION_TRIP, Value::LengthDelimited(trip)) => {
+                for trip_field in messages(trip, TRIP_DESCRIPTOR_TRIP_ID) {
+                    let id = String::from_utf8(trip_field?.to_vec())
+                        .context("trip_id is not a valid UTF-8 string")?;
+                    trip_id = Some(id);
+                }
+            }
+            (VEHICLE_POSITION_OCCUPANCY_STATUS, Value::Varint(value)) => {
+                occupancy_status = Some(value)
+            }
+            _ => {}
+        }
+    }
+    Ok((trip_id, occupancy_status))
+}
+
+/// Set the occupancy of the vehicle journeys from the `VehiclePosition`
+/// entities of a GTFS-RT feed (a protobuf encoded `FeedMessage`).
+///
+/// The `trip_id` of each vehicle position is the identifier of the vehicle
+/// journey. The vehicle positions without occupancy status, with an unknown
+/// status or of an unknown trip are skipped with a warning.
+///
+/// ```no_run
+/// # fn run() -> transit_model::Result<()> {
+/// let mut collections = transit_model::gtfs::read("path/to/gtfs")?.into_collections();
+/// let feed = std::fs::File::open("path/to/vehicle_positions.pb")?;
+/// transit_model::gtfs::read_vehicle_positions(&mut collections, feed)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn read_vehicle_positions<R: Read>(collections: &mut Collections, mut reader: R) -> Result<()> {
+    let mut feed = vec![];
+    reader
+        .read_to_end(&mut feed)
+        .context("impossible to read the GTFS-RT feed")?;
+    for entity in messages(&feed, FEED_MESSAGE_ENTITY) {
+        for vehicle in messages(entity?, FEED_ENTITY_VEHICLE) {
+            let (trip_id, occupancy_status) = vehicle_position(vehicle?)?;
+            let (trip_id, occupancy_status) = match (trip_id, occupancy_status) {
+                (Some(trip_id), Some(occupancy_status)) => (trip_id, occupancy_status),
+                // no occupancy, or no way to know which trip it applies to
+                _ => continue,
+            };
+            let occupancy = match i32::try_from(occupancy_status)
+                .map_err(|_| format!("unknown occupancy status {}", occupancy_status))
+                .and_then(OccupancyStatus::try_from)
+            {
+                Ok(occupancy) => occupancy,
+                Err(e) => {
+                    warn!("vehicle position of trip {}: {}", trip_id, e);
+                    continue;
+                }
+            };
+            match collections.vehicle_journeys.get_mut(&trip_id) {
+                Some(mut vj) => vj.occupancy = Some(occupancy),
+                None => warn!("vehicle position of unknown trip {}", trip_id),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::VehicleJourney;
+    use pretty_assertions::assert_eq;
+    use typed_index_collection::CollectionWithId;
+
+    fn varint(mut value: u64, buffer: &mut Vec<u8>) {
+        while value >= 0x80 {
+            buffer.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        buffer.push(value as u8);
+    }
+
+    fn message(number: u64, content: &[u8]) -> Vec<u8> {
+        let mut buffer = vec![];
+        varint(number << 3 | 2, &mut buffer);
+        varint(content.len() as u64, &mut buffer);
+        buffer.extend_from_slice(content);
+        buffer
+    }
+
+    // FeedEntity with a VehiclePosition
+    fn entity(trip_id: &str, occupancy_status: Option<u64>) -> Vec<u8> {
+        let trip = message(TRIP_DESCRIPTOR_TRIP_ID, trip_id.as_bytes());
+        // a timestamp, not read
+        let mut vehicle = vec![5 << 3, 0xd2, 0x09];
+        vehicle.extend(message(VEHICLE_POSITION_TRIP, &trip));
+        if let Some(occupancy_status) = occupancy_status {
+            varint(VEHICLE_POSITION_OCCUPANCY_STATUS << 3, &mut vehicle);
+            varint(occupancy_status, &mut vehicle);
+        }
+        let mut entity = message(1, b"entity_id");
+        entity.extend(message(FEED_ENTITY_VEHICLE, &vehicle));
+        message(FEED_MESSAGE_ENTITY, &entity)
+    }
+
+    fn collections() -> Collections {
+        let vehicle_journeys = ["vj1", "vj2", "vj3"]
+            .iter()
+            .map(|id| VehicleJourney {
+                id: id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        Collections {
+            vehicle_journeys: CollectionWithId::new(vehicle_journeys).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn read_occupancy_of_vehicle_positions() {
+        let mut collections = collections();
+        // the feed header is skipped
+        let mut feed = message(1, &message(1, b"2.0"));
+        feed.extend(entity("vj1", Some(2)));
+        feed.extend(entity("vj2", None));
+        feed.extend(entity("vj3", Some(42)));
+        feed.extend(entity("unknown", Some(5)));
+        read_vehicle_positions(&mut collections, feed.as_slice()).unwrap();
+
+        let occupancies: Vec<_> = collections
+            .vehicle_journeys
+            .values()
+            .map(|vj| vj.occupancy)
+            .collect();
+        assert_eq!(
+            vec![Some(OccupancyStatus::FewSeatsAvailable), None, None],
+            occupancies
+        );
+    }
+
+    #[test]
+    fn truncated_feed() {
+        let mut collections = collections();
+        let feed = entity("vj1", Some(2));
+        let error = read_vehicle_positions(&mut collections, &feed[..feed.len() - 2]);
+        assert_eq!("truncated field", error.unwrap_err().to_string());
+    }
+}
@@ -415,6 +415,8 @@ pub fn write_routes(path: &path::Path, model: &Model) -> Result<()> {
     Ok(())
 }
 
+// As for the NTFS, the stop times are streamed vehicle journey by vehicle
+// journey: the file is grouped by trip_id but not globally sorted.
 pub fn write_stop_times(
     path: &path::Path,
     vehicle_journeys: &CollectionWithId<VehicleJourney>,
@@ -440,7 +442,10 @@ pub fn write_stop_times(
                     stop_headsign: stop_times_headsigns
                         .get(&(vehicle_journeys[vj_idx].id.clone(), st.sequence))
                         .cloned(),
+                    shape_dist_traveled: None,
                     timepoint: !st.datetime_estimated,
+                    pickup_booking_rule_id: None,
+                    drop_off_booking_rule_id: None,
                 })
                 .with_context(|_| format!("Error reading {:?}", st_wtr))?;
         }
@@ -520,6 +525,8 @@ mod tests {
             phone: Some("0123456789".to_string()),
             address: Some("somewhere".to_string()),
             sort_order: Some(1),
+            email: Some("contact@savac.fr".to_string()),
+            fare_url: Some("http://www.vianavigo.com/tarifs".to_string()),
             codes: Default::default(),
         });
 
@@ -530,7 +537,8 @@ mod tests {
             timezone: chrono_tz::Europe::Madrid,
             lang: Some("fr".to_string()),
             phone: Some("0123456789".to_string()),
-            email: None,
+            email: Some("contact@savac.fr".to_string()),
+            fare_url: Some("http://www.vianavigo.com/tarifs".to_string()),
         };
 
         assert_eq!(expected_agency, agency);
@@ -547,6 +555,8 @@ mod tests {
             phone: None,
             address: None,
             sort_order: None,
+            email: None,
+            fare_url: None,
             codes: Default::default(),
         });
 
@@ -558,6 +568,7 @@ mod tests {
             lang: None,
             phone: None,
             email: None,
+            fare_url: None,
         };
 
         assert_eq!(expected_agency, agency);
@@ -623,6 +634,7 @@ mod tests {
             equipment_id: Some("1".to_string()),
             fare_zone_id: Some("1".to_string()),
             stop_type: StopType::Point,
+            platform_code: Some("3B".to_string()),
             ..Default::default()
         };
 
@@ -640,7 +652,7 @@ mod tests {
             url: None,
             timezone: Some(chrono_tz::Europe::Paris),
             level_id: None,
-            platform_code: None,
+            platform_code: Some("3B".to_string()),
         };
 
         assert_eq!(
@@ -1215,6 +1227,7 @@ mod tests {
             geometry_id: None,
             stop_times: stop_times_vec,
             journey_pattern_id: Some(String::from("jp:01")),
+            occupancy: None,
         });
         let mut stop_times_headsigns = HashMap::new();
         stop_times_headsigns.insert(("vj:01".to_string(), 1), "somewhere".to_string());
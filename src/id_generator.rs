@@ -0,0 +1,61 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Generation of the identifiers of the objects created by the readers
+//! (objects that have no equivalent in the input data).
+
+/// Strategy to build the identifiers of the objects created by the readers.
+///
+/// Every method has a default implementation, which is the historical
+/// behavior of the crate (see [`DefaultIdGenerator`]). Override only the
+/// identifiers that need another naming scheme.
+///
+/// ```
+/// use transit_model::IdGenerator;
+///
+/// struct MyIdGenerator;
+/// impl IdGenerator for MyIdGenerator {
+///     fn stop_area_id(&self, stop_point_id: &str) -> String {
+///         format!("stop_area_of_{}", stop_point_id)
+///     }
+/// }
+///
+/// assert_eq!("stop_area_of_SP1", MyIdGenerator.stop_area_id("SP1"));
+/// assert_eq!("trip-3", MyIdGenerator.frequency_vehicle_journey_id("trip", 3));
+/// ```
+pub trait IdGenerator {
+    /// Identifier of the `StopArea` created for a `StopPoint` without parent
+    /// station.
+    fn stop_area_id(&self, stop_point_id: &str) -> String {
+        format!("Navitia:{}", stop_point_id)
+    }
+
+    /// Identifier of the `index`-th `VehicleJourney` generated from the
+    /// frequencies of a trip.
+    fn frequency_vehicle_journey_id(&self, trip_id: &str, index: u32) -> String {
+        format!("{}-{}", trip_id, index)
+    }
+
+    /// Identifier of the `Calendar` created by shifting an existing calendar
+    /// of `nb_days` days (for the trips generated after midnight).
+    fn shifted_calendar_id(&self, calendar_id: &str, nb_days: u32) -> String {
+        format!("{}:+{}days", calendar_id, nb_days)
+    }
+}
+
+/// The default identifiers generation, as historically done by the crate.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultIdGenerator;
+
+impl IdGenerator for DefaultIdGenerator {}
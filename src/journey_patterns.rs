@@ -0,0 +1,76 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Computation of the journey patterns of the vehicle journeys.
+
+use crate::{model::Collections, objects::VehicleJourney};
+use std::collections::BTreeMap;
+use typed_index_collection::Idx;
+
+/// Key of a journey pattern: the ordered stop point identifiers with their
+/// pickup and drop off types.
+pub type JourneyPatternKey = Vec<(String, u8, u8)>;
+
+/// Group the vehicle journeys by journey pattern, see
+/// [Collections::journey_patterns].
+pub(crate) fn journey_patterns(
+    collections: &Collections,
+) -> BTreeMap<JourneyPatternKey, Vec<Idx<VehicleJourney>>> {
+    let mut journey_patterns: BTreeMap<JourneyPatternKey, Vec<Idx<VehicleJourney>>> =
+        BTreeMap::new();
+    for (vj_idx, vj) in &collections.vehicle_journeys {
+        let key = vj
+            .stop_times
+            .iter()
+            .map(|stop_time| {
+                (
+                    collections.stop_points[stop_time.stop_point_idx].id.clone(),
+                    stop_time.pickup_type,
+                    stop_time.drop_off_type,
+                )
+            })
+            .collect();
+        journey_patterns
+            .entry(key)
+            .or_insert_with(Vec::new)
+            .push(vj_idx);
+    }
+    for vj_idxs in journey_patterns.values_mut() {
+        vj_idxs.sort_unstable_by(|idx1, idx2| {
+            collections.vehicle_journeys[*idx1]
+                .id
+                .cmp(&collections.vehicle_journeys[*idx2].id)
+        });
+    }
+    journey_patterns
+}
+
+/// Fill the missing `journey_pattern_id`, see
+/// [Collections::enhance_journey_pattern_ids].
+pub(crate) fn enhance_journey_pattern_ids(collections: &mut Collections) {
+    let journey_patterns = journey_patterns(collections);
+    for (position, vj_idxs) in journey_patterns.values().enumerate() {
+        for vj_idx in vj_idxs {
+            if collections.vehicle_journeys[*vj_idx]
+                .journey_pattern_id
+                .is_none()
+            {
+                collections
+                    .vehicle_journeys
+                    .index_mut(*vj_idx)
+                    .journey_pattern_id = Some(format!("journey_pattern:{}", position));
+            }
+        }
+    }
+}
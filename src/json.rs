@@ -0,0 +1,544 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Serialization of a whole `Model` into a single JSON document.
+//!
+//! The collections are serialized as they are, then the fields which are not
+//! part of their serialized form (codes, object properties, comment links,
+//! calendar dates, stop times, etc.) are stored in side tables, referencing
+//! the objects by identifier.
+
+use crate::{
+    model::{Collections, Model},
+    objects::*,
+    Result,
+};
+use failure::{format_err, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use typed_index_collection::{CollectionWithId, Id};
+
+type ObjectsCodes = BTreeMap<String, KeysValues>;
+type ObjectsProperties = BTreeMap<String, PropertiesMap>;
+type ObjectsCommentLinks = BTreeMap<String, CommentLinksT>;
+type StopTimeValues = Vec<(String, u32, String)>;
+
+#[derive(Serialize, Deserialize)]
+struct JsonStopTime {
+    stop_point_id: String,
+    sequence: u32,
+    arrival_time: Time,
+    departure_time: Time,
+    boarding_duration: u16,
+    alighting_duration: u16,
+    pickup_type: u8,
+    drop_off_type: u8,
+    datetime_estimated: bool,
+    local_zone_id: Option<u16>,
+    precision: Option<StopTimePrecision>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonModel<C> {
+    collections: C,
+    codes: BTreeMap<String, ObjectsCodes>,
+    object_properties: BTreeMap<String, ObjectsProperties>,
+    comment_links: BTreeMap<String, ObjectsCommentLinks>,
+    line_directions: BTreeMap<String, (Option<String>, Option<String>)>,
+    calendar_dates: BTreeMap<String, Vec<String>>,
+    stop_times: BTreeMap<String, Vec<JsonStopTime>>,
+    occupancies: BTreeMap<String, OccupancyStatus>,
+    stop_point_types: BTreeMap<String, StopType>,
+    stop_location_types: BTreeMap<String, StopType>,
+    pathway_stop_types: BTreeMap<String, (StopType, StopType)>,
+    stop_time_headsigns: StopTimeValues,
+    stop_time_ids: StopTimeValues,
+    stop_time_comments: StopTimeValues,
+    stop_time_pickup_booking_rules: StopTimeValues,
+    stop_time_drop_off_booking_rules: StopTimeValues,
+}
+
+fn codes_of<T: Id<T> + Codes>(collection: &CollectionWithId<T>) -> ObjectsCodes {
+    collection
+        .values()
+        .filter(|object| !object.codes().is_empty())
+        .map(|object| (object.id().to_string(), object.codes().clone()))
+        .collect()
+}
+
+fn properties_of<T: Id<T> + Properties>(collection: &CollectionWithId<T>) -> ObjectsProperties {
+    collection
+        .values()
+        .filter(|object| !object.properties().is_empty())
+        .map(|object| (object.id().to_string(), object.properties().clone()))
+        .collect()
+}
+
+fn comment_links_of<T: Id<T> + CommentLinks>(
+    collection: &CollectionWithId<T>,
+) -> ObjectsCommentLinks {
+    collection
+        .values()
+        .filter(|object| !object.comment_links().is_empty())
+        .map(|object| (object.id().to_string(), object.comment_links().clone()))
+        .collect()
+}
+
+fn stop_time_values(values: &HashMap<(String, u32), String>) -> StopTimeValues {
+    let mut values: StopTimeValues = values
+        .iter()
+        .map(|((vj_id, sequence), value)| (vj_id.clone(), *sequence, value.clone()))
+        .collect();
+    values.sort();
+    values
+}
+
+/// Serializes a `Model` into a single JSON document, which can be read back
+/// with [from_json_reader].
+pub fn to_json_writer<W: std::io::Write>(model: &Model, writer: W) -> Result<()> {
+    let c: &Collections = model;
+    let mut codes = BTreeMap::new();
+    codes.insert("networks".to_string(), codes_of(&c.networks));
+    codes.insert("companies".to_string(), codes_of(&c.companies));
+    codes.insert("lines".to_string(), codes_of(&c.lines));
+    codes.insert("routes".to_string(), codes_of(&c.routes));
+    codes.insert(
+        "vehicle_journeys".to_string(),
+        codes_of(&c.vehicle_journeys),
+    );
+    codes.insert("stop_areas".to_string(), codes_of(&c.stop_areas));
+    codes.insert("stop_points".to_string(), codes_of(&c.stop_points));
+    let mut object_properties = BTreeMap::new();
+    object_properties.insert("lines".to_string(), properties_of(&c.lines));
+    object_properties.insert("routes".to_string(), properties_of(&c.routes));
+    object_properties.insert(
+        "vehicle_journeys".to_string(),
+        properties_of(&c.vehicle_journeys),
+    );
+    object_properties.insert("stop_areas".to_string(), properties_of(&c.stop_areas));
+    object_properties.insert("stop_points".to_string(), properties_of(&c.stop_points));
+    let mut comment_links = BTreeMap::new();
+    comment_links.insert("lines".to_string(), comment_links_of(&c.lines));
+    comment_links.insert("routes".to_string(), comment_links_of(&c.routes));
+    comment_links.insert(
+        "vehicle_journeys".to_string(),
+        comment_links_of(&c.vehicle_journeys),
+    );
+    comment_links.insert("stop_areas".to_string(), comment_links_of(&c.stop_areas));
+    comment_links.insert("stop_points".to_string(), comment_links_of(&c.stop_points));
+    comment_links.insert(
+        "stop_locations".to_string(),
+        comment_links_of(&c.stop_locations),
+    );
+
+    let json_model = JsonModel {
+        collections: c,
+        codes,
+        object_properties,
+        comment_links,
+        line_directions: c
+            .lines
+            .values()
+            .filter(|line| line.forward_direction.is_some() || line.backward_direction.is_some())
+            .map(|line| {
+                (
+                    line.id.clone(),
+                    (
+                        line.forward_direction.clone(),
+                        line.backward_direction.clone(),
+                    ),
+                )
+            })
+            .collect(),
+        calendar_dates: c
+            .calendars
+            .values()
+            .map(|calendar| {
+                let dates = calendar
+                    .dates
+                    .iter()
+                    .map(|date| date.format("%Y%m%d").to_string())
+                    .collect();
+                (calendar.id.clone(), dates)
+            })
+            .collect(),
+        stop_times: c
+            .vehicle_journeys
+            .values()
+            .map(|vj| {
+                let stop_times = vj
+                    .stop_times
+                    .iter()
+                    .map(|st| JsonStopTime {
+                        stop_point_id: c.stop_points[st.stop_point_idx].id.clone(),
+                        sequence: st.sequence,
+                        arrival_time: st.arrival_time,
+                        departure_time: st.departure_time,
+                        boarding_duration: st.boarding_duration,
+                        alighting_duration: st.alighting_duration,
+                        pickup_type: st.pickup_type,
+                        drop_off_type: st.drop_off_type,
+                        datetime_estimated: st.datetime_estimated,
+                        local_zone_id: st.local_zone_id,
+                        precision: st.precision.clone(),
+                    })
+                    .collect();
+                (vj.id.clone(), stop_times)
+            })
+            .collect(),
+        occupancies: c
+            .vehicle_journeys
+            .values()
+            .filter_map(|vj| vj.occupancy.map(|occupancy| (vj.id.clone(), occupancy)))
+            .collect(),
+        stop_point_types: c
+            .stop_points
+            .values()
+            .map(|sp| (sp.id.clone(), sp.stop_type.clone()))
+            .collect(),
+        stop_location_types: c
+            .stop_locations
+            .values()
+            .map(|sl| (sl.id.clone(), sl.stop_type.clone()))
+            .collect(),
+        pathway_stop_types: c
+            .pathways
+            .values()
+            .map(|pathway| {
+                (
+                    pathway.id.clone(),
+                    (pathway.from_stop_type.clone(), pathway.to_stop_type.clone()),
+                )
+            })
+            .collect(),
+        stop_time_headsigns: stop_time_values(&c.stop_time_headsigns),
+        stop_time_ids: stop_time_values(
+            &c.stop_time_ids
+                .iter()
+                .map(|((vj_idx, sequence), stop_time_id)| {
+                    (
+                        (c.vehicle_journeys[*vj_idx].id.clone(), *sequence),
+                        stop_time_id.clone(),
+                    )
+                })
+                .collect(),
+        ),
+        stop_time_comments: stop_time_values(&c.stop_time_comments),
+        stop_time_pickup_booking_rules: stop_time_values(&c.stop_time_pickup_booking_rules),
+        stop_time_drop_off_booking_rules: stop_time_values(&c.stop_time_drop_off_booking_rules),
+    };
+    serde_json::to_writer(writer, &json_model).context("Error writing the model as JSON")?;
+    Ok(())
+}
+
+fn set_values<T, V>(
+    collection: &mut CollectionWithId<T>,
+    values: BTreeMap<String, V>,
+    mut set_value: impl FnMut(&mut T, V),
+) -> Result<()>
+where
+    T: Id<T>,
+{
+    for (id, value) in values {
+        let mut object = collection
+            .get_mut(&id)
+            .ok_or_else(|| format_err!("Unknown object {:?} in the JSON side tables", id))?;
+        set_value(&mut *object, value);
+    }
+    Ok(())
+}
+
+fn take_table<V: Default>(tables: &mut BTreeMap<String, V>, name: &str) -> V {
+    tables.remove(name).unwrap_or_default()
+}
+
+fn stop_time_map(values: StopTimeValues) -> HashMap<(String, u32), String> {
+    values
+        .into_iter()
+        .map(|(vj_id, sequence, value)| ((vj_id, sequence), value))
+        .collect()
+}
+
+/// Reads a `Model` serialized with [to_json_writer].
+///
+/// The model is built again with `Model::new`, so the collections are
+/// checked and sanitized as for any other reader.
+pub fn from_json_reader<R: std::io::Read>(reader: R) -> Result<Model> {
+    let json_model: JsonModel<Collections> =
+        serde_json::from_reader(reader).context("Error reading the model from JSON")?;
+    let JsonModel {
+        collections: mut c,
+        mut codes,
+        mut object_properties,
+        mut comment_links,
+        line_directions,
+        calendar_dates,
+        stop_times,
+        occupancies,
+        stop_point_types,
+        stop_location_types,
+        pathway_stop_types,
+        stop_time_headsigns,
+        stop_time_ids,
+        stop_time_comments,
+        stop_time_pickup_booking_rules,
+        stop_time_drop_off_booking_rules,
+    } = json_model;
+
+    set_values(
+        &mut c.networks,
+        take_table(&mut codes, "networks"),
+        |o, v| *o.codes_mut() = v,
+    )?;
+    set_values(
+        &mut c.companies,
+        take_table(&mut codes, "companies"),
+        |o, v| *o.codes_mut() = v,
+    )?;
+    set_values(&mut c.lines, take_table(&mut codes, "lines"), |o, v| {
+        *o.codes_mut() = v
+    })?;
+    set_values(&mut c.routes, take_table(&mut codes, "routes"), |o, v| {
+        *o.codes_mut() = v
+    })?;
+    set_values(
+        &mut c.vehicle_journeys,
+        take_table(&mut codes, "vehicle_journeys"),
+        |o, v| *o.codes_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_areas,
+        take_table(&mut codes, "stop_areas"),
+        |o, v| *o.codes_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_points,
+        take_table(&mut codes, "stop_points"),
+        |o, v| *o.codes_mut() = v,
+    )?;
+
+    set_values(
+        &mut c.lines,
+        take_table(&mut object_properties, "lines"),
+        |o, v| *o.properties_mut() = v,
+    )?;
+    set_values(
+        &mut c.routes,
+        take_table(&mut object_properties, "routes"),
+        |o, v| *o.properties_mut() = v,
+    )?;
+    set_values(
+        &mut c.vehicle_journeys,
+        take_table(&mut object_properties, "vehicle_journeys"),
+        |o, v| *o.properties_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_areas,
+        take_table(&mut object_properties, "stop_areas"),
+        |o, v| *o.properties_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_points,
+        take_table(&mut object_properties, "stop_points"),
+        |o, v| *o.properties_mut() = v,
+    )?;
+
+    set_values(
+        &mut c.lines,
+        take_table(&mut comment_links, "lines"),
+        |o, v| *o.comment_links_mut() = v,
+    )?;
+    set_values(
+        &mut c.routes,
+        take_table(&mut comment_links, "routes"),
+        |o, v| *o.comment_links_mut() = v,
+    )?;
+    set_values(
+        &mut c.vehicle_journeys,
+        take_table(&mut comment_links, "vehicle_journeys"),
+        |o, v| *o.comment_links_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_areas,
+        take_table(&mut comment_links, "stop_areas"),
+        |o, v| *o.comment_links_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_points,
+        take_table(&mut comment_links, "stop_points"),
+        |o, v| *o.comment_links_mut() = v,
+    )?;
+    set_values(
+        &mut c.stop_locations,
+        take_table(&mut comment_links, "stop_locations"),
+        |o, v| *o.comment_links_mut() = v,
+    )?;
+
+    set_values(
+        &mut c.lines,
+        line_directions,
+        |line, (forward_direction, backward_direction)| {
+            line.forward_direction = forward_direction;
+            line.backward_direction = backward_direction;
+        },
+    )?;
+    let mut calendars = BTreeMap::new();
+    for (calendar_id, dates) in calendar_dates {
+        let dates = dates
+            .iter()
+            .map(|date| Date::parse_from_str(date, "%Y%m%d"))
+            .collect::<std::result::Result<BTreeSet<_>, _>>()
+            .with_context(|_| format!("Invalid date for the calendar {:?}", calendar_id))?;
+        calendars.insert(calendar_id, dates);
+    }
+    set_values(&mut c.calendars, calendars, |calendar, dates| {
+        calendar.dates = dates
+    })?;
+    let mut vj_stop_times = BTreeMap::new();
+    for (vj_id, json_stop_times) in stop_times {
+        let mut vj_st = Vec::with_capacity(json_stop_times.len());
+        for st in json_stop_times {
+            let stop_point_idx = c.stop_points.get_idx(&st.stop_point_id).ok_or_else(|| {
+                format_err!(
+                    "Unknown stop point {:?} in the stop times of {:?}",
+                    st.stop_point_id,
+                    vj_id
+                )
+            })?;
+            vj_st.push(StopTime {
+                stop_point_idx,
+                sequence: st.sequence,
+                arrival_time: st.arrival_time,
+                departure_time: st.departure_time,
+                boarding_duration: st.boarding_duration,
+                alighting_duration: st.alighting_duration,
+                pickup_type: st.pickup_type,
+                drop_off_type: st.drop_off_type,
+                datetime_estimated: st.datetime_estimated,
+                local_zone_id: st.local_zone_id,
+                precision: st.precision,
+            });
+        }
+        vj_stop_times.insert(vj_id, vj_st);
+    }
+    set_values(&mut c.vehicle_journeys, vj_stop_times, |vj, stop_times| {
+        vj.stop_times = stop_times
+    })?;
+    set_values(&mut c.vehicle_journeys, occupancies, |vj, occupancy| {
+        vj.occupancy = Some(occupancy)
+    })?;
+    set_values(&mut c.stop_points, stop_point_types, |sp, stop_type| {
+        sp.stop_type = stop_type
+    })?;
+    set_values(
+        &mut c.stop_locations,
+        stop_location_types,
+        |sl, stop_type| sl.stop_type = stop_type,
+    )?;
+    set_values(
+        &mut c.pathways,
+        pathway_stop_types,
+        |pathway, (from_stop_type, to_stop_type)| {
+            pathway.from_stop_type = from_stop_type;
+            pathway.to_stop_type = to_stop_type;
+        },
+    )?;
+    c.stop_time_headsigns = stop_time_map(stop_time_headsigns);
+    // keyed by vehicle journey index once the vehicle journeys are read
+    c.restore_stop_time_ids(stop_time_map(stop_time_ids));
+    c.stop_time_comments = stop_time_map(stop_time_comments);
+    c.stop_time_pickup_booking_rules = stop_time_map(stop_time_pickup_booking_rules);
+    c.stop_time_drop_off_booking_rules = stop_time_map(stop_time_drop_off_booking_rules);
+
+    Model::new(c)
+}
+
+#[cfg(test)]
+mod tests {
+    // Fields which are not serialized with their collection, and therefore
+    // stored in a side table of `JsonModel`
+    const SIDE_TABLE_FIELDS: &[(&str, &str)] = &[
+        ("Calendar", "dates"),
+        ("Collections", "stop_time_comments"),
+        ("Collections", "stop_time_drop_off_booking_rules"),
+        ("Collections", "stop_time_headsigns"),
+        ("Collections", "stop_time_ids"),
+        ("Collections", "stop_time_pickup_booking_rules"),
+        ("Company", "codes"),
+        ("Line", "backward_direction"),
+        ("Line", "codes"),
+        ("Line", "comment_links"),
+        ("Line", "forward_direction"),
+        ("Line", "object_properties"),
+        ("Network", "codes"),
+        ("Pathway", "from_stop_type"),
+        ("Pathway", "to_stop_type"),
+        ("Route", "codes"),
+        ("Route", "comment_links"),
+        ("Route", "object_properties"),
+        ("StopArea", "codes"),
+        ("StopArea", "comment_links"),
+        ("StopArea", "object_properties"),
+        ("StopLocation", "comment_links"),
+        ("StopLocation", "stop_type"),
+        ("StopPoint", "codes"),
+        ("StopPoint", "comment_links"),
+        ("StopPoint", "object_properties"),
+        ("StopPoint", "stop_type"),
+        ("VehicleJourney", "codes"),
+        ("VehicleJourney", "comment_links"),
+        ("VehicleJourney", "object_properties"),
+        ("VehicleJourney", "occupancy"),
+        ("VehicleJourney", "stop_times"),
+    ];
+
+    // (struct, field) of the `#[serde(skip)]` fields of a source file
+    fn skipped_fields(source: &str) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        let mut current_struct = "";
+        let mut skipped = false;
+        for line in source.lines().map(str::trim) {
+            if let Some(declaration) = line.strip_prefix("pub struct ") {
+                current_struct = declaration
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .next()
+                    .unwrap_or_default();
+            } else if line == "#[serde(skip)]" || line.starts_with("#[serde(skip,") {
+                skipped = true;
+            } else if skipped {
+                if let Some(declaration) = line.strip_prefix("pub ") {
+                    let field = declaration.split(':').next().unwrap_or_default();
+                    fields.push((current_struct.to_string(), field.to_string()));
+                    skipped = false;
+                }
+            }
+        }
+        fields
+    }
+
+    #[test]
+    fn skipped_fields_are_in_side_tables() {
+        let mut fields = skipped_fields(include_str!("objects.rs"));
+        fields.extend(skipped_fields(include_str!("model.rs")));
+        fields.sort();
+        let expected: Vec<_> = SIDE_TABLE_FIELDS
+            .iter()
+            .map(|(object, field)| (object.to_string(), field.to_string()))
+            .collect();
+        assert_eq!(
+            expected, fields,
+            "each `#[serde(skip)]` field must be stored in a side table of `JsonModel`"
+        );
+    }
+}
@@ -33,6 +33,15 @@
 //! this case, take a look at the [`CONTRIBUTING.md`] for more information on
 //! this feature.
 //!
+//! ## `json`
+//! `json` feature adds [`model::to_json_writer`] and
+//! [`model::from_json_reader`] to save a whole `Model` in a single JSON
+//! document, faster to load than the original feed (cache of parsed feeds for
+//! example). The feature only gates this API: `serde_json` can't be made
+//! optional as it's always a dependency of `transit_model`, needed to read the
+//! `config.json` of the converters (see [`read_utils::read_config`]) and the
+//! patches of [`patch`].
+//!
 //! ## `mutable-model`
 //! This is an experimental feature that allows you to get some abilities to
 //! mutate a `Model`. It might not be completely stable at the moment so use
@@ -46,17 +55,31 @@
 mod utils;
 mod add_prefix;
 pub use add_prefix::{AddPrefix, PrefixConfiguration};
+mod id_generator;
+pub use id_generator::{DefaultIdGenerator, IdGenerator};
 pub mod calendars;
+mod canonicalize_times;
+mod consistency;
 #[macro_use]
 pub mod objects;
 mod enhancers;
 pub mod gtfs;
+mod journey_patterns;
+#[cfg(feature = "json")]
+mod json;
+mod merge_collections;
+mod merge_lines;
 pub mod model;
 #[cfg(feature = "proj")]
 pub mod netex_france;
 pub mod netex_utils;
 pub mod ntfs;
+pub mod patch;
 pub mod read_utils;
+pub mod reader;
+mod remove_contributor;
+mod restrict_to_stop_areas;
+mod split_stop_area;
 #[doc(hidden)]
 pub mod test_utils;
 pub mod transfers;
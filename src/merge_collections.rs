@@ -0,0 +1,166 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Merge of the collections of several sources.
+
+use crate::{
+    model::Collections,
+    objects::{StopPoint, VehicleJourney},
+    Result,
+};
+use failure::format_err;
+use std::collections::HashMap;
+use typed_index_collection::{CollectionWithId, Idx};
+
+/// Merge `other` into `collections`, see [Collections::try_merge].
+pub(crate) fn try_merge(collections: &mut Collections, other: Collections) -> Result<()> {
+    // Destructuring (without `..`) makes sure a new collection can't be
+    // added without deciding here how it is merged.
+    let Collections {
+        contributors,
+        datasets,
+        networks,
+        commercial_modes,
+        lines,
+        routes,
+        vehicle_journeys,
+        frequencies,
+        physical_modes,
+        stop_areas,
+        stop_points,
+        stop_locations,
+        feed_infos,
+        calendars,
+        companies,
+        comments,
+        booking_rules,
+        equipments,
+        transfers,
+        trip_properties,
+        geometries,
+        admin_stations,
+        stop_time_headsigns,
+        stop_time_ids,
+        stop_time_comments,
+        stop_time_pickup_booking_rules,
+        stop_time_drop_off_booking_rules,
+        prices_v1,
+        od_fares_v1,
+        fares_v1,
+        tickets,
+        ticket_uses,
+        ticket_prices,
+        ticket_use_perimeters,
+        ticket_use_restrictions,
+        pathways,
+        levels,
+        grid_calendars,
+        grid_exception_dates,
+        grid_periods,
+        grid_rel_calendar_line,
+    } = other;
+    collections.contributors.try_merge(contributors)?;
+    collections.datasets.try_merge(datasets)?;
+    collections.networks.try_merge(networks)?;
+    // Commercial and physical modes have standardized NTFS identifiers
+    // shared by every source, the first one is kept
+    collections.commercial_modes.merge(commercial_modes);
+    collections.physical_modes.merge(physical_modes);
+    collections.lines.try_merge(lines)?;
+    collections.routes.try_merge(routes)?;
+    collections.stop_areas.try_merge(stop_areas)?;
+
+    // The stop times reference the stop points by index, which are not the
+    // same once merged
+    let stop_point_ids: HashMap<Idx<StopPoint>, String> = stop_points
+        .iter()
+        .map(|(idx, stop_point)| (idx, stop_point.id.clone()))
+        .collect();
+    collections.stop_points.try_merge(stop_points)?;
+    // Same for the stop time identifiers, keyed by vehicle journey index
+    let stop_time_ids: Vec<((String, u32), String)> = stop_time_ids
+        .into_iter()
+        .map(|((vj_idx, sequence), stop_time_id)| {
+            (
+                (vehicle_journeys[vj_idx].id.clone(), sequence),
+                stop_time_id,
+            )
+        })
+        .collect();
+    let mut vehicle_journeys: Vec<VehicleJourney> = vehicle_journeys.into_vec();
+    for vj in &mut vehicle_journeys {
+        for stop_time in &mut vj.stop_times {
+            let stop_point_id = &stop_point_ids[&stop_time.stop_point_idx];
+            stop_time.stop_point_idx = collections
+                .stop_points
+                .get_idx(stop_point_id)
+                .ok_or_else(|| format_err!("stop point {} not found", stop_point_id))?;
+        }
+    }
+    collections
+        .vehicle_journeys
+        .try_merge(CollectionWithId::new(vehicle_journeys)?)?;
+    collections.frequencies.merge(frequencies);
+    collections.stop_locations.try_merge(stop_locations)?;
+    for (key, value) in feed_infos {
+        collections.feed_infos.entry(key).or_insert(value);
+    }
+    collections.calendars.try_merge(calendars)?;
+    collections.companies.try_merge(companies)?;
+    collections.comments.try_merge(comments)?;
+    collections.booking_rules.try_merge(booking_rules)?;
+    collections.equipments.try_merge(equipments)?;
+    collections.transfers.merge(transfers);
+    collections.trip_properties.try_merge(trip_properties)?;
+    collections.geometries.try_merge(geometries)?;
+    collections.admin_stations.merge(admin_stations);
+    collections.stop_time_headsigns.extend(stop_time_headsigns);
+    for ((vj_id, sequence), stop_time_id) in stop_time_ids {
+        let vj_idx = collections
+            .vehicle_journeys
+            .get_idx(&vj_id)
+            .ok_or_else(|| format_err!("vehicle journey {} not found", vj_id))?;
+        collections
+            .stop_time_ids
+            .insert((vj_idx, sequence), stop_time_id);
+    }
+    collections.stop_time_comments.extend(stop_time_comments);
+    collections
+        .stop_time_pickup_booking_rules
+        .extend(stop_time_pickup_booking_rules);
+    collections
+        .stop_time_drop_off_booking_rules
+        .extend(stop_time_drop_off_booking_rules);
+    collections.prices_v1.merge(prices_v1);
+    collections.od_fares_v1.merge(od_fares_v1);
+    collections.fares_v1.merge(fares_v1);
+    collections.tickets.try_merge(tickets)?;
+    collections.ticket_uses.try_merge(ticket_uses)?;
+    collections.ticket_prices.merge(ticket_prices);
+    collections
+        .ticket_use_perimeters
+        .merge(ticket_use_perimeters);
+    collections
+        .ticket_use_restrictions
+        .merge(ticket_use_restrictions);
+    collections.pathways.try_merge(pathways)?;
+    collections.levels.try_merge(levels)?;
+    collections.grid_calendars.try_merge(grid_calendars)?;
+    collections.grid_exception_dates.merge(grid_exception_dates);
+    collections.grid_periods.merge(grid_periods);
+    collections
+        .grid_rel_calendar_line
+        .merge(grid_rel_calendar_line);
+    Ok(())
+}
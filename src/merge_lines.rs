@@ -0,0 +1,162 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Merge of the lines representing the same service.
+
+use crate::{
+    model::Collections,
+    objects::{Line, ObjectType},
+    Result,
+};
+use failure::bail;
+use log::info;
+use std::collections::{BTreeMap, HashSet};
+
+/// Merge the line `source_id` into the line `target_id`, see
+/// [Collections::merge_lines].
+pub(crate) fn merge_lines(
+    collections: &mut Collections,
+    source_id: &str,
+    target_id: &str,
+) -> Result<()> {
+    if source_id == target_id {
+        bail!("cannot merge line {} into itself", source_id);
+    }
+    let source = match collections.lines.get(source_id) {
+        Some(source) => source,
+        None => bail!("line {} not found", source_id),
+    };
+    if !collections.lines.contains_id(target_id) {
+        bail!("line {} not found", target_id);
+    }
+    let route_idxs: Vec<_> = collections
+        .routes
+        .iter()
+        .filter(|(_, route)| route.line_id == source_id)
+        .map(|(idx, _)| idx)
+        .collect();
+    if route_idxs.is_empty() {
+        bail!(
+            "line {} has no route to merge into line {}",
+            source_id,
+            target_id
+        );
+    }
+    let codes = source.codes.clone();
+    let comment_links = source.comment_links.clone();
+
+    for route_idx in route_idxs {
+        collections.routes.index_mut(route_idx).line_id = target_id.to_string();
+    }
+    {
+        let mut target = collections.lines.get_mut(target_id).unwrap();
+        target.codes.extend(codes);
+        target.comment_links.extend(comment_links);
+    }
+    for perimeter in collections.ticket_use_perimeters.values_mut() {
+        if perimeter.object_type == ObjectType::Line && perimeter.object_id == source_id {
+            perimeter.object_id = target_id.to_string();
+        }
+    }
+    for grid_rel_calendar_line in collections.grid_rel_calendar_line.values_mut() {
+        if grid_rel_calendar_line.line_id == source_id {
+            grid_rel_calendar_line.line_id = target_id.to_string();
+        }
+    }
+    collections.lines.retain(|line| line.id != source_id);
+    Ok(())
+}
+
+// Attributes of the line `source` which differ from the ones of the line
+// `target`, and are therefore dropped when merging `source` into `target`
+fn discarded_attributes(source: &Line, target: &Line) -> Vec<String> {
+    let mut discarded = Vec::new();
+    macro_rules! compare {
+        ($($name:expr => $field:ident),* $(,)?) => {
+            $(
+                if source.$field != target.$field {
+                    discarded.push(format!("{}={:?}", $name, source.$field));
+                }
+            )*
+        };
+    }
+    compare!(
+        "line_code" => code,
+        "line_name" => name,
+        "forward_line_name" => forward_name,
+        "forward_direction" => forward_direction,
+        "backward_line_name" => backward_name,
+        "backward_direction" => backward_direction,
+        "line_color" => color,
+        "line_text_color" => text_color,
+        "line_sort_order" => sort_order,
+        "network_id" => network_id,
+        "commercial_mode_id" => commercial_mode_id,
+        "geometry_id" => geometry_id,
+        "line_opening_time" => opening_time,
+        "line_closing_time" => closing_time,
+        "object_properties" => object_properties,
+    );
+    discarded
+}
+
+/// Merge the lines having the same key, see [Collections::merge_lines_by].
+pub(crate) fn merge_lines_by<F, K>(collections: &mut Collections, f: F) -> Result<()>
+where
+    F: Fn(&Line) -> K,
+    K: Ord,
+{
+    let lines_with_routes: HashSet<&str> = collections
+        .routes
+        .values()
+        .map(|route| route.line_id.as_str())
+        .collect();
+    let mut lines_by_key = BTreeMap::<K, Vec<String>>::new();
+    for line in collections
+        .lines
+        .values()
+        .filter(|line| lines_with_routes.contains(line.id.as_str()))
+    {
+        lines_by_key
+            .entry(f(line))
+            .or_insert_with(Vec::new)
+            .push(line.id.clone());
+    }
+    for (_, mut line_ids) in lines_by_key {
+        line_ids.sort_unstable();
+        let mut line_ids = line_ids.into_iter();
+        let target_id = match line_ids.next() {
+            Some(target_id) => target_id,
+            None => continue,
+        };
+        for source_id in line_ids {
+            let discarded = discarded_attributes(
+                collections.lines.get(&source_id).unwrap(),
+                collections.lines.get(&target_id).unwrap(),
+            );
+            if discarded.is_empty() {
+                info!("line {} merged into line {}", source_id, target_id);
+            } else {
+                info!(
+                    "line {} merged into line {}, discarding {}",
+                    source_id,
+                    target_id,
+                    discarded.join(", ")
+                );
+            }
+            merge_lines(collections, &source_id, &target_id)?;
+        }
+    }
+    Ok(())
+}
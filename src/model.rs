@@ -14,24 +14,41 @@
 
 //! Definition of the navitia transit model.
 
-use crate::{enhancers, objects::*, Error, Result};
+use crate::{
+    add_prefix, canonicalize_times, consistency, enhancers, journey_patterns, merge_collections,
+    merge_lines,
+    objects::*,
+    patch::{self, ModelPatch},
+    remove_contributor, restrict_to_stop_areas, split_stop_area, transfers, validity_period,
+    AddPrefix, Error, PrefixConfiguration, Result,
+};
 use chrono::NaiveDate;
 use derivative::Derivative;
 use failure::{bail, format_err};
 use geo::algorithm::centroid::Centroid;
-use geo::MultiPoint;
-use log::{debug, warn};
+use geo::{Coordinate, Geometry as GeoGeometry, LineString, MultiLineString, MultiPoint};
+use log::{debug, info, warn};
 use relational_types::{GetCorresponding, IdxSet, ManyToMany, OneToMany, Relation};
 use serde::{Deserialize, Serialize};
 use skip_error::skip_error_and_log;
 use std::{
     cmp::{self, Ordering, Reverse},
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     convert::TryFrom,
     ops,
 };
 use typed_index_collection::{Collection, CollectionWithId, Id, Idx};
 
+#[cfg(feature = "json")]
+pub use crate::json::{from_json_reader, to_json_writer};
+pub use crate::{
+    canonicalize_times::{OvernightTimes, CALENDAR_SHIFT_COMMENT_ID},
+    consistency::InconsistencyError,
+    journey_patterns::JourneyPatternKey,
+    remove_contributor::{ContributorRemoval, RemovedObjects},
+    restrict_to_stop_areas::PartialJourneys,
+};
+
 /// Physical mode for Air
 pub const AIR_PHYSICAL_MODE: &str = "Air";
 /// Physical mode for Bike
@@ -65,6 +82,15 @@ pub const TRAIN_PHYSICAL_MODE: &str = "Train";
 /// Physical mode for Tramway
 pub const TRAMWAY_PHYSICAL_MODE: &str = "Tramway";
 
+/// Options of [Collections::sanitize_with_configuration].
+#[derive(Debug, Clone, Default)]
+pub struct SanitizeConfiguration {
+    /// Remove and report the vehicle journeys whose calendar has no active
+    /// date, see [Collections::validate_calendar_coverage]. Disabled by
+    /// default.
+    pub validate_calendar_coverage: bool,
+}
+
 /// The set of collections representing the model.
 #[derive(Derivative, Serialize, Deserialize, Debug)]
 #[derivative(Default)]
@@ -86,6 +112,7 @@ pub struct Collections {
     pub calendars: CollectionWithId<Calendar>,
     pub companies: CollectionWithId<Company>,
     pub comments: CollectionWithId<Comment>,
+    pub booking_rules: CollectionWithId<BookingRule>,
     pub equipments: CollectionWithId<Equipment>,
     pub transfers: Collection<Transfer>,
     pub trip_properties: CollectionWithId<TripProperty>,
@@ -95,11 +122,17 @@ pub struct Collections {
     //HashMap<(vehicle_journey_id, stop_sequence), headsign>,
     pub stop_time_headsigns: HashMap<(String, u32), String>,
     #[serde(skip)]
-    //HashMap<(vehicle_journey_id, stop_sequence), stop_time_id>,
-    pub stop_time_ids: HashMap<(String, u32), String>,
+    //HashMap<(vehicle_journey_idx, stop_sequence), stop_time_id>,
+    pub stop_time_ids: HashMap<(Idx<VehicleJourney>, u32), String>,
     #[serde(skip)]
     //HashMap<(vehicle_journey_id, stop_sequence), comment_id>
     pub stop_time_comments: HashMap<(String, u32), String>,
+    #[serde(skip)]
+    //HashMap<(vehicle_journey_id, stop_sequence), booking_rule_id>
+    pub stop_time_pickup_booking_rules: HashMap<(String, u32), String>,
+    #[serde(skip)]
+    //HashMap<(vehicle_journey_id, stop_sequence), booking_rule_id>
+    pub stop_time_drop_off_booking_rules: HashMap<(String, u32), String>,
     pub prices_v1: Collection<PriceV1>,
     pub od_fares_v1: Collection<OdFareV1>,
     pub fares_v1: Collection<FareV1>,
@@ -116,6 +149,19 @@ pub struct Collections {
     pub grid_rel_calendar_line: Collection<GridRelCalendarLine>,
 }
 
+// A coordinate is valid if it's not (0, 0) and within the WGS84 bounds
+pub(crate) fn is_valid_coord(coord: &Coord) -> bool {
+    *coord != Coord::default()
+        && coord.lon.is_finite()
+        && coord.lat.is_finite()
+        && (-180.0..=180.0).contains(&coord.lon)
+        && (-90.0..=90.0).contains(&coord.lat)
+}
+
+fn log_object_removed(object_type: &str, id: &str) {
+    debug!("{} with ID {} has been removed", object_type, id);
+}
+
 impl Collections {
     /// Restrict the validity period of the current `Collections` with the start_date and end_date
     pub fn restrict_period(&mut self, start_date: NaiveDate, end_date: NaiveDate) -> Result<()> {
@@ -138,12 +184,88 @@ impl Collections {
         Ok(())
     }
 
+    /// The stop time identifiers are keyed by vehicle journey index, which
+    /// changes when vehicle journeys are removed or reordered: take them by
+    /// vehicle journey identifier before and restore them with
+    /// `restore_stop_time_ids` after.
+    pub(crate) fn take_stop_time_ids(&mut self) -> HashMap<(String, u32), String> {
+        let vehicle_journeys = &self.vehicle_journeys;
+        self.stop_time_ids
+            .drain()
+            .map(|((vj_idx, sequence), stop_time_id)| {
+                (
+                    (vehicle_journeys[vj_idx].id.clone(), sequence),
+                    stop_time_id,
+                )
+            })
+            .collect()
+    }
+
+    /// Restore the stop time identifiers taken by `take_stop_time_ids`, the
+    /// ones of the vehicle journeys which don't exist anymore are dropped.
+    pub(crate) fn restore_stop_time_ids(&mut self, stop_time_ids: HashMap<(String, u32), String>) {
+        let vehicle_journeys = &self.vehicle_journeys;
+        self.stop_time_ids = stop_time_ids
+            .into_iter()
+            .filter_map(|((vj_id, sequence), stop_time_id)| {
+                vehicle_journeys
+                    .get_idx(&vj_id)
+                    .map(|vj_idx| ((vj_idx, sequence), stop_time_id))
+            })
+            .collect();
+    }
+
+    /// Remove the vehicle journeys whose calendar has no active date (for
+    /// example after `restrict_period`) and return their sorted identifiers.
+    ///
+    /// This is an optional step of `sanitize_with_configuration`, which then
+    /// removes the objects not used anymore (routes, lines, ...).
+    pub fn validate_calendar_coverage(&mut self) -> Vec<String> {
+        let calendars = &self.calendars;
+        let mut removed_vj_ids: Vec<String> = self
+            .vehicle_journeys
+            .values()
+            .filter(|vj| match calendars.get(&vj.service_id) {
+                Some(calendar) if calendar.dates.is_empty() => {
+                    warn!(
+                        "vehicle journey {} has been removed because its calendar has no active date",
+                        vj.id
+                    );
+                    true
+                }
+                Some(_) => false,
+                None => {
+                    warn!(
+                        "vehicle journey {} has been removed because its calendar {} does not exist",
+                        vj.id, vj.service_id
+                    );
+                    true
+                }
+            })
+            .map(|vj| vj.id.clone())
+            .collect();
+        removed_vj_ids.sort_unstable();
+        let stop_time_ids = self.take_stop_time_ids();
+        self.vehicle_journeys
+            .retain(|vj| removed_vj_ids.binary_search(&vj.id).is_err());
+        self.restore_stop_time_ids(stop_time_ids);
+        removed_vj_ids
+    }
+
     /// Keep the collections consistent for the new model by purging unreferenced data by
     /// calendars
     pub fn sanitize(&mut self) -> Result<()> {
-        fn log_object_removed(object_type: &str, id: &str) {
-            debug!("{} with ID {} has been removed", object_type, id);
-        }
+        self.sanitize_with_configuration(&SanitizeConfiguration::default())
+            .map(|_| ())
+    }
+
+    /// Same as `sanitize`, with the optional steps of the configuration.
+    /// Returns the sorted identifiers of the vehicle journeys removed by
+    /// `validate_calendar_coverage` (empty if this step is disabled).
+    pub fn sanitize_with_configuration(
+        &mut self,
+        configuration: &SanitizeConfiguration,
+    ) -> Result<Vec<String>> {
         fn log_predicate<'a, T, F>(object_type: &'a str, mut f: F) -> impl 'a + FnMut(&T) -> bool
         where
             T: Id<T>,
@@ -169,6 +291,14 @@ impl Collections {
             *source = Collection::new(dedup);
         }
 
+        let removed_vj_ids = if configuration.validate_calendar_coverage {
+            self.validate_calendar_coverage()
+        } else {
+            Vec::new()
+        };
+
+        self.equipment_deduplication();
+
         self.calendars
             .retain(log_predicate("Calendar", |cal: &Calendar| {
                 !cal.dates.is_empty()
@@ -188,6 +318,7 @@ impl Collections {
 
         let stop_point_id_to_old_idx = self.stop_points.get_id_to_idx().clone();
 
+        let mut stop_time_ids = self.take_stop_time_ids();
         let mut vjs: Vec<VehicleJourney> = self.vehicle_journeys.take();
         vjs.retain(|vj| {
             if vj.stop_times.is_empty() {
@@ -271,11 +402,21 @@ impl Collections {
             })
             .collect::<Vec<_>>();
 
+        // the stop locations have already been taken out of the collection
+        let stop_location_ids: HashSet<&str> =
+            stop_locations.iter().map(|sl| sl.id.as_str()).collect();
         let pathways = self
             .pathways
             .take()
             .into_iter()
             .filter(|pw| {
+                let stop_exists = |stop_id: &str| {
+                    self.stop_points.contains_id(stop_id) || stop_location_ids.contains(stop_id)
+                };
+                if !stop_exists(&pw.from_stop_id) || !stop_exists(&pw.to_stop_id) {
+                    log_object_removed("Pathway", &pw.id);
+                    return false;
+                }
                 let mut insert_if_used = |stop_type: &StopType, stop_id: &String| {
                     if *stop_type == StopType::BoardingArea || *stop_type == StopType::Point {
                         stop_points_used.insert(stop_id.clone());
@@ -369,6 +510,9 @@ impl Collections {
                     if let Some(level_id) = &sa.level_id {
                         level_id_used.insert(level_id.clone());
                     }
+                    if let Some(equipment_id) = &sa.equipment_id {
+                        equipments_used.insert(equipment_id.clone());
+                    }
                     comments_used.extend(&mut sa.comment_links.iter().map(|cl| cl.to_string()));
                     true
                 } else {
@@ -408,15 +552,37 @@ impl Collections {
         self.stop_areas = CollectionWithId::new(stop_areas)?;
         self.routes = CollectionWithId::new(routes)?;
         let vehicle_journeys_used: HashSet<String> = vjs.iter().map(|vj| vj.id.clone()).collect();
+        let stop_times_used: HashSet<(String, u32)> = vjs
+            .iter()
+            .flat_map(|vj| {
+                vj.stop_times
+                    .iter()
+                    .map(move |st| (vj.id.clone(), st.sequence))
+            })
+            .collect();
         self.vehicle_journeys = CollectionWithId::new(vjs)?;
         self.stop_locations = CollectionWithId::new(stop_locations)?;
-        self.stop_time_comments.retain(|(vj_id, _), comment_id| {
-            vehicle_journeys_used.contains(vj_id) && comments_used.contains(comment_id)
+        self.stop_time_comments.retain(|stop_time, comment_id| {
+            stop_times_used.contains(stop_time) && comments_used.contains(comment_id)
         });
-        self.stop_time_ids
-            .retain(|(vj_id, _), _| vehicle_journeys_used.contains(vj_id));
+        stop_time_ids.retain(|stop_time, _| stop_times_used.contains(stop_time));
+        self.restore_stop_time_ids(stop_time_ids);
         self.stop_time_headsigns
             .retain(|(vj_id, _), _| vehicle_journeys_used.contains(vj_id));
+        self.stop_time_pickup_booking_rules
+            .retain(|stop_time, _| stop_times_used.contains(stop_time));
+        self.stop_time_drop_off_booking_rules
+            .retain(|stop_time, _| stop_times_used.contains(stop_time));
+        let booking_rules_used: HashSet<&str> = self
+            .stop_time_pickup_booking_rules
+            .values()
+            .chain(self.stop_time_drop_off_booking_rules.values())
+            .map(String::as_str)
+            .collect();
+        self.booking_rules.retain(log_predicate(
+            "BookingRule",
+            |booking_rule: &BookingRule| booking_rules_used.contains(booking_rule.id.as_str()),
+        ));
         self.grid_rel_calendar_line
             .retain(|grid_rel_calendar_line| {
                 line_ids_used.contains(&grid_rel_calendar_line.line_id)
@@ -479,6 +645,7 @@ impl Collections {
         self.levels
             .retain(|level| level_id_used.contains(&level.id));
         self.calendars.retain(|c| calendars_used.contains(&c.id));
+        self.remove_dangling_comment_links();
 
         dedup_collection(&mut self.frequencies);
         dedup_collection(&mut self.transfers);
@@ -493,7 +660,7 @@ impl Collections {
         dedup_collection(&mut self.grid_periods);
         dedup_collection(&mut self.grid_rel_calendar_line);
 
-        Ok(())
+        Ok(removed_vj_ids)
     }
 
     /// Sets the opening and closing times of lines (if they are missing).
@@ -847,9 +1014,12 @@ impl Collections {
         self.vehicle_journeys = CollectionWithId::new(vehicle_journeys).unwrap();
     }
 
-    /// Many calendars are identical and can be deduplicate
+    /// Many calendars are identical and can be deduplicate.
+    /// Vehicle journeys are moved to the first calendar (by identifier) with
+    /// the same dates, and the calendars which are not used anymore are removed.
     pub fn calendar_deduplication(&mut self) {
         let mut calendars_used: Vec<Calendar> = vec![];
+        let stop_time_ids = self.take_stop_time_ids();
         let mut vehicle_journeys = self.vehicle_journeys.take();
         vehicle_journeys.sort_unstable_by(|vj1, vj2| vj1.service_id.cmp(&vj2.service_id));
         for vehicle_journey in &mut vehicle_journeys {
@@ -863,9 +1033,16 @@ impl Collections {
                 }
             }
         }
-        self.calendars
-            .retain(|calendar| calendars_used.contains(calendar));
+        self.calendars.retain(|calendar| {
+            if calendars_used.contains(calendar) {
+                true
+            } else {
+                log_object_removed("Calendar", &calendar.id);
+                false
+            }
+        });
         self.vehicle_journeys = CollectionWithId::new(vehicle_journeys).unwrap();
+        self.restore_stop_time_ids(stop_time_ids);
     }
 
     /// Some comments are identical and can be deduplicated
@@ -919,6 +1096,73 @@ impl Collections {
         }
     }
 
+    /// Merge the equipments having the same values (identifier apart) and
+    /// replace the references to the duplicates by the equipment with the
+    /// smallest identifier.
+    pub fn equipment_deduplication(&mut self) {
+        let mut equipments: Vec<&Equipment> = self.equipments.values().collect();
+        equipments.sort_unstable_by(|e1, e2| e1.id.cmp(&e2.id));
+        let mut duplicate2ref = BTreeMap::<String, String>::new();
+        let mut map_ref = HashMap::<Equipment, &str>::new();
+        for equipment in equipments {
+            let similarity_key = Equipment {
+                id: String::new(),
+                ..equipment.clone()
+            };
+            if let Some(ref_id) = map_ref.get(&similarity_key) {
+                duplicate2ref.insert(equipment.id.clone(), ref_id.to_string());
+            } else {
+                map_ref.insert(similarity_key, &equipment.id);
+            }
+        }
+        if duplicate2ref.is_empty() {
+            return;
+        }
+        info!(
+            "{} duplicated equipments have been merged",
+            duplicate2ref.len()
+        );
+
+        fn replace(equipment_id: &mut Option<String>, duplicate2ref: &BTreeMap<String, String>) {
+            if let Some(ref_id) = equipment_id.as_ref().and_then(|id| duplicate2ref.get(id)) {
+                *equipment_id = Some(ref_id.clone());
+            }
+        }
+        fn replace_in_collection<T: Id<T>>(
+            collection: &mut CollectionWithId<T>,
+            equipment_id: fn(&mut T) -> &mut Option<String>,
+            duplicate2ref: &BTreeMap<String, String>,
+        ) {
+            let indexes: Vec<_> = collection.iter().map(|(idx, _)| idx).collect();
+            for index in indexes {
+                replace(
+                    equipment_id(&mut *collection.index_mut(index)),
+                    duplicate2ref,
+                );
+            }
+        }
+        replace_in_collection(
+            &mut self.stop_points,
+            |stop_point| &mut stop_point.equipment_id,
+            &duplicate2ref,
+        );
+        replace_in_collection(
+            &mut self.stop_areas,
+            |stop_area| &mut stop_area.equipment_id,
+            &duplicate2ref,
+        );
+        replace_in_collection(
+            &mut self.stop_locations,
+            |stop_location| &mut stop_location.equipment_id,
+            &duplicate2ref,
+        );
+        for transfer in self.transfers.values_mut() {
+            replace(&mut transfer.equipment_id, &duplicate2ref);
+        }
+        self.equipments
+            .retain(|equipment| !duplicate2ref.contains_key(&equipment.id));
+    }
+
     /// Remove comments with empty message from the model
     pub fn clean_comments(&mut self) {
         fn remove_comment<T: Id<T> + CommentLinks>(
@@ -959,6 +1203,56 @@ impl Collections {
         }
     }
 
+    // Remove the links to comments which don't exist (anymore), for example
+    // after the removal of some comments.
+    fn remove_dangling_comment_links(&mut self) {
+        fn remove_links<T: Id<T> + CommentLinks>(
+            object_type: &str,
+            collection: &mut CollectionWithId<T>,
+            comments: &CollectionWithId<Comment>,
+        ) {
+            let object_idxs: Vec<Idx<T>> = collection
+                .iter()
+                .filter(|(_, object)| {
+                    object
+                        .comment_links()
+                        .iter()
+                        .any(|comment_id| !comments.contains_id(comment_id))
+                })
+                .map(|(idx, _)| idx)
+                .collect();
+            for object_idx in object_idxs {
+                let mut object = collection.index_mut(object_idx);
+                let dangling_comment_ids: Vec<String> = object
+                    .comment_links()
+                    .iter()
+                    .filter(|comment_id| !comments.contains_id(comment_id))
+                    .cloned()
+                    .collect();
+                for comment_id in dangling_comment_ids {
+                    log_object_removed(
+                        &format!("{} {} link to comment", object_type, object.id()),
+                        &comment_id,
+                    );
+                    object.comment_links_mut().remove(&comment_id);
+                }
+            }
+        }
+        remove_links("Line", &mut self.lines, &self.comments);
+        remove_links("Route", &mut self.routes, &self.comments);
+        remove_links(
+            "Vehicle Journey",
+            &mut self.vehicle_journeys,
+            &self.comments,
+        );
+        remove_links("Stop Area", &mut self.stop_areas, &self.comments);
+        remove_links("Stop Point", &mut self.stop_points, &self.comments);
+        remove_links("Stop Location", &mut self.stop_locations, &self.comments);
+        let comments = &self.comments;
+        self.stop_time_comments
+            .retain(|_, comment_id| comments.contains_id(comment_id));
+    }
+
     /// From comment collection only, return a map of the similar comments.
     ///
     /// Result: duplicates (comments to be removed) are mapped to their similar
@@ -1236,6 +1530,39 @@ impl Collections {
         check_and_fix_object_geometries!(self.stop_areas);
     }
 
+    /// Fill the missing CO2 emissions of the physical modes from a built-in
+    /// table of default values (Bus, Coach, Metro, ...).
+    /// Explicitly provided values are preserved.
+    pub fn enrich_co2_emissions(&mut self) {
+        enhancers::enrich_co2_emissions(self);
+    }
+
+    /// Merge the collections of another source into this one. The objects
+    /// with an identifier must not exist in both (use `prefix` beforehand),
+    /// except the commercial and physical modes whose first version is kept.
+    /// Each dataset keeps its own validity period, and the `feed_infos`
+    /// already set are not overridden.
+    pub fn try_merge(&mut self, other: Collections) -> Result<()> {
+        merge_collections::try_merge(self, other)
+    }
+
+    /// Compute the validity period of each dataset from the calendars of its
+    /// own vehicle journeys.
+    /// See `validity_period::compute_datasets_validity_periods`.
+    ///
+    /// The readers compute the period of the single dataset they create, and
+    /// `restrict_period` narrows the existing periods, so this is only needed
+    /// when the vehicle journeys of several datasets are gathered in the same
+    /// `Collections` by hand, or when the periods read don't match the
+    /// calendars anymore.
+    pub fn compute_datasets_validity_periods(&mut self) {
+        validity_period::compute_datasets_validity_periods(
+            &mut self.datasets,
+            &self.vehicle_journeys,
+            &self.calendars,
+        );
+    }
+
     /// Calculate the validity period in the 'Model'.
     /// The calculation is based on the minimum start date and the maximum end
     /// date of all the datasets.
@@ -1253,6 +1580,358 @@ impl Collections {
             bail!("Cannot calculate validity period because there is no dataset")
         }
     }
+
+    /// Group the vehicle journeys by journey pattern.
+    ///
+    /// Two vehicle journeys share a journey pattern if they serve the same
+    /// succession of stop points with the same pickup and drop off types;
+    /// times are ignored. The vehicle journeys of each pattern are sorted by
+    /// identifier.
+    pub fn journey_patterns(&self) -> BTreeMap<JourneyPatternKey, Vec<Idx<VehicleJourney>>> {
+        journey_patterns::journey_patterns(self)
+    }
+
+    /// Set `journey_pattern_id` on the vehicle journeys which don't have one,
+    /// using the patterns computed by `journey_patterns()`.
+    /// The generated identifiers are `journey_pattern:<n>`, `n` being the
+    /// position of the pattern in the ordered patterns.
+    pub fn enhance_journey_pattern_ids(&mut self) {
+        journey_patterns::enhance_journey_pattern_ids(self)
+    }
+
+    /// Count the objects of every collection with identifiers by prefix.
+    /// The prefix is the part of the identifier before the first `:`
+    /// separator; identifiers without separator are counted with an empty prefix.
+    ///
+    /// Useful to check that all the collections have been prefixed
+    /// after a merge.
+    ///
+    /// ```
+    /// # use transit_model::model::Collections;
+    /// # use transit_model::objects::Line;
+    /// # use typed_index_collection::CollectionWithId;
+    /// let collections = Collections {
+    ///     lines: CollectionWithId::new(vec![
+    ///         Line { id: "A:line".into(), ..Default::default() },
+    ///         Line { id: "line".into(), ..Default::default() },
+    ///     ])
+    ///     .unwrap(),
+    ///     ..Default::default()
+    /// };
+    /// let prefixes = collections.distinct_prefixes();
+    /// assert_eq!(Some(&1), prefixes.get("A"));
+    /// assert_eq!(Some(&1), prefixes.get(""));
+    /// ```
+    pub fn distinct_prefixes(&self) -> BTreeMap<&str, usize> {
+        add_prefix::distinct_prefixes(self)
+    }
+
+    /// Replace the prefix `old` by `new` in all the identifiers and references
+    /// of an already prefixed dataset (e.g. `OLD:line:1` becomes
+    /// `NEW:line:1`), including the objects without identifier like
+    /// transfers or fares. The objects concerned are the ones prefixed by
+    /// [`AddPrefix::prefix`](crate::AddPrefix::prefix); the identifiers which
+    /// do not start with `old:` are kept unchanged.
+    ///
+    /// ```
+    /// # use transit_model::{model::Collections, objects::Line, AddPrefix, PrefixConfiguration};
+    /// # use typed_index_collection::CollectionWithId;
+    /// let mut collections = Collections::default();
+    /// collections.lines = CollectionWithId::from(Line {
+    ///     id: "line:1".to_string(),
+    ///     ..Default::default()
+    /// });
+    /// let mut prefix_conf = PrefixConfiguration::default();
+    /// prefix_conf.set_data_prefix("OLD");
+    /// collections.prefix(&prefix_conf);
+    ///
+    /// collections.rename_prefix("OLD", "NEW");
+    /// assert!(collections.lines.contains_id("NEW:line:1"));
+    /// ```
+    pub fn rename_prefix(&mut self, old: &str, new: &str) {
+        self.prefix(&PrefixConfiguration::renaming(old, new));
+    }
+
+    /// Reorder the objects of every collection with identifiers by identifier,
+    /// so that the written files don't depend on the order in which the
+    /// objects have been inserted (e.g. after a merge).
+    /// The stop points referenced by the stop times are updated accordingly.
+    pub fn sort_by_id(&mut self) {
+        fn sort_collection<T: Id<T>>(collection: &mut CollectionWithId<T>) {
+            let mut objects = collection.take();
+            objects.sort_unstable_by(|o1, o2| o1.id().cmp(o2.id()));
+            // identifiers were already unique in the collection
+            *collection = CollectionWithId::new(objects).unwrap();
+        }
+        macro_rules! sort_collections {
+            ($($collection:expr),*) => {
+                $(sort_collection(&mut $collection);)*
+            };
+        }
+
+        let stop_point_id_to_old_idx = self.stop_points.get_id_to_idx().clone();
+        let stop_time_ids = self.take_stop_time_ids();
+        sort_collections!(
+            self.contributors,
+            self.datasets,
+            self.networks,
+            self.commercial_modes,
+            self.lines,
+            self.routes,
+            self.vehicle_journeys,
+            self.physical_modes,
+            self.stop_areas,
+            self.stop_points,
+            self.stop_locations,
+            self.calendars,
+            self.companies,
+            self.comments,
+            self.booking_rules,
+            self.equipments,
+            self.trip_properties,
+            self.geometries,
+            self.tickets,
+            self.ticket_uses,
+            self.pathways,
+            self.levels,
+            self.grid_calendars
+        );
+        let stop_point_old_idx_to_new_idx: HashMap<Idx<StopPoint>, Idx<StopPoint>> = self
+            .stop_points
+            .iter()
+            .map(|(new_idx, stop_point)| (stop_point_id_to_old_idx[&stop_point.id], new_idx))
+            .collect();
+        let mut vehicle_journeys = self.vehicle_journeys.take();
+        for vj in vehicle_journeys.iter_mut() {
+            for st in vj.stop_times.iter_mut() {
+                st.stop_point_idx = stop_point_old_idx_to_new_idx[&st.stop_point_idx];
+            }
+        }
+        self.vehicle_journeys = CollectionWithId::new(vehicle_journeys).unwrap();
+        self.restore_stop_time_ids(stop_time_ids);
+    }
+
+    /// Generate the walking transfers between the stop points distant of at
+    /// most `max_distance_m` meters, including the transfer of each stop
+    /// point to itself, as [transfers::generates_transfers] does.
+    ///
+    /// The `min_transfer_time` (and `real_min_transfer_time`) of a generated
+    /// transfer is the distance divided by `transfer_speed_m_per_s`.
+    /// Pairs of stop points which already have a transfer are left untouched.
+    ///
+    /// WARNING: if the geolocation of a `StopPoint` is (0, 0), it's considered
+    /// incorrect and no transfer is generated to or from this `StopPoint`.
+    pub fn compute_transfers(&mut self, max_distance_m: f64, transfer_speed_m_per_s: f64) {
+        transfers::generate_missing_transfers(self, max_distance_m, transfer_speed_m_per_s, 0);
+    }
+
+    /// For each transfer A→B without a transfer B→A, add the mirrored
+    /// transfer B→A with the same transfer times (and equipment).
+    /// Transfers existing in both directions are left untouched, even with
+    /// different times.
+    pub fn make_transfers_symmetric(&mut self) {
+        transfers::make_transfers_symmetric(self)
+    }
+
+    /// Returns the geographic extent of the `StopArea`s, `None` if there is
+    /// no geolocated `StopArea`.
+    ///
+    /// `StopArea`s without valid coordinates (e.g. (0, 0)) are ignored.
+    pub fn stop_area_bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::from_coords(
+            self.stop_areas
+                .values()
+                .map(|stop_area| &stop_area.coord)
+                .filter(|coord| is_valid_coord(coord)),
+        )
+    }
+
+    /// Returns the geographic extent of the `StopPoint`s, `None` if there is
+    /// no geolocated `StopPoint`.
+    ///
+    /// `StopPoint`s without valid coordinates (e.g. (0, 0)) are ignored.
+    pub fn stop_point_bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::from_coords(
+            self.stop_points
+                .values()
+                .map(|stop_point| &stop_point.coord)
+                .filter(|coord| is_valid_coord(coord)),
+        )
+    }
+
+    /// Fill the coordinates of the stop points which have no valid
+    /// geolocation (e.g. (0, 0)), using the coordinates returned by `f`.
+    ///
+    /// `f` is only called for those stop points, and is typically a geocoder
+    /// based on the stop point's name or address. When `f` returns `None`,
+    /// the stop point is left untouched.
+    /// A stop area containing only one stop point inherits the coordinates
+    /// filled for this stop point.
+    pub fn fill_missing_coords<F>(&mut self, f: F)
+    where
+        F: Fn(&StopPoint) -> Option<Coord>,
+    {
+        enhancers::fill_missing_coords(self, f)
+    }
+
+    /// Apply the overrides of a `ModelPatch` (see [patch](crate::patch)),
+    /// typically after reading the data and before building the `Model`.
+    ///
+    /// Fails without modifying anything if the patch references an object
+    /// which doesn't exist.
+    pub fn apply_patch(&mut self, patch: ModelPatch) -> Result<()> {
+        patch::apply_patch(self, patch)
+    }
+
+    /// Check that all the references between objects point to existing
+    /// objects, and report the broken ones. The fares v1 (`prices_v1`,
+    /// `od_fares_v1` and `fares_v1`), whose references are free-form, are
+    /// not checked.
+    ///
+    /// The stop times are identified by `<vehicle_journey_id>-<sequence>`
+    /// and the objects without identifier by their main reference (e.g.
+    /// `<from_stop_id>-<to_stop_id>` for a transfer).
+    ///
+    /// Unlike `sanitize`, nothing is modified: the returned list is empty if
+    /// the collections are consistent.
+    pub fn is_consistent(&self) -> Vec<InconsistencyError> {
+        consistency::is_consistent(self)
+    }
+
+    /// Merge the line `source_id` into the line `target_id`, when both
+    /// represent the same service: the routes of the source line are moved to
+    /// the target line, with its object codes, comments, fare perimeters and
+    /// grid calendars, then the source line is removed.
+    ///
+    /// Fails if one of the lines doesn't exist or if the source line has no
+    /// route.
+    pub fn merge_lines(&mut self, source_id: &str, target_id: &str) -> Result<()> {
+        merge_lines::merge_lines(self, source_id, target_id)
+    }
+
+    /// Merge the lines having the same key, as computed by `f` (e.g. the
+    /// network and the code of the line), for example when a line has been
+    /// split in several GTFS routes. In each group, the line with the
+    /// smallest identifier is kept with its attributes, and the other lines
+    /// are merged into it (see [merge_lines](Collections::merge_lines)).
+    /// The lines without any route are left untouched.
+    ///
+    /// ```
+    /// # use transit_model_builder::ModelBuilder;
+    /// let mut collections = ModelBuilder::default()
+    ///     .route("r1", |route| route.line_id = "l1".to_string())
+    ///     .route("r2", |route| route.line_id = "l2".to_string())
+    ///     .vj("vj1", |vj| {
+    ///         vj.route("r1").st("A", "10:00:00", "10:01:00");
+    ///     })
+    ///     .vj("vj2", |vj| {
+    ///         vj.route("r2").st("B", "11:00:00", "11:01:00");
+    ///     })
+    ///     .build()
+    ///     .into_collections();
+    /// collections.merge_lines_by(|line| line.network_id.clone())?;
+    /// assert!(!collections.lines.contains_id("l2"));
+    /// assert_eq!("l1", collections.routes.get("r2").unwrap().line_id);
+    /// # Ok::<(), transit_model::Error>(())
+    /// ```
+    pub fn merge_lines_by<F, K>(&mut self, f: F) -> Result<()>
+    where
+        F: Fn(&Line) -> K,
+        K: Ord,
+    {
+        merge_lines::merge_lines_by(self, f)
+    }
+
+    /// Split a `StopArea`, for example to undo a wrong merge: for each group
+    /// `(new_stop_area_id, stop_point_ids)`, a new stop area is created as a
+    /// copy of the original one (name, object codes, comments, ...) and the
+    /// listed stop points are moved into it. The original stop area is
+    /// removed if nothing refers to it anymore: stop points, stop locations,
+    /// destinations of routes, directions of lines, admin stations and
+    /// origin-destination ticket use restrictions.
+    ///
+    /// Fails, without modifying anything, if the stop area doesn't exist, if
+    /// a new identifier is already used or if a stop point doesn't belong to
+    /// the stop area.
+    pub fn split_stop_area(
+        &mut self,
+        stop_area_id: &str,
+        groups: Vec<(String, Vec<String>)>,
+    ) -> Result<()> {
+        split_stop_area::split_stop_area(self, stop_area_id, groups)
+    }
+
+    /// Remove a contributor from the collections.
+    ///
+    /// Depending on the `policy`, the datasets of the contributor are either
+    /// removed with their vehicle journeys, or reassigned to another existing
+    /// contributor. The collections are then sanitized so that the objects
+    /// which are not used anymore (routes, lines, stop points, ...) are
+    /// removed too, and counted in the returned `RemovedObjects`.
+    pub fn remove_contributor(
+        &mut self,
+        contributor_id: &str,
+        policy: ContributorRemoval,
+    ) -> Result<RemovedObjects> {
+        remove_contributor::remove_contributor(self, contributor_id, policy)
+    }
+
+    /// Keep only the vehicle journeys stopping at least once in one of the
+    /// given stop areas, and the objects they use.
+    ///
+    /// The vehicle journeys also stopping outside of these stop areas are
+    /// handled depending on `partial_journeys`: with `PartialJourneys::Trim`,
+    /// their stop times are restricted to the stop points of the stop areas,
+    /// but only if these stop times are contiguous (otherwise the whole
+    /// vehicle journey is kept). With `PartialJourneys::KeepWhole`, they are
+    /// kept with all their stop times. The collections are then sanitized.
+    pub fn restrict_to_stop_areas(
+        &mut self,
+        stop_area_ids: &[String],
+        partial_journeys: PartialJourneys,
+    ) -> Result<()> {
+        restrict_to_stop_areas::restrict_to_stop_areas(self, stop_area_ids, partial_journeys)
+    }
+
+    /// Rewrites the times of the stop times depending on `mode`.
+    ///
+    /// With `OvernightTimes::KeepOvernight`, nothing is changed. With
+    /// `OvernightTimes::ModuloDay`, a vehicle journey starting after
+    /// 24:00:00 is moved as a whole within the day (25:10:00 becomes
+    /// 01:10:00), as well as its frequencies, so its times are still
+    /// increasing. Its service is replaced by a calendar running one day
+    /// later, with the identifier `<service_id>:shifted_1`, and the vehicle
+    /// journey is linked to the comment `CALENDAR_SHIFT_COMMENT_ID` (the
+    /// calendars can't be linked to comments, the identifier of the shifted
+    /// calendar tells the shift).
+    ///
+    /// A vehicle journey running across midnight (e.g. from 23:50:00 to
+    /// 24:10:00) can't be moved within a day, so its times are kept and a
+    /// warning is logged.
+    ///
+    /// ```
+    /// # use transit_model::model::{OvernightTimes, CALENDAR_SHIFT_COMMENT_ID};
+    /// # use transit_model::objects::{Date, Time};
+    /// # use transit_model_builder::ModelBuilder;
+    /// let mut collections = ModelBuilder::default()
+    ///     .vj("vj1", |vj| {
+    ///         vj.st("A", "24:10:00", "24:10:00")
+    ///             .st("B", "24:50:00", "24:50:00");
+    ///     })
+    ///     .build()
+    ///     .into_collections();
+    /// collections.canonicalize_times(OvernightTimes::ModuloDay)?;
+    /// let vj = collections.vehicle_journeys.get("vj1").unwrap();
+    /// assert_eq!(Time::new(0, 10, 0), vj.stop_times[0].arrival_time);
+    /// assert_eq!("default_service:shifted_1", vj.service_id);
+    /// assert!(vj.comment_links.contains(CALENDAR_SHIFT_COMMENT_ID));
+    /// let calendar = collections.calendars.get(&vj.service_id).unwrap();
+    /// assert!(calendar.dates.contains(&Date::from_ymd(2020, 1, 2)));
+    /// # Ok::<(), transit_model::Error>(())
+    /// ```
+    pub fn canonicalize_times(&mut self, mode: OvernightTimes) -> Result<()> {
+        canonicalize_times::canonicalize_times(self, mode)
+    }
 }
 
 /// The navitia transit model.
@@ -1274,6 +1953,8 @@ pub struct Model {
     vehicle_journeys_to_stop_points: ManyToMany<VehicleJourney, StopPoint>,
     transfers_to_stop_points: ManyToMany<Transfer, StopPoint>,
     calendars_to_vehicle_journeys: OneToMany<Calendar, VehicleJourney>,
+    stop_areas_to_equipments: ManyToMany<StopArea, Equipment>,
+    frequencies_to_vehicle_journeys: ManyToMany<Frequency, VehicleJourney>,
 
     // shortcuts
     #[get_corresponding(weight = "1.9")]
@@ -1325,7 +2006,26 @@ impl Model {
         c.comment_deduplication();
         c.clean_comments();
         c.sanitize()?;
+        Self::build_relations(c)
+    }
 
+    /// Constructs a model from collections where some data is deliberately
+    /// absent (no vehicle journeys or no stop times for example).
+    ///
+    /// Unlike `Model::new`, the objects not referenced by a vehicle journey
+    /// are kept. Only the transfers between unknown stop points are removed.
+    pub(crate) fn new_partial(mut c: Collections) -> Result<Self> {
+        c.comment_deduplication();
+        c.clean_comments();
+        let stop_points = &c.stop_points;
+        c.transfers.retain(|transfer| {
+            stop_points.contains_id(&transfer.from_stop_id)
+                && stop_points.contains_id(&transfer.to_stop_id)
+        });
+        Self::build_relations(c)
+    }
+
+    fn build_relations(mut c: Collections) -> Result<Self> {
         let forward_vj_to_sp = c
             .vehicle_journeys
             .iter()
@@ -1349,7 +2049,26 @@ impl Model {
                 Ok((idx, stop_points))
             })
             .collect::<Result<BTreeMap<_, _>, Error>>()?;
+        let forward_sa_to_eq = c
+            .stop_areas
+            .iter()
+            .filter_map(|(idx, sa)| {
+                let equipment_id = sa.equipment_id.as_ref()?;
+                let equipment_idx = c.equipments.get_idx(equipment_id)?;
+                Some((idx, std::iter::once(equipment_idx).collect()))
+            })
+            .collect();
+        let forward_freq_to_vj = c
+            .frequencies
+            .iter()
+            .filter_map(|(idx, frequency)| {
+                let vj_idx = c.vehicle_journeys.get_idx(&frequency.vehicle_journey_id)?;
+                Some((idx, std::iter::once(vj_idx).collect()))
+            })
+            .collect();
         let vehicle_journeys_to_stop_points = ManyToMany::from_forward(forward_vj_to_sp);
+        let stop_areas_to_equipments = ManyToMany::from_forward(forward_sa_to_eq);
+        let frequencies_to_vehicle_journeys = ManyToMany::from_forward(forward_freq_to_vj);
         let routes_to_vehicle_journeys =
             OneToMany::new(&c.routes, &c.vehicle_journeys, "routes_to_vehicle_journeys")?;
         let physical_modes_to_vehicle_journeys = OneToMany::new(
@@ -1435,6 +2154,8 @@ impl Model {
             contributors_to_datasets,
             companies_to_vehicle_journeys,
             calendars_to_vehicle_journeys,
+            stop_areas_to_equipments,
+            frequencies_to_vehicle_journeys,
             collections: c,
         })
     }
@@ -1464,7 +2185,361 @@ impl Model {
     pub fn into_collections(self) -> Collections {
         self.collections
     }
+
+    /// Returns the `StopArea` of a `StopPoint`, if both exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use transit_model_builder::ModelBuilder;
+    /// let model = ModelBuilder::default()
+    ///     .vj("vj1", |vj| {
+    ///         vj.st("SP1", "10:00:00", "10:01:00");
+    ///     })
+    ///     .build();
+    /// let stop_area = model.stop_area_for_stop_point("SP1").unwrap();
+    /// assert_eq!("sa:SP1", stop_area.id);
+    /// assert!(model.stop_area_for_stop_point("unknown").is_none());
+    /// ```
+    pub fn stop_area_for_stop_point(&self, stop_point_id: &str) -> Option<&StopArea> {
+        self.stop_points
+            .get(stop_point_id)
+            .and_then(|stop_point| self.stop_areas.get(&stop_point.stop_area_id))
+    }
+
+    /// Returns the `StopPoint`s of a `StopArea`, ordered by identifier.
+    /// The result is empty if the `StopArea` doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use transit_model_builder::ModelBuilder;
+    /// let model = ModelBuilder::default()
+    ///     .vj("vj1", |vj| {
+    ///         vj.st("SP1", "10:00:00", "10:01:00");
+    ///     })
+    ///     .build();
+    /// let stop_points = model.stop_points_for_stop_area("sa:SP1");
+    /// assert_eq!(1, stop_points.len());
+    /// assert_eq!("SP1", stop_points[0].id);
+    /// ```
+    pub fn stop_points_for_stop_area(&self, stop_area_id: &str) -> Vec<&StopPoint> {
+        let stop_area_idx = match self.stop_areas.get_idx(stop_area_id) {
+            Some(idx) => idx,
+            None => return vec![],
+        };
+        let mut stop_points: Vec<&StopPoint> = self
+            .stop_areas_to_stop_points
+            .get_corresponding_forward(&std::iter::once(stop_area_idx).collect())
+            .into_iter()
+            .map(|idx| &self.stop_points[idx])
+            .collect();
+        stop_points.sort_unstable_by(|sp1, sp2| sp1.id.cmp(&sp2.id));
+        stop_points
+    }
+
+    /// Returns the `StopPoint`s whose `name_alias` is `alias`, ordered by
+    /// identifier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use transit_model_builder::ModelBuilder;
+    /// let mut collections = ModelBuilder::default()
+    ///     .vj("vj1", |vj| {
+    ///         vj.st("SP1", "10:00:00", "10:01:00")
+    ///             .st("SP2", "11:00:00", "11:01:00");
+    ///     })
+    ///     .build()
+    ///     .into_collections();
+    /// collections.stop_points.get_mut("SP1").unwrap().name_alias = Some("Old Town".to_string());
+    /// let model = transit_model::Model::new(collections).unwrap();
+    /// let stop_points = model.stop_points_by_alias("Old Town");
+    /// assert_eq!(1, stop_points.len());
+    /// assert_eq!("SP1", stop_points[0].id);
+    /// assert!(model.stop_points_by_alias("New Town").is_empty());
+    /// ```
+    pub fn stop_points_by_alias(&self, alias: &str) -> Vec<&StopPoint> {
+        let mut stop_points: Vec<&StopPoint> = self
+            .stop_points
+            .values()
+            .filter(|stop_point| stop_point.name_alias.as_deref() == Some(alias))
+            .collect();
+        stop_points.sort_unstable_by(|sp1, sp2| sp1.id.cmp(&sp2.id));
+        stop_points
+    }
+
+    /// Returns the pairs of `StopArea`s located within `distance_threshold_m`
+    /// meters of each other, with their distance in meters. Each pair is
+    /// returned once, the identifiers of a pair and the pairs being in
+    /// alphabetical order.
+    ///
+    /// WARNING: if the geolocation of a `StopArea` is (0, 0), it's considered
+    /// incorrect and the `StopArea` is ignored.
+    pub fn duplicate_stop_areas(&self, distance_threshold_m: f64) -> Vec<(String, String, f64)> {
+        let mut stop_areas: Vec<&StopArea> = self
+            .stop_areas
+            .values()
+            .filter(|sa| sa.coord != Coord::default())
+            .collect();
+        stop_areas.sort_unstable_by(|sa1, sa2| sa1.id.cmp(&sa2.id));
+        let mut duplicates = Vec::new();
+        for (position, stop_area) in stop_areas.iter().enumerate() {
+            for other in &stop_areas[position + 1..] {
+                let distance = stop_area.coord.distance_to(&other.coord);
+                if distance <= distance_threshold_m {
+                    duplicates.push((stop_area.id.clone(), other.id.clone(), distance));
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// Iterates over the stop times of all the vehicle journeys, with the
+    /// index of their vehicle journey, without cloning them.
+    pub fn iter_stop_times(&self) -> impl Iterator<Item = (Idx<VehicleJourney>, &StopTime)> {
+        self.vehicle_journeys.iter().flat_map(|(vj_idx, vj)| {
+            vj.stop_times
+                .iter()
+                .map(move |stop_time| (vj_idx, stop_time))
+        })
+    }
+
+    /// Returns the identifier and the sequence of the stop times without any
+    /// dwell (arrival time equal to departure time, see [StopTime::dwell]),
+    /// ordered by vehicle journey identifier then sequence. The first and
+    /// last stop times of the vehicle journeys are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use transit_model_builder::ModelBuilder;
+    /// let model = ModelBuilder::default()
+    ///     .vj("vj1", |vj| {
+    ///         vj.st("SP1", "10:00:00", "10:00:00")
+    ///             .st("SP2", "10:10:00", "10:10:00")
+    ///             .st("SP3", "10:20:00", "10:21:00")
+    ///             .st("SP4", "10:30:00", "10:30:00");
+    ///     })
+    ///     .build();
+    /// assert_eq!(vec![("vj1", 1)], model.zero_dwell_stop_times());
+    /// ```
+    pub fn zero_dwell_stop_times(&self) -> Vec<(&str, u32)> {
+        let mut zero_dwells: Vec<(&str, u32)> = self
+            .vehicle_journeys
+            .values()
+            .flat_map(|vj| {
+                let nb_intermediate_stops = vj.stop_times.len().saturating_sub(2);
+                vj.stop_times
+                    .iter()
+                    .skip(1)
+                    .take(nb_intermediate_stops)
+                    .filter(|stop_time| stop_time.dwell() == chrono::Duration::zero())
+                    .map(move |stop_time| (vj.id.as_str(), stop_time.sequence))
+            })
+            .collect();
+        zero_dwells.sort_unstable();
+        zero_dwells
+    }
+
+    /// Same as [iter_stop_times](Model::iter_stop_times), restricted to the
+    /// vehicle journeys of a `Line`.
+    pub fn iter_line_stop_times(
+        &self,
+        line_idx: Idx<Line>,
+    ) -> impl Iterator<Item = (Idx<VehicleJourney>, &StopTime)> {
+        let vj_idxs: IdxSet<VehicleJourney> = self.get_corresponding_from_idx(line_idx);
+        vj_idxs.into_iter().flat_map(move |vj_idx| {
+            self.vehicle_journeys[vj_idx]
+                .stop_times
+                .iter()
+                .map(move |stop_time| (vj_idx, stop_time))
+        })
+    }
+
+    /// Returns the vehicle journeys which can be operated by the same vehicle
+    /// right after the given one (interlining): they have the same
+    /// `block_id`, their first stop time is after the last stop time of the
+    /// given vehicle journey and they have at least one date in common. The
+    /// result is ordered by departure time (then by identifier), and empty
+    /// if the vehicle journey has no `block_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use transit_model_builder::ModelBuilder;
+    /// let model = ModelBuilder::default()
+    ///     .vj("vj1", |vj| {
+    ///         vj.block_id("block_1")
+    ///             .st("SP1", "10:00:00", "10:01:00")
+    ///             .st("SP2", "11:00:00", "11:01:00");
+    ///     })
+    ///     .vj("vj2", |vj| {
+    ///         vj.block_id("block_1")
+    ///             .st("SP3", "12:00:00", "12:01:00")
+    ///             .st("SP4", "13:00:00", "13:01:00");
+    ///     })
+    ///     .build();
+    /// let vj1_idx = model.vehicle_journeys.get_idx("vj1").unwrap();
+    /// let vj2_idx = model.vehicle_journeys.get_idx("vj2").unwrap();
+    /// assert_eq!(vec![vj2_idx], model.next_journeys_in_block(vj1_idx));
+    /// assert!(model.next_journeys_in_block(vj2_idx).is_empty());
+    /// ```
+    pub fn next_journeys_in_block(&self, vj_idx: Idx<VehicleJourney>) -> Vec<Idx<VehicleJourney>> {
+        let vj = &self.vehicle_journeys[vj_idx];
+        let (block_id, last_stop_time) = match (vj.block_id.as_ref(), vj.stop_times.last()) {
+            (Some(block_id), Some(last_stop_time)) => (block_id, last_stop_time),
+            _ => return vec![],
+        };
+        let calendar = match self.calendars.get(&vj.service_id) {
+            Some(calendar) => calendar,
+            None => return vec![],
+        };
+        let mut next_journeys: Vec<_> = self
+            .vehicle_journeys
+            .iter()
+            .filter(|(idx, next_vj)| *idx != vj_idx && next_vj.block_id.as_ref() == Some(block_id))
+            .filter(|(_, next_vj)| {
+                self.calendars
+                    .get(&next_vj.service_id)
+                    .map_or(false, |next_calendar| calendar.overlaps(next_calendar))
+            })
+            .filter_map(|(idx, next_vj)| {
+                next_vj
+                    .stop_times
+                    .first()
+                    .filter(|first_stop_time| {
+                        last_stop_time.departure_time <= first_stop_time.arrival_time
+                    })
+                    .map(|first_stop_time| (first_stop_time.departure_time, &next_vj.id, idx))
+            })
+            .collect();
+        next_journeys.sort_unstable_by(|(time1, id1, _), (time2, id2, _)| {
+            time1.cmp(time2).then_with(|| id1.cmp(id2))
+        });
+        next_journeys.into_iter().map(|(_, _, idx)| idx).collect()
+    }
+
+    /// Returns a `MULTILINESTRING` combining the geometries of the routes and
+    /// vehicle journeys of a `Line`, each segment appearing only once
+    /// (whatever its direction). Returns `None` if none of them has a
+    /// geometry. The returned `Geometry` has the identifier of the line.
+    pub fn line_geometry(&self, line_idx: Idx<Line>) -> Option<Geometry> {
+        let route_idxs: IdxSet<Route> = self.get_corresponding_from_idx(line_idx);
+        let vj_idxs: IdxSet<VehicleJourney> = self.get_corresponding_from_idx(line_idx);
+        let geometry_ids: BTreeSet<&str> = route_idxs
+            .into_iter()
+            .filter_map(|idx| self.routes[idx].geometry_id.as_deref())
+            .chain(
+                vj_idxs
+                    .into_iter()
+                    .filter_map(|idx| self.vehicle_journeys[idx].geometry_id.as_deref()),
+            )
+            .collect();
+
+        fn segment_key(segment: &geo::Line<f64>) -> [(u64, u64); 2] {
+            let mut key = [
+                (segment.start.x.to_bits(), segment.start.y.to_bits()),
+                (segment.end.x.to_bits(), segment.end.y.to_bits()),
+            ];
+            key.sort_unstable();
+            key
+        }
+        let mut segments = HashSet::new();
+        let mut line_strings = Vec::new();
+        for geometry in geometry_ids
+            .into_iter()
+            .filter_map(|id| self.geometries.get(id))
+        {
+            let geometry_line_strings = match &geometry.geometry {
+                GeoGeometry::LineString(line_string) => vec![line_string.clone()],
+                GeoGeometry::MultiLineString(multi_line_string) => multi_line_string.0.clone(),
+                _ => {
+                    warn!(
+                        "geometry {:?} of line {:?} is not a linestring and is ignored",
+                        geometry.id, self.lines[line_idx].id
+                    );
+                    continue;
+                }
+            };
+            for line_string in geometry_line_strings {
+                // consecutive new segments are kept in the same linestring
+                let mut coords: Vec<Coordinate<f64>> = Vec::new();
+                for segment in line_string.lines() {
+                    if segments.insert(segment_key(&segment)) {
+                        if coords.is_empty() {
+                            coords.push(segment.start);
+                        }
+                        coords.push(segment.end);
+                    } else if !coords.is_empty() {
+                        line_strings.push(LineString(std::mem::take(&mut coords)));
+                    }
+                }
+                if !coords.is_empty() {
+                    line_strings.push(LineString(coords));
+                }
+            }
+        }
+        if line_strings.is_empty() {
+            return None;
+        }
+        Some(Geometry {
+            id: self.lines[line_idx].id.clone(),
+            geometry: GeoGeometry::MultiLineString(MultiLineString(line_strings)),
+        })
+    }
+
+    /// Returns a GeoJSON `FeatureCollection` with a `Point` feature for each
+    /// `StopPoint`, with its `id`, `name` and `stop_area_id` as properties.
+    pub fn stop_points_geojson(&self) -> serde_json::Value {
+        geojson_feature_collection(self.stop_points.values().map(|stop_point| {
+            geojson_point_feature(
+                &stop_point.coord,
+                serde_json::json!({
+                    "id": stop_point.id,
+                    "name": stop_point.name,
+                    "stop_area_id": stop_point.stop_area_id,
+                }),
+            )
+        }))
+    }
+
+    /// Returns a GeoJSON `FeatureCollection` with a `Point` feature for each
+    /// `StopArea`, with its `id` and `name` as properties.
+    pub fn stop_areas_geojson(&self) -> serde_json::Value {
+        geojson_feature_collection(self.stop_areas.values().map(|stop_area| {
+            geojson_point_feature(
+                &stop_area.coord,
+                serde_json::json!({
+                    "id": stop_area.id,
+                    "name": stop_area.name,
+                }),
+            )
+        }))
+    }
+}
+
+fn geojson_point_feature(coord: &Coord, properties: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "Point",
+            "coordinates": [coord.lon, coord.lat],
+        },
+        "properties": properties,
+    })
 }
+
+fn geojson_feature_collection<I>(features: I) -> serde_json::Value
+where
+    I: Iterator<Item = serde_json::Value>,
+{
+    serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features.collect::<Vec<_>>(),
+    })
+}
+
 #[cfg(feature = "mutable-model")]
 impl Model {
     /// Add a Calendar inside the model
@@ -2256,6 +3331,56 @@ mod tests {
             let calendar = collections.calendars.get("service_2");
             assert_eq!(None, calendar);
         }
+
+        #[test]
+        fn collapse_identical_calendars() {
+            testing_logger::setup();
+            let dates = [
+                NaiveDate::from_ymd(2019, 10, 1),
+                NaiveDate::from_ymd(2019, 10, 2),
+            ];
+            let mut collections = Collections::default();
+            for i in 1..=3 {
+                let mut calendar = Calendar::new(format!("service_{}", i));
+                calendar.dates.extend(dates.iter().cloned());
+                collections.calendars.push(calendar).unwrap();
+                collections
+                    .vehicle_journeys
+                    .push(VehicleJourney {
+                        id: format!("vehicle_journey_id_{}", i),
+                        service_id: format!("service_{}", i),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+
+            collections.calendar_deduplication();
+
+            let calendar_ids: Vec<_> = collections
+                .calendars
+                .values()
+                .map(|c| c.id.as_str())
+                .collect();
+            assert_eq!(vec!["service_1"], calendar_ids);
+            assert!(collections
+                .vehicle_journeys
+                .values()
+                .all(|vj| vj.service_id == "service_1"));
+            testing_logger::validate(|captured_logs| {
+                let removed: Vec<_> = captured_logs
+                    .iter()
+                    .filter(|log| log.body.starts_with("Calendar with ID"))
+                    .map(|log| log.body.as_str())
+                    .collect();
+                assert_eq!(
+                    vec![
+                        "Calendar with ID service_2 has been removed",
+                        "Calendar with ID service_3 has been removed",
+                    ],
+                    removed
+                );
+            });
+        }
     }
 
     mod clean_comments {
@@ -2468,6 +3593,7 @@ mod tests {
                 geometry_id: None,
                 stop_times,
                 journey_pattern_id: None,
+                occupancy: None,
             }
         }
 
@@ -2721,6 +3847,132 @@ mod tests {
         }
     }
 
+    mod bounding_box {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn stop_bounding_boxes() {
+            let stop_point = |id: &str, stop_area_id: &str, lon: f64, lat: f64| StopPoint {
+                id: id.into(),
+                stop_area_id: stop_area_id.into(),
+                coord: Coord { lon, lat },
+                ..Default::default()
+            };
+            let collections = Collections {
+                stop_points: CollectionWithId::new(vec![
+                    stop_point("sp1", "sa1", 2.37, 48.84),
+                    stop_point("sp2", "sa1", 2.39, 48.83),
+                    stop_point("sp3", "sa2", 2.30, 48.88),
+                    // no geolocation
+                    stop_point("sp4", "sa2", 0.0, 0.0),
+                ])
+                .unwrap(),
+                stop_areas: CollectionWithId::new(vec![
+                    StopArea {
+                        id: "sa1".into(),
+                        coord: Coord {
+                            lon: 2.38,
+                            lat: 48.835,
+                        },
+                        ..Default::default()
+                    },
+                    // no geolocation
+                    StopArea {
+                        id: "sa2".into(),
+                        ..Default::default()
+                    },
+                ])
+                .unwrap(),
+                ..Default::default()
+            };
+            let bbox = collections.stop_point_bounding_box().unwrap();
+            assert_eq!(
+                BoundingBox {
+                    min_lon: 2.30,
+                    min_lat: 48.83,
+                    max_lon: 2.39,
+                    max_lat: 48.88,
+                },
+                bbox
+            );
+            assert!(bbox.contains(&Coord {
+                lon: 2.35,
+                lat: 48.85
+            }));
+            assert!(!bbox.contains(&Coord {
+                lon: 2.35,
+                lat: 48.9
+            }));
+            assert_eq!(
+                BoundingBox {
+                    min_lon: 2.38,
+                    min_lat: 48.835,
+                    max_lon: 2.38,
+                    max_lat: 48.835,
+                },
+                collections.stop_area_bounding_box().unwrap()
+            );
+        }
+
+        #[test]
+        fn no_bounding_box() {
+            let collections = Collections::default();
+            assert_eq!(None, collections.stop_point_bounding_box());
+            assert_eq!(None, collections.stop_area_bounding_box());
+        }
+    }
+
+    mod duplicate_stop_areas {
+        use super::*;
+        use approx::assert_relative_eq;
+        use pretty_assertions::assert_eq;
+
+        fn stop_area(id: &str, lon: f64, lat: f64) -> StopArea {
+            StopArea {
+                id: id.to_string(),
+                coord: Coord { lon, lat },
+                ..Default::default()
+            }
+        }
+
+        fn model() -> Model {
+            let mut collections = Collections::default();
+            collections.stop_areas = CollectionWithId::new(vec![
+                stop_area("sa:3", 2.371, 48.846),
+                stop_area("sa:1", 2.37, 48.846),
+                stop_area("sa:2", 2.3701, 48.846),
+                stop_area("sa:far", 2.5, 48.9),
+                stop_area("sa:no_coord", 0.0, 0.0),
+                stop_area("sa:no_coord_either", 0.0, 0.0),
+            ])
+            .unwrap();
+            Model::new_partial(collections).unwrap()
+        }
+
+        #[test]
+        fn pairs_in_alphabetical_order() {
+            let duplicates = model().duplicate_stop_areas(100.0);
+            let pairs: Vec<_> = duplicates
+                .iter()
+                .map(|(sa1, sa2, _)| (sa1.as_str(), sa2.as_str()))
+                .collect();
+            assert_eq!(
+                vec![("sa:1", "sa:2"), ("sa:1", "sa:3"), ("sa:2", "sa:3")],
+                pairs
+            );
+            assert_relative_eq!(duplicates[0].2, 7.317, epsilon = 1e-3);
+        }
+
+        #[test]
+        fn threshold_is_applied() {
+            let duplicates = model().duplicate_stop_areas(10.0);
+            assert_eq!(1, duplicates.len());
+            assert_eq!("sa:1", duplicates[0].0);
+            assert_eq!("sa:2", duplicates[0].1);
+        }
+    }
+
     mod update_stop_area_coords {
         use super::*;
         use approx::assert_relative_eq;
@@ -166,6 +166,22 @@ impl<'a> Exporter<'a> {
         Ok(())
     }
 
+    /// Write only the stops of `model` (`StopPlace` with their `Quay`) into
+    /// the file `filepath`, as a single `SiteFrame`.
+    pub fn write_stops_referential<P>(&self, filepath: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let filepath = filepath.as_ref();
+        let file = File::create(filepath)?;
+        let site_frame = self.create_site_frame()?;
+        let netex = self.wrap_frame(site_frame, VersionType::Stops);
+        let mut writer = ElementWriter::pretty(file);
+        info!("Writing {:?}", filepath);
+        writer.write(&netex)?;
+        Ok(())
+    }
+
     pub(in crate::netex_france) fn generate_id(id: &'a str, object_type: ObjectType) -> String {
         let id = id.replace(':', "_");
         format!("FR:{}:{}:", object_type, id)
@@ -339,6 +355,23 @@ impl Exporter<'_> {
         Ok(frame)
     }
 
+    // Returns a 'SiteFrame' containing all 'StopPlace' with their 'Quay'
+    fn create_site_frame(&self) -> Result<Element> {
+        let stop_exporter = StopExporter::new(&self.model, &self.participant_ref)?.inline_quays();
+        let stop_places = stop_exporter.export_stop_places()?;
+        let stop_places = Element::builder("stopPlaces")
+            .append_all(stop_places)
+            .build();
+        let site_frame_id =
+            self.generate_frame_id(FrameType::Site, &format!("NETEX_{}", VersionType::Stops));
+        let frame = Element::builder(FrameType::Site.to_string())
+            .attr("id", site_frame_id)
+            .attr("version", "any")
+            .append(stop_places)
+            .build();
+        Ok(frame)
+    }
+
     fn write_calendars<P>(&self, path: P) -> Result<()>
     where
         P: AsRef<Path>,
@@ -94,6 +94,25 @@ pub fn write<P: AsRef<std::path::Path>>(
     Ok(())
 }
 
+/// Exports only the stops of a `Model` (the "référentiel d'arrêts") to a
+/// [NeTEx France](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_to_netex_france_specs.md)
+/// XML file at the given full path: a single `SiteFrame` with a `StopPlace`
+/// for each stop area (even without any stop point) containing its `Quay`s.
+pub fn write_stops<P: AsRef<std::path::Path>>(
+    model: &Model,
+    path: P,
+    config: WriteConfiguration,
+) -> Result<()> {
+    let exporter = Exporter::new(
+        model,
+        config.participant,
+        config.stop_provider,
+        config.current_datetime,
+    );
+    exporter.write_stops_referential(path)?;
+    Ok(())
+}
+
 /// Exports a `Model` to a
 /// [NeTEx France](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_to_netex_france_specs.md)
 /// ZIP archive at the given full path.
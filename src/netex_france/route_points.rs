@@ -140,6 +140,7 @@ mod tests {
                 geometry_id: None,
                 stop_times,
                 journey_pattern_id: None,
+                occupancy: None,
             }
         }
 
@@ -47,6 +47,7 @@ pub struct StopExporter<'a> {
     stop_point_modes: StopPointModes<'a>,
     stop_area_stop_points: StopAreaStopPoints<'a>,
     stop_area_entrances: StopAreaEntrances<'a>,
+    inline_quays: bool,
 }
 
 // Publicly exposed methods
@@ -63,9 +64,18 @@ impl<'a> StopExporter<'a> {
             stop_point_modes,
             stop_area_stop_points,
             stop_area_entrances,
+            inline_quays: false,
         };
         Ok(exporter)
     }
+    // Write the complete `Quay` inside the `quays` of the `StopPlace`
+    // instead of a `QuayRef`
+    pub fn inline_quays(self) -> Self {
+        StopExporter {
+            inline_quays: true,
+            ..self
+        }
+    }
     pub fn export(&self) -> Result<Vec<Element>> {
         let stop_points_elements = self
             .model
@@ -80,24 +90,26 @@ impl<'a> StopExporter<'a> {
             .stop_areas
             .values()
             // Create StopPlace for `stop_area` with at least one `stop_point` with a NeTEx mode
-            .filter(|stop_area| {
-                if let Some(stop_point_ids) = self.stop_area_stop_points.get(stop_area.id.as_str())
-                {
-                    let stop_points_with_netex_modes = stop_point_ids
-                        .iter()
-                        .filter(|stop_point_id| self.stop_point_modes.contains_key(*stop_point_id))
-                        .count();
-                    stop_points_with_netex_modes > 0
-                } else {
-                    false
-                }
-            })
+            .filter(|stop_area| self.has_netex_modes(stop_area))
             .map(|stop_area| self.export_stop_area(stop_area))
             .collect::<Result<Vec<Vec<Element>>>>()?;
         let mut elements = stop_points_elements;
         elements.extend(stop_areas_elements.into_iter().flatten());
         Ok(elements)
     }
+    // Export all the `stop_area` as StopPlace, even the ones without any
+    // NeTEx mode (for the stops referential, to be used with `inline_quays`)
+    pub fn export_stop_places(&self) -> Result<Vec<Element>> {
+        let mut elements = Vec::new();
+        for stop_area in self.model.stop_areas.values() {
+            if self.has_netex_modes(stop_area) {
+                elements.extend(self.export_stop_area(stop_area)?);
+            } else {
+                elements.push(self.export_stop_area_without_mode(stop_area)?);
+            }
+        }
+        Ok(elements)
+    }
 
     pub(in crate::netex_france) fn generate_stop_place_id(
         stop_area_id: &'a str,
@@ -177,6 +189,17 @@ impl<'a> StopExporter<'a> {
             })
     }
 
+    fn has_netex_modes(&self, stop_area: &'a StopArea) -> bool {
+        self.stop_area_stop_points
+            .get(stop_area.id.as_str())
+            .map(|stop_point_ids| {
+                stop_point_ids
+                    .iter()
+                    .any(|stop_point_id| self.stop_point_modes.contains_key(stop_point_id))
+            })
+            .unwrap_or(false)
+    }
+
     fn export_stop_point(&self, stop_point: &'a StopPoint) -> Result<Element> {
         let element_builder = Element::builder("Quay")
             .attr(
@@ -198,29 +221,28 @@ impl<'a> StopExporter<'a> {
             } else {
                 element_builder
             };
-        let netex_modes = self
-            .stop_point_modes
-            .get(stop_point.id.as_str())
-            .ok_or_else(|| {
-                // Should never happen, a Stop Point always have some associated mode
-                format_err!("Unable to find modes for Stop Point '{}'", stop_point.id)
-            })?;
-        if netex_modes.len() > 1 {
-            warn!(
-                "StopPoint '{}' has more than one associated NeTEx mode: {:?}",
-                stop_point.id, netex_modes
-            );
-        }
-        let highest_netex_mode =
-            NetexMode::calculate_highest_mode(&netex_modes).ok_or_else(|| {
-                // Should never happen, a Stop Point always have at least one associated mode
-                format_err!(
-                    "Unable to resolve main NeTEx mode for Stop Point {}",
-                    stop_point.id
-                )
-            })?;
+        // A Stop Point has no associated mode only in the stops referential,
+        // when it's not served by any trip with a NeTEx mode
         let element_builder =
-            element_builder.append(self.generate_transport_mode(highest_netex_mode));
+            if let Some(netex_modes) = self.stop_point_modes.get(stop_point.id.as_str()) {
+                if netex_modes.len() > 1 {
+                    warn!(
+                        "StopPoint '{}' has more than one associated NeTEx mode: {:?}",
+                        stop_point.id, netex_modes
+                    );
+                }
+                let highest_netex_mode = NetexMode::calculate_highest_mode(&netex_modes)
+                    .ok_or_else(|| {
+                        // Should never happen, a Stop Point always have at least one associated mode
+                        format_err!(
+                            "Unable to resolve main NeTEx mode for Stop Point {}",
+                            stop_point.id
+                        )
+                    })?;
+                element_builder.append(self.generate_transport_mode(highest_netex_mode))
+            } else {
+                element_builder
+            };
         let element_builder = if let Some(tariff_zones) = self.generate_tariff_zones(stop_point) {
             element_builder.append(tariff_zones)
         } else {
@@ -278,7 +300,8 @@ impl<'a> StopExporter<'a> {
                     element_builder.append(self.generate_transport_mode(*netex_mode));
                 let element_builder =
                     element_builder.append(self.generate_stop_place_type(*netex_mode));
-                let element_builder = element_builder.append(self.generate_quays(stop_point_ids));
+                let element_builder =
+                    element_builder.append(self.generate_quays(stop_point_ids, Some(*netex_mode))?);
                 stop_place_elements.push(element_builder.build());
             }
             // *** Multimodal stopplaces generation ***
@@ -311,6 +334,21 @@ impl<'a> StopExporter<'a> {
                 element_builder.append(self.generate_transport_mode(highest_netex_mode));
             let element_builder =
                 element_builder.append(self.generate_stop_place_type(highest_netex_mode));
+            // In the stops referential, the Stop Points without NeTEx mode
+            // are not in any monomodal StopPlace
+            let element_builder = if self.inline_quays {
+                let stop_point_ids_without_mode = stop_point_ids
+                    .iter()
+                    .filter(|stop_point_id| !self.stop_point_modes.contains_key(*stop_point_id));
+                let quays = self.generate_quays(stop_point_ids_without_mode, None)?;
+                if quays.children().count() == 0 {
+                    element_builder
+                } else {
+                    element_builder.append(quays)
+                }
+            } else {
+                element_builder
+            };
             stop_place_elements.push(element_builder.build());
             Ok(stop_place_elements)
         } else {
@@ -318,6 +356,41 @@ impl<'a> StopExporter<'a> {
         }
     }
 
+    // StopPlace of a `stop_area` without any NeTEx mode, only exported in the
+    // stops referential
+    fn export_stop_area_without_mode(&self, stop_area: &'a StopArea) -> Result<Element> {
+        let element_builder = Element::builder("StopPlace")
+            .attr(
+                "id",
+                Exporter::generate_id(&stop_area.id, ObjectType::StopPlace),
+            )
+            .attr("version", "any")
+            .append(self.generate_name(&stop_area.name));
+        let element_builder =
+            if let Some(centroid_element) = self.generate_centroid(&stop_area.coord) {
+                element_builder.append(centroid_element)
+            } else {
+                element_builder
+            };
+        let element_builder = if let Some(entrances) = self.generate_entrances(&stop_area.id) {
+            element_builder.append(entrances)
+        } else {
+            element_builder
+        };
+        let stop_point_ids = self
+            .stop_area_stop_points
+            .get(stop_area.id.as_str())
+            .into_iter()
+            .flatten();
+        let quays = self.generate_quays(stop_point_ids, None)?;
+        let element_builder = if quays.children().count() == 0 {
+            element_builder
+        } else {
+            element_builder.append(quays)
+        };
+        Ok(element_builder.build())
+    }
+
     fn generate_name(&self, name: &'a str) -> Element {
         Element::builder("Name")
             .append(Node::Text(name.to_owned()))
@@ -478,16 +551,45 @@ impl<'a> StopExporter<'a> {
         })
     }
 
-    fn generate_quays<I, T>(&self, stop_point_ids: I) -> Element
+    // In the stops referential, a `Quay` is written in the `StopPlace` of its
+    // highest NeTEx mode (or of its Stop Area when it has no NeTEx mode) and
+    // only referenced by a `QuayRef` in the other ones, so that each `Quay`
+    // is written once
+    fn is_inline_quay(&self, stop_point_id: &str, netex_mode: Option<NetexMode>) -> bool {
+        self.inline_quays
+            && netex_mode
+                == self
+                    .stop_point_modes
+                    .get(stop_point_id)
+                    .and_then(NetexMode::calculate_highest_mode)
+    }
+
+    fn generate_quays<I, T>(
+        &self,
+        stop_point_ids: I,
+        netex_mode: Option<NetexMode>,
+    ) -> Result<Element>
     where
         I: IntoIterator<Item = T>,
         T: Borrow<&'a str>,
     {
         let quays = stop_point_ids
             .into_iter()
-            .map(|stop_point_id| Exporter::generate_id(stop_point_id.borrow(), ObjectType::Quay))
-            .map(|quay_id| Element::builder("QuayRef").attr("ref", quay_id).build());
-        Element::builder("quays").append_all(quays).build()
+            .map(|stop_point_id| {
+                let stop_point_id: &'a str = *stop_point_id.borrow();
+                if self.is_inline_quay(stop_point_id, netex_mode) {
+                    let stop_point =
+                        self.model.stop_points.get(stop_point_id).ok_or_else(|| {
+                            format_err!("Unable to find Stop Point '{}'", stop_point_id)
+                        })?;
+                    self.export_stop_point(stop_point)
+                } else {
+                    let quay_id = Exporter::generate_id(stop_point_id, ObjectType::Quay);
+                    Ok(Element::builder("QuayRef").attr("ref", quay_id).build())
+                }
+            })
+            .collect::<Result<Vec<Element>>>()?;
+        Ok(Element::builder("quays").append_all(quays).build())
     }
 
     fn generate_stop_place_type(&self, netex_mode: NetexMode) -> Element {
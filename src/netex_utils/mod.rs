@@ -37,6 +37,8 @@ pub enum FrameType {
     Resource,
     /// Type of a `<ServiceFrame>`
     Service,
+    /// Type of a `<SiteFrame>`
+    Site,
 }
 /// Map of frames, categorized by `FrameType`. Multiple frames of the same type
 /// can exist, they're stored in a `Vec`.
@@ -51,6 +53,7 @@ impl Display for FrameType {
             General => write!(f, "GeneralFrame"),
             Resource => write!(f, "ResourceFrame"),
             Service => write!(f, "ServiceFrame"),
+            Site => write!(f, "SiteFrame"),
         }
     }
 }
@@ -65,6 +68,7 @@ impl FromStr for FrameType {
             "GeneralFrame" => Ok(General),
             "ResourceFrame" => Ok(Resource),
             "ServiceFrame" => Ok(Service),
+            "SiteFrame" => Ok(Site),
             _ => bail!("Failed to convert '{}' into a FrameType", s),
         }
     }
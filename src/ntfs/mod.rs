@@ -22,9 +22,9 @@ use crate::{
     calendars::{manage_calendars, write_calendar_dates},
     model::{Collections, Model},
     objects::*,
-    read_utils::{self, FileHandler},
+    read_utils::{self, CsvConfig, FileHandler, UnknownColumns},
     utils::*,
-    Result,
+    DefaultIdGenerator, IdGenerator, Result,
 };
 use chrono::{DateTime, FixedOffset};
 use chrono_tz::Tz;
@@ -32,7 +32,7 @@ use derivative::Derivative;
 use failure::ResultExt;
 use log::info;
 use serde::{Deserialize, Serialize};
-use std::path;
+use std::{path, rc::Rc};
 use tempfile::tempdir;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -129,6 +129,8 @@ struct Stop {
     equipment_id: Option<String>,
     level_id: Option<String>,
     platform_code: Option<String>,
+    #[serde(rename = "stop_name_alias")]
+    name_alias: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -173,12 +175,25 @@ fn has_fares_v1(collections: &Collections) -> bool {
     !collections.prices_v1.is_empty()
 }
 
+/// Subset of the NTFS to read with [read_partial].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadScope {
+    /// Only the referential is read (stops, lines, routes, networks, modes,
+    /// companies, ...). Trips, stop times, frequencies, calendars and fares
+    /// are left empty.
+    StopsOnly,
+    /// Everything but the stop times. Vehicle journeys have no stop times.
+    NoStopTimes,
+    /// The whole NTFS, same as [read].
+    Full,
+}
+
 /// Imports a `Model` from the
 /// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
 /// files in the given directory.
 pub fn from_dir<P: AsRef<path::Path>>(p: P) -> Result<Model> {
     let mut file_handle = read_utils::PathFileHandler::new(p.as_ref().to_path_buf());
-    read_file_handler(&mut file_handle)
+    read_file_handler(&mut file_handle, ReadScope::Full, &DefaultIdGenerator)
 }
 
 /// Imports a `Model` from a zip file containing the
@@ -186,7 +201,7 @@ pub fn from_dir<P: AsRef<path::Path>>(p: P) -> Result<Model> {
 pub fn from_zip<P: AsRef<path::Path>>(p: P) -> Result<Model> {
     let reader = std::fs::File::open(p.as_ref())?;
     let mut file_handler = read_utils::ZipHandler::new(reader, p)?;
-    read_file_handler(&mut file_handler)
+    read_file_handler(&mut file_handler, ReadScope::Full, &DefaultIdGenerator)
 }
 
 /// Imports a `Model` from an object implementing `Read` and `Seek` and containing a zip file with a
@@ -209,7 +224,7 @@ where
     R: std::io::Seek + std::io::Read,
 {
     let mut file_handler = read_utils::ZipHandler::new(reader, &source_name)?;
-    read_file_handler(&mut file_handler)
+    read_file_handler(&mut file_handler, ReadScope::Full, &DefaultIdGenerator)
 }
 
 /// Imports a `Model` from the
@@ -219,13 +234,194 @@ where
 /// If the default file type mechanism is not enough, you can use
 /// [from_zip] or [from_dir].
 pub fn read<P: AsRef<path::Path>>(path: P) -> Result<Model> {
+    read_with_configuration(path, &ReadConfiguration::default())
+}
+
+/// Imports a subset of the
+/// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
+/// from a directory or a zipped archive, as [read] does.
+/// The files out of the `ReadScope` are not read and the corresponding
+/// collections are left empty. Except with `ReadScope::Full`, the objects
+/// not used by any vehicle journey are kept in the `Model`.
+///
+/// ```
+/// # use transit_model::ntfs::{self, ReadScope};
+/// let model = ntfs::read_partial("tests/fixtures/minimal_ntfs", ReadScope::StopsOnly)?;
+/// assert!(model.vehicle_journeys.is_empty());
+/// assert!(!model.stop_points.is_empty());
+/// # Ok::<(), transit_model::Error>(())
+/// ```
+pub fn read_partial<P: AsRef<path::Path>>(path: P, scope: ReadScope) -> Result<Model> {
+    let configuration = ReadConfiguration {
+        scope,
+        ..Default::default()
+    };
+    read_with_configuration(path, &configuration)
+}
+
+/// Options of the NTFS reader, see [read_with_configuration].
+#[derive(Derivative, Clone)]
+#[derivative(Debug)]
+pub struct ReadConfiguration {
+    /// Subset of the NTFS to read, the whole NTFS by default.
+    pub scope: ReadScope,
+    /// CSV dialect of the files. The NTFS requires comma separated files,
+    /// but another dialect makes it possible to read malformed files.
+    pub csv_config: CsvConfig,
+    /// Generation of the identifiers of the objects created by the reader
+    /// (stop areas of the stop points without parent station),
+    /// [DefaultIdGenerator] by default.
+    #[derivative(Debug = "ignore")]
+    pub id_generator: Rc<dyn IdGenerator>,
+}
+
+impl Default for ReadConfiguration {
+    fn default() -> Self {
+        ReadConfiguration {
+            scope: ReadScope::Full,
+            csv_config: CsvConfig::default(),
+            id_generator: Rc::new(DefaultIdGenerator),
+        }
+    }
+}
+
+/// Imports the
+/// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
+/// from a directory or a zipped archive, as [read] does, with the given
+/// options.
+pub fn read_with_configuration<P: AsRef<path::Path>>(
+    path: P,
+    configuration: &ReadConfiguration,
+) -> Result<Model> {
+    read_with_report(path, configuration).map(|(model, _)| model)
+}
+
+/// Same as [read_with_configuration], also returning the columns which are
+/// not part of the NTFS and were ignored, by file name.
+///
+/// ```
+/// # use transit_model::ntfs::{self, ReadConfiguration};
+/// let (_model, unknown_columns) =
+///     ntfs::read_with_report("tests/fixtures/minimal_ntfs", &ReadConfiguration::default())?;
+/// assert!(unknown_columns.is_empty());
+/// # Ok::<(), transit_model::Error>(())
+/// ```
+pub fn read_with_report<P: AsRef<path::Path>>(
+    path: P,
+    configuration: &ReadConfiguration,
+) -> Result<(Model, UnknownColumns)> {
     let p = path.as_ref();
-    if p.is_file() {
+    let scope = configuration.scope;
+    let csv_config = configuration.csv_config;
+    let id_generator = configuration.id_generator.as_ref();
+    let (model, unknown_columns) = if p.is_file() {
         // if it's a file, we consider it to be a zip (and an error will be returned if it is not)
-        Ok(from_zip(p).with_context(|_| format!("impossible to read zipped ntfs {:?}", p))?)
+        let read_zip = || -> Result<(Model, UnknownColumns)> {
+            let reader = std::fs::File::open(p)?;
+            let mut file_handler =
+                read_utils::ZipHandler::new(reader, p)?.with_csv_config(csv_config);
+            let model = read_file_handler(&mut file_handler, scope, id_generator)?;
+            Ok((model, file_handler.unknown_columns().clone()))
+        };
+        read_zip().with_context(|_| format!("impossible to read zipped ntfs {:?}", p))?
+    } else if p.is_dir() {
+        let mut file_handler =
+            read_utils::PathFileHandler::new(p.to_path_buf()).with_csv_config(csv_config);
+        let model = read_file_handler(&mut file_handler, scope, id_generator)
+            .with_context(|_| format!("impossible to read ntfs directory from {:?}", p))?;
+        (model, file_handler.unknown_columns().clone())
+    } else {
+        return Err(failure::format_err!(
+            "file {:?} is neither a file nor a directory, cannot read a ntfs from it",
+            p
+        ));
+    };
+    if !unknown_columns.is_empty() {
+        info!(
+            "unknown columns are ignored in {}",
+            unknown_columns
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok((model, unknown_columns))
+}
+
+/// A line of `object_codes.txt` which cannot be read, reported by
+/// [validate_object_codes]. When reading a NTFS, an unknown `object_type`
+/// makes the reading fail while an unresolved reference is only logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The `object_type` is unknown or doesn't support codes
+    UnknownObjectType {
+        /// Value of the `object_type` column
+        object_type: String,
+        /// Value of the `object_id` column
+        object_id: String,
+    },
+    /// No object of type `object_type` has the identifier `object_id`
+    UnresolvedReference {
+        /// Value of the `object_type` column
+        object_type: String,
+        /// Value of the `object_id` column
+        object_id: String,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::UnknownObjectType {
+                object_type,
+                object_id,
+            } => write!(
+                f,
+                "object_type={} is not supported (object_id={})",
+                object_type, object_id
+            ),
+            ValidationError::UnresolvedReference {
+                object_type,
+                object_id,
+            } => write!(
+                f,
+                "object_type={} object_id={} not found",
+                object_type, object_id
+            ),
+        }
+    }
+}
+
+/// Checks the `object_codes.txt` file of a
+/// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
+/// (directory or zipped archive) and returns all its lines with an unknown
+/// `object_type` or referencing an object which doesn't exist. Only the
+/// files of the referenced object types are read, with the CSV dialect of
+/// the configuration.
+///
+/// ```
+/// # use transit_model::ntfs::{self, ReadConfiguration};
+/// let errors =
+///     ntfs::validate_object_codes("tests/fixtures/minimal_ntfs", &ReadConfiguration::default())?;
+/// assert!(errors.is_empty());
+/// # Ok::<(), transit_model::Error>(())
+/// ```
+pub fn validate_object_codes<P: AsRef<path::Path>>(
+    path: P,
+    configuration: &ReadConfiguration,
+) -> Result<Vec<ValidationError>> {
+    let p = path.as_ref();
+    let csv_config = configuration.csv_config;
+    let id_generator = configuration.id_generator.as_ref();
+    if p.is_file() {
+        let reader = std::fs::File::open(p)?;
+        let mut file_handler = read_utils::ZipHandler::new(reader, p)?.with_csv_config(csv_config);
+        read::validate_codes(&mut file_handler, id_generator)
     } else if p.is_dir() {
-        Ok(from_dir(p)
-            .with_context(|_| format!("impossible to read ntfs directory from {:?}", p))?)
+        let mut file_handler =
+            read_utils::PathFileHandler::new(p.to_path_buf()).with_csv_config(csv_config);
+        read::validate_codes(&mut file_handler, id_generator)
     } else {
         Err(failure::format_err!(
             "file {:?} is neither a file nor a directory, cannot read a ntfs from it",
@@ -234,11 +430,27 @@ pub fn read<P: AsRef<path::Path>>(path: P) -> Result<Model> {
     }
 }
 
-fn read_file_handler<H>(file_handler: &mut H) -> Result<Model>
+fn read_file_handler<H>(
+    file_handler: &mut H,
+    scope: ReadScope,
+    id_generator: &dyn IdGenerator,
+) -> Result<Model>
 where
     for<'a> &'a mut H: read_utils::FileHandler,
 {
     info!("Loading NTFS from {:?}", file_handler.source_name());
+    let read_schedules = scope != ReadScope::StopsOnly;
+    // The files are read in the same order whatever the scope, the ones out
+    // of the scope are left empty
+    macro_rules! schedules {
+        ($collection:expr) => {
+            if read_schedules {
+                $collection
+            } else {
+                Default::default()
+            }
+        };
+    }
     let mut collections = Collections {
         contributors: make_collection_with_id(file_handler, "contributors.txt")?,
         datasets: make_collection_with_id(file_handler, "datasets.txt")?,
@@ -246,19 +458,30 @@ where
         networks: make_collection_with_id(file_handler, "networks.txt")?,
         lines: make_collection_with_id(file_handler, "lines.txt")?,
         routes: make_collection_with_id(file_handler, "routes.txt")?,
-        vehicle_journeys: make_collection_with_id(file_handler, "trips.txt")?,
-        frequencies: make_opt_collection(file_handler, "frequencies.txt")?,
+        vehicle_journeys: schedules!(make_collection_with_id(file_handler, "trips.txt")?),
         physical_modes: make_collection_with_id(file_handler, "physical_modes.txt")?,
         companies: make_collection_with_id(file_handler, "companies.txt")?,
         equipments: make_opt_collection_with_id(file_handler, "equipments.txt")?,
-        trip_properties: make_opt_collection_with_id(file_handler, "trip_properties.txt")?,
+        trip_properties: schedules!(make_opt_collection_with_id(
+            file_handler,
+            "trip_properties.txt"
+        )?),
         transfers: make_opt_collection(file_handler, "transfers.txt")?,
         admin_stations: make_opt_collection(file_handler, "admin_stations.txt")?,
-        tickets: make_opt_collection_with_id(file_handler, "tickets.txt")?,
-        ticket_uses: make_opt_collection_with_id(file_handler, "ticket_uses.txt")?,
-        ticket_prices: make_opt_collection(file_handler, "ticket_prices.txt")?,
-        ticket_use_perimeters: make_opt_collection(file_handler, "ticket_use_perimeters.txt")?,
-        ticket_use_restrictions: make_opt_collection(file_handler, "ticket_use_restrictions.txt")?,
+        tickets: schedules!(make_opt_collection_with_id(file_handler, "tickets.txt")?),
+        ticket_uses: schedules!(make_opt_collection_with_id(
+            file_handler,
+            "ticket_uses.txt"
+        )?),
+        ticket_prices: schedules!(make_opt_collection(file_handler, "ticket_prices.txt")?),
+        ticket_use_perimeters: schedules!(make_opt_collection(
+            file_handler,
+            "ticket_use_perimeters.txt"
+        )?),
+        ticket_use_restrictions: schedules!(make_opt_collection(
+            file_handler,
+            "ticket_use_restrictions.txt"
+        )?),
         levels: make_opt_collection_with_id(file_handler, "levels.txt")?,
         grid_calendars: make_opt_collection_with_id(file_handler, "grid_calendars.txt")?,
         grid_exception_dates: make_opt_collection(file_handler, "grid_exception_dates.txt")?,
@@ -266,23 +489,43 @@ where
         grid_rel_calendar_line: make_opt_collection(file_handler, "grid_rel_calendar_line.txt")?,
         ..Default::default()
     };
-    manage_calendars(file_handler, &mut collections)?;
+    if read_schedules {
+        read::manage_frequencies(&mut collections, file_handler)?;
+        manage_calendars(file_handler, &mut collections)?;
+    }
     read::manage_geometries(&mut collections, file_handler)?;
     read::manage_feed_infos(&mut collections, file_handler)?;
-    read::manage_stops(&mut collections, file_handler)?;
+    read::manage_stops(&mut collections, file_handler, id_generator)?;
     read::manage_pathways(&mut collections, file_handler)?;
-    read::manage_stop_times(&mut collections, file_handler)?;
+    if scope == ReadScope::Full {
+        read::manage_stop_times(&mut collections, file_handler)?;
+    }
     read::manage_codes(&mut collections, file_handler)?;
     read::manage_comments(&mut collections, file_handler)?;
     read::manage_object_properties(&mut collections, file_handler)?;
-    read::manage_fares_v1(&mut collections, file_handler)?;
+    if scope != ReadScope::StopsOnly {
+        read::manage_fares_v1(&mut collections, file_handler)?;
+    }
     read::manage_companies_on_vj(&mut collections)?;
     info!("Indexing");
-    let res = Model::new(collections)?;
+    let res = match scope {
+        ReadScope::Full => Model::new(collections)?,
+        ReadScope::StopsOnly | ReadScope::NoStopTimes => Model::new_partial(collections)?,
+    };
     info!("Loading NTFS done");
     Ok(res)
 }
 
+/// Options of the NTFS writer, see [write_with_configuration].
+#[derive(Debug, Default, Clone)]
+pub struct WriteConfiguration {
+    /// If true, the optional files without any object (comments.txt,
+    /// transfers.txt, equipments.txt, ...) are written with their header only
+    /// instead of being omitted, replacing the files of a previous export in
+    /// the same directory.
+    pub write_empty_optional_files: bool,
+}
+
 /// Exports a `Model` to the
 /// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
 /// files in the given directory.
@@ -290,11 +533,32 @@ pub fn write<P: AsRef<path::Path>>(
     model: &Model,
     path: P,
     current_datetime: DateTime<FixedOffset>,
+) -> Result<()> {
+    write_with_configuration(
+        model,
+        path,
+        current_datetime,
+        &WriteConfiguration::default(),
+    )
+}
+
+/// Exports a `Model` to the
+/// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
+/// files in the given directory, with the given options.
+pub fn write_with_configuration<P: AsRef<path::Path>>(
+    model: &Model,
+    path: P,
+    current_datetime: DateTime<FixedOffset>,
+    configuration: &WriteConfiguration,
 ) -> Result<()> {
     let path = path.as_ref();
     std::fs::create_dir_all(path)?;
     info!("Writing NTFS to {:?}", path);
 
+    if configuration.write_empty_optional_files {
+        // the files of the non-empty collections are overwritten below
+        write::write_empty_optional_files(path)?;
+    }
     write::write_feed_infos(path, &model, current_datetime)?;
     write_collection_with_id(path, "contributors.txt", &model.contributors)?;
     write_collection_with_id(path, "datasets.txt", &model.datasets)?;
@@ -359,6 +623,36 @@ pub fn write<P: AsRef<path::Path>>(
     Ok(())
 }
 
+/// Serializes the objects of a collection into `writer`, exactly as [write]
+/// does for the NTFS files written from a collection as is (`lines.txt` for
+/// the lines, `networks.txt` for the networks, ...). Useful to check a single
+/// collection without writing a whole NTFS.
+///
+/// ```
+/// # use transit_model::{model::Collections, objects::Network};
+/// # use typed_index_collection::CollectionWithId;
+/// let mut collections = Collections::default();
+/// collections.networks = CollectionWithId::from(Network {
+///     id: "network:1".to_string(),
+///     name: "Network 1".to_string(),
+///     ..Default::default()
+/// });
+/// let mut bytes = Vec::new();
+/// transit_model::ntfs::write_collection_to_writer(collections.networks.values(), &mut bytes)?;
+/// assert!(String::from_utf8(bytes)?.starts_with("network_id,network_name,"));
+/// # Ok::<(), transit_model::Error>(())
+/// ```
+pub fn write_collection_to_writer<'a, T, W>(
+    collection: impl IntoIterator<Item = &'a T>,
+    writer: W,
+) -> Result<()>
+where
+    T: 'a + Serialize,
+    W: std::io::Write,
+{
+    write_objects_to_writer(collection, writer)
+}
+
 /// Exports a `Model` to a
 /// [NTFS](https://github.com/CanalTP/ntfs-specification/blob/master/ntfs_fr.md)
 /// ZIP archive at the given full path.
@@ -376,6 +670,265 @@ pub fn write_to_zip<P: AsRef<path::Path>>(
     Ok(())
 }
 
+/// Kind of difference of an object between two models, see [diff].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffKind {
+    /// The object only exists in the new model
+    Added,
+    /// The object only exists in the old model
+    Removed,
+    /// The object exists in both models with different values
+    Modified {
+        /// Names of the changed fields (as named in the NTFS files)
+        fields: Vec<String>,
+    },
+}
+
+/// Difference of one object between two models, see [diff].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    /// Type of the object
+    pub object_type: ObjectType,
+    /// Identifier of the object
+    pub object_id: String,
+    /// How the object changed
+    pub kind: DiffKind,
+}
+
+/// Differences between two models, as computed by [diff].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ModelDiff {
+    /// One entry per added, removed or modified object
+    pub entries: Vec<DiffEntry>,
+}
+
+impl ModelDiff {
+    /// Returns `true` if both models have the same objects.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn serialized_fields<T: Serialize>(object: &T) -> serde_json::Map<String, serde_json::Value> {
+    match serde_json::to_value(object) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    }
+}
+
+fn no_other_field<T>(_: &T, _: &T) -> Vec<String> {
+    Vec::new()
+}
+
+// The codes, object properties and comment links are not serialized with the
+// objects (they are written in their own NTFS files), so they are compared
+// separately
+fn changed_codes<T: Codes>(old: &T, new: &T) -> Vec<String> {
+    if old.codes() != new.codes() {
+        vec!["codes".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+fn changed_codes_properties_and_comments<T>(old: &T, new: &T) -> Vec<String>
+where
+    T: Codes + Properties + CommentLinks,
+{
+    let mut fields = changed_codes(old, new);
+    if old.properties() != new.properties() {
+        fields.push("object_properties".to_string());
+    }
+    if old.comment_links() != new.comment_links() {
+        fields.push("comment_links".to_string());
+    }
+    fields
+}
+
+// The stop times reference their stop point by its index in the model, so
+// they are compared on the identifier of the stop point instead
+fn stop_times_changed(
+    old: &Model,
+    old_vj: &VehicleJourney,
+    new: &Model,
+    new_vj: &VehicleJourney,
+) -> bool {
+    let comparable = |model: &Model, stop_time: &crate::objects::StopTime| {
+        (
+            model.stop_points[stop_time.stop_point_idx].id.clone(),
+            stop_time.sequence,
+            stop_time.arrival_time,
+            stop_time.departure_time,
+            stop_time.boarding_duration,
+            stop_time.alighting_duration,
+            stop_time.pickup_type,
+            stop_time.drop_off_type,
+            stop_time.datetime_estimated,
+            stop_time.local_zone_id,
+            stop_time.precision.clone(),
+        )
+    };
+    old_vj.stop_times.len() != new_vj.stop_times.len()
+        || old_vj
+            .stop_times
+            .iter()
+            .zip(new_vj.stop_times.iter())
+            .any(|(old_st, new_st)| comparable(old, old_st) != comparable(new, new_st))
+}
+
+fn diff_collection<T, F>(
+    object_type: ObjectType,
+    old: &typed_index_collection::CollectionWithId<T>,
+    new: &typed_index_collection::CollectionWithId<T>,
+    other_changed_fields: F,
+    entries: &mut Vec<DiffEntry>,
+) where
+    T: typed_index_collection::Id<T> + Serialize,
+    F: Fn(&T, &T) -> Vec<String>,
+{
+    let mut ids: Vec<&str> = old
+        .values()
+        .chain(new.values())
+        .map(|object| object.id())
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    for id in ids {
+        let kind = match (old.get(id), new.get(id)) {
+            (Some(_), None) => DiffKind::Removed,
+            (None, Some(_)) => DiffKind::Added,
+            (Some(old_object), Some(new_object)) => {
+                let old_fields = serialized_fields(old_object);
+                let new_fields = serialized_fields(new_object);
+                let mut fields: Vec<String> = old_fields
+                    .keys()
+                    .chain(new_fields.keys())
+                    .filter(|field| old_fields.get(*field) != new_fields.get(*field))
+                    .cloned()
+                    .collect();
+                fields.extend(other_changed_fields(old_object, new_object));
+                fields.sort_unstable();
+                fields.dedup();
+                if fields.is_empty() {
+                    continue;
+                }
+                DiffKind::Modified { fields }
+            }
+            (None, None) => continue,
+        };
+        entries.push(DiffEntry {
+            object_type: object_type.clone(),
+            object_id: id.to_string(),
+            kind,
+        });
+    }
+}
+
+/// Computes the differences between two models, typically two versions of
+/// the same feed.
+///
+/// Networks, companies, lines, routes, vehicle journeys, stop areas, stop
+/// points and tickets are compared on the fields written in their NTFS file,
+/// and on their codes, object properties and comment links (reported as
+/// `codes`, `object_properties` and `comment_links`). The stop times of the
+/// vehicle journeys are also compared (reported as `stop_times`), their stop
+/// point being compared on its identifier. The entries are grouped by object
+/// type and sorted by identifier.
+///
+/// ```
+/// # use transit_model::{model::Model, objects::*};
+/// # use transit_model::ntfs::{DiffEntry, DiffKind};
+/// # use transit_model_builder::ModelBuilder;
+/// let build = || {
+///     ModelBuilder::default()
+///         .vj("vj1", |vj| {
+///             vj.st("A", "10:00:00", "10:01:00").st("B", "11:00:00", "11:01:00");
+///         })
+///         .build()
+/// };
+/// let old = build();
+/// let mut collections = build().into_collections();
+/// collections.lines.get_mut("default_line").unwrap().name = "New name".to_string();
+/// let new = Model::new(collections)?;
+///
+/// let diff = transit_model::ntfs::diff(&old, &new);
+/// assert_eq!(
+///     vec![DiffEntry {
+///         object_type: ObjectType::Line,
+///         object_id: "default_line".to_string(),
+///         kind: DiffKind::Modified { fields: vec!["line_name".to_string()] },
+///     }],
+///     diff.entries
+/// );
+/// # Ok::<(), transit_model::Error>(())
+/// ```
+pub fn diff(old: &Model, new: &Model) -> ModelDiff {
+    let mut entries = Vec::new();
+    diff_collection(
+        ObjectType::Network,
+        &old.networks,
+        &new.networks,
+        changed_codes,
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::Company,
+        &old.companies,
+        &new.companies,
+        changed_codes,
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::Line,
+        &old.lines,
+        &new.lines,
+        changed_codes_properties_and_comments,
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::Route,
+        &old.routes,
+        &new.routes,
+        changed_codes_properties_and_comments,
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::VehicleJourney,
+        &old.vehicle_journeys,
+        &new.vehicle_journeys,
+        |old_vj: &VehicleJourney, new_vj: &VehicleJourney| {
+            let mut fields = changed_codes_properties_and_comments(old_vj, new_vj);
+            if stop_times_changed(old, old_vj, new, new_vj) {
+                fields.push("stop_times".to_string());
+            }
+            fields
+        },
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::StopArea,
+        &old.stop_areas,
+        &new.stop_areas,
+        changed_codes_properties_and_comments,
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::StopPoint,
+        &old.stop_points,
+        &new.stop_points,
+        changed_codes_properties_and_comments,
+        &mut entries,
+    );
+    diff_collection(
+        ObjectType::Ticket,
+        &old.tickets,
+        &new.tickets,
+        no_other_field,
+        &mut entries,
+    );
+    ModelDiff { entries }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Collections;
@@ -486,6 +1039,8 @@ mod tests {
                 phone: Some("0123456789".to_string()),
                 address: Some("somewhere".to_string()),
                 sort_order: Some(1),
+                email: Some("contact@savac.fr".to_string()),
+                fare_url: Some("http://www.vianavigo.com/tarifs".to_string()),
                 codes: KeysValues::default(),
             },
             Network {
@@ -497,6 +1052,8 @@ mod tests {
                 phone: None,
                 address: None,
                 sort_order: None,
+                email: None,
+                fare_url: None,
                 codes: KeysValues::default(),
             },
         ]);
@@ -720,6 +1277,7 @@ mod tests {
                     },
                 ],
                 journey_pattern_id: Some(String::from("OIF:JP:1")),
+                occupancy: None,
             },
             VehicleJourney {
                 id: "OIF:90014407-1_425283-1".to_string(),
@@ -738,6 +1296,7 @@ mod tests {
                 geometry_id: None,
                 stop_times: vec![],
                 journey_pattern_id: Some(String::from("OIF:JP:1")),
+                occupancy: None,
             },
         ])
         .unwrap();
@@ -749,7 +1308,10 @@ mod tests {
         );
         let mut stop_time_ids = HashMap::new();
         stop_time_ids.insert(
-            ("OIF:87604986-1_11595-1".to_string(), 0),
+            (
+                vehicle_journeys.get_idx("OIF:87604986-1_11595-1").unwrap(),
+                0,
+            ),
             "StopTime:OIF:87604986-1_11595-1:0".to_string(),
         );
 
@@ -920,6 +1482,7 @@ mod tests {
             StopPoint {
                 id: "sp_1".to_string(),
                 name: "sp_name_1".to_string(),
+                name_alias: Some("sp_alias_1".to_string()),
                 visible: true,
                 coord: Coord {
                     lon: 2.073_034,
@@ -992,7 +1555,7 @@ mod tests {
 
             let mut collections = Collections::default();
             let mut handler = PathFileHandler::new(path.to_path_buf());
-            read::manage_stops(&mut collections, &mut handler).unwrap();
+            read::manage_stops(&mut collections, &mut handler, &DefaultIdGenerator).unwrap();
 
             assert_eq!(stop_points, collections.stop_points);
             assert_eq!(stop_areas, collections.stop_areas);
@@ -1154,6 +1717,7 @@ mod tests {
                 precision: None,
             }],
             journey_pattern_id: None,
+            occupancy: None,
         });
 
         let networks = CollectionWithId::from(Network {
@@ -1165,11 +1729,16 @@ mod tests {
             phone: None,
             address: None,
             sort_order: None,
+            email: None,
+            fare_url: None,
             codes: KeysValues::default(),
         });
 
         let mut stop_time_ids = HashMap::new();
-        stop_time_ids.insert((("VJ:1").to_string(), 0), "StopTime:VJ:1:0".to_string());
+        stop_time_ids.insert(
+            (vehicle_journeys.get_idx("VJ:1").unwrap(), 0),
+            "StopTime:VJ:1:0".to_string(),
+        );
         let mut stop_time_comments = HashMap::new();
         stop_time_comments.insert(("VJ:1".to_string(), 0), "c:2".to_string());
 
@@ -1215,7 +1784,7 @@ mod tests {
                 networks: make_collection_with_id(&mut handler, "networks.txt").unwrap(),
                 ..Default::default()
             };
-            read::manage_stops(&mut des_collections, &mut handler).unwrap();
+            read::manage_stops(&mut des_collections, &mut handler, &DefaultIdGenerator).unwrap();
             read::manage_stop_times(&mut des_collections, &mut handler).unwrap();
             read::manage_comments(&mut des_collections, &mut handler).unwrap();
             read::manage_codes(&mut des_collections, &mut handler).unwrap();
@@ -12,18 +12,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>
 
-use super::{Code, CommentLink, ObjectProperty, Stop, StopLocationType, StopTime};
+use super::{Code, CommentLink, ObjectProperty, Stop, StopLocationType, StopTime, ValidationError};
 use crate::model::Collections;
 use crate::ntfs::has_fares_v2;
 use crate::objects::*;
 use crate::read_utils::{read_objects, read_objects_loose, FileHandler};
 use crate::utils;
-use crate::Result;
+use crate::{IdGenerator, Result};
 use failure::{bail, ensure, format_err, ResultExt};
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use skip_error::skip_error_and_log;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::convert::TryFrom;
 use typed_index_collection::{Collection, CollectionWithId, Id, Idx};
 
@@ -75,6 +75,7 @@ impl TryFrom<Stop> for StopPoint {
         let stop_point = StopPoint {
             id: stop.id,
             name: stop.name,
+            name_alias: stop.name_alias,
             code: stop.code,
             visible: stop.visible,
             coord,
@@ -151,7 +152,11 @@ impl TryFrom<Stop> for StopLocation {
     }
 }
 
-pub(crate) fn manage_stops<H>(collections: &mut Collections, file_handler: &mut H) -> Result<()>
+pub(crate) fn manage_stops<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+    id_generator: &dyn IdGenerator,
+) -> Result<()>
 where
     for<'a> &'a mut H: FileHandler,
 {
@@ -166,6 +171,7 @@ where
                     skip_error_and_log!(StopPoint::try_from(stop.clone()), tracing::Level::WARN);
                 if stop.parent_station.is_none() {
                     let mut stop_area = StopArea::from(stop_point.clone());
+                    stop_area.id = id_generator.stop_area_id(&stop_point.id);
                     stop_point.stop_area_id = stop_area.id.clone();
                     stop_area.visible = stop.location_type == StopLocationType::StopPoint;
                     skip_error_and_log!(stop_areas.push(stop_area), tracing::Level::WARN);
@@ -309,10 +315,7 @@ where
         });
 
         if let Some(stop_time_id) = stop_time.stop_time_id {
-            stop_time_ids.insert(
-                (stop_time.trip_id.clone(), stop_time.stop_sequence),
-                stop_time_id,
-            );
+            stop_time_ids.insert((vj_idx, stop_time.stop_sequence), stop_time_id);
         }
 
         collections
@@ -394,6 +397,124 @@ where
     Ok(())
 }
 
+// `object_type` is read as a `String` to report the unknown types instead
+// of failing
+#[derive(Deserialize, Debug)]
+struct ObjectCode {
+    object_type: String,
+    object_id: String,
+}
+
+// Types of the objects supporting codes
+fn code_object_type(object_type: &str) -> Option<ObjectType> {
+    match object_type {
+        "stop_area" => Some(ObjectType::StopArea),
+        "stop_point" => Some(ObjectType::StopPoint),
+        "network" => Some(ObjectType::Network),
+        "line" => Some(ObjectType::Line),
+        "route" => Some(ObjectType::Route),
+        "trip" => Some(ObjectType::VehicleJourney),
+        "company" => Some(ObjectType::Company),
+        _ => None,
+    }
+}
+
+fn read_ids<H, T>(file_handler: &mut H, file_name: &str) -> Result<HashSet<String>>
+where
+    for<'a> &'a mut H: FileHandler,
+    T: Id<T> + for<'de> Deserialize<'de>,
+{
+    let objects = read_objects::<_, T>(file_handler, file_name, true)?;
+    Ok(objects
+        .iter()
+        .map(|object| object.id().to_string())
+        .collect())
+}
+
+// Identifiers of the objects of a type supporting codes, only reading the
+// file of this type
+fn read_object_ids<H>(
+    file_handler: &mut H,
+    object_type: &ObjectType,
+    id_generator: &dyn IdGenerator,
+) -> Result<HashSet<String>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    match object_type {
+        ObjectType::StopArea | ObjectType::StopPoint => {
+            let stops = read_objects::<_, Stop>(file_handler, "stops.txt", true)?;
+            let mut ids = HashSet::new();
+            for stop in stops {
+                match stop.location_type {
+                    StopLocationType::StopArea if *object_type == ObjectType::StopArea => {
+                        ids.insert(stop.id);
+                    }
+                    // the stop area created for a stop point without parent
+                    StopLocationType::StopPoint | StopLocationType::GeographicArea
+                        if *object_type == ObjectType::StopArea
+                            && stop.parent_station.is_none() =>
+                    {
+                        ids.insert(id_generator.stop_area_id(&stop.id));
+                    }
+                    StopLocationType::StopPoint | StopLocationType::GeographicArea
+                        if *object_type == ObjectType::StopPoint =>
+                    {
+                        ids.insert(stop.id);
+                    }
+                    _ => {}
+                }
+            }
+            Ok(ids)
+        }
+        ObjectType::Network => read_ids::<_, Network>(file_handler, "networks.txt"),
+        ObjectType::Line => read_ids::<_, Line>(file_handler, "lines.txt"),
+        ObjectType::Route => read_ids::<_, Route>(file_handler, "routes.txt"),
+        ObjectType::VehicleJourney => read_ids::<_, VehicleJourney>(file_handler, "trips.txt"),
+        ObjectType::Company => read_ids::<_, Company>(file_handler, "companies.txt"),
+        _ => Ok(HashSet::new()),
+    }
+}
+
+// Check each line of `object_codes.txt`, only reading the files of the
+// referenced object types
+pub(crate) fn validate_codes<H>(
+    file_handler: &mut H,
+    id_generator: &dyn IdGenerator,
+) -> Result<Vec<ValidationError>>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let codes = read_objects::<_, ObjectCode>(file_handler, "object_codes.txt", false)?;
+    let mut ids_by_type: HashMap<&'static str, HashSet<String>> = HashMap::new();
+    let mut errors = Vec::new();
+    for code in codes {
+        let object_type = match code_object_type(&code.object_type) {
+            Some(object_type) => object_type,
+            None => {
+                errors.push(ValidationError::UnknownObjectType {
+                    object_type: code.object_type,
+                    object_id: code.object_id,
+                });
+                continue;
+            }
+        };
+        let ids = match ids_by_type.entry(object_type.as_str()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(read_object_ids(file_handler, &object_type, id_generator)?)
+            }
+        };
+        if !ids.contains(&code.object_id) {
+            errors.push(ValidationError::UnresolvedReference {
+                object_type: code.object_type,
+                object_id: code.object_id,
+            });
+        }
+    }
+    Ok(errors)
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct FeedInfo {
     #[serde(rename = "feed_info_param")]
@@ -425,6 +546,44 @@ where
     Ok(())
 }
 
+// The frequencies with a null headway, or ending before they start, are
+// ignored. The lines which cannot be read (a negative headway for example)
+// are also ignored.
+/// Like in the GTFS reader, a frequency with a null headway is ignored and a
+/// frequency ending before it starts is considered to end on the next day.
+pub(crate) fn manage_frequencies<H>(
+    collections: &mut Collections,
+    file_handler: &mut H,
+) -> Result<()>
+where
+    for<'a> &'a mut H: FileHandler,
+{
+    let file = "frequencies.txt";
+    let mut frequencies = read_objects_loose::<_, Frequency>(file_handler, file, false)?;
+    frequencies.retain(|frequency| {
+        if frequency.headway_secs == 0 {
+            warn!(
+                "Problem reading {:?}: frequency of trip {:?} has a null headway and is ignored",
+                file, frequency.vehicle_journey_id
+            );
+            false
+        } else {
+            true
+        }
+    });
+    for frequency in &mut frequencies {
+        if frequency.end_time < frequency.start_time {
+            warn!(
+                "Problem reading {:?}: frequency of trip {:?} ends ({}) before it starts ({}), considering it ends the next day",
+                file, frequency.vehicle_journey_id, frequency.end_time, frequency.start_time
+            );
+            frequency.end_time = frequency.end_time + Time::new(24, 0, 0);
+        }
+    }
+    collections.frequencies = Collection::new(frequencies);
+    Ok(())
+}
+
 fn insert_comment_link<T>(
     collection: &mut CollectionWithId<T>,
     comments: &CollectionWithId<Comment>,
@@ -499,7 +658,12 @@ where
     let stop_time_ids = collections
         .stop_time_ids
         .iter()
-        .map(|(k, v)| (v, k.clone()))
+        .map(|((vj_idx, sequence), stop_time_id)| {
+            (
+                stop_time_id,
+                (collections.vehicle_journeys[*vj_idx].id.clone(), *sequence),
+            )
+        })
         .collect();
     // info!("Reading comment_links.txt");
     for comment_link in comment_links {
@@ -564,6 +728,30 @@ where
     );
 }
 
+fn manage_occupancy(
+    vehicle_journeys: &mut CollectionWithId<VehicleJourney>,
+    obj_prop: ObjectProperty,
+) {
+    let occupancy = match obj_prop.object_property_value.parse::<OccupancyStatus>() {
+        Ok(occupancy) => occupancy,
+        Err(e) => {
+            warn!(
+                "object_properties.txt: object_id={}: {}",
+                obj_prop.object_id, e
+            );
+            return;
+        }
+    };
+    match vehicle_journeys.get_mut(&obj_prop.object_id) {
+        Some(mut vj) => vj.occupancy = Some(occupancy),
+        None => error!(
+            "object_properties.txt: object_type={} object_id={} not found",
+            obj_prop.object_type.as_str(),
+            obj_prop.object_id
+        ),
+    }
+}
+
 pub(crate) fn manage_object_properties<H>(
     collections: &mut Collections,
     file_handler: &mut H,
@@ -579,6 +767,9 @@ where
             ObjectType::StopPoint => insert_object_property(&mut collections.stop_points, obj_prop),
             ObjectType::Line => insert_object_property(&mut collections.lines, obj_prop),
             ObjectType::Route => insert_object_property(&mut collections.routes, obj_prop),
+            ObjectType::VehicleJourney if obj_prop.object_property_name == OCCUPANCY_PROPERTY => {
+                manage_occupancy(&mut collections.vehicle_journeys, obj_prop)
+            }
             ObjectType::VehicleJourney => {
                 insert_object_property(&mut collections.vehicle_journeys, obj_prop)
             }
@@ -689,6 +880,7 @@ mod tests {
     use crate::read_utils::{self, PathFileHandler};
     use crate::test_utils::*;
     use crate::utils::make_collection_with_id;
+    use crate::DefaultIdGenerator;
     use pretty_assertions::assert_eq;
     use std::path;
 
@@ -770,7 +962,7 @@ mod tests {
         collections.companies =
             make_collection_with_id(&mut file_handler, "companies.txt").unwrap();
         calendars::manage_calendars(&mut file_handler, &mut collections).unwrap();
-        manage_stops(&mut collections, &mut file_handler).unwrap();
+        manage_stops(&mut collections, &mut file_handler, &DefaultIdGenerator).unwrap();
         manage_stop_times(&mut collections, &mut file_handler).unwrap();
         manage_codes(&mut collections, &mut file_handler).unwrap();
         collections
@@ -786,7 +978,7 @@ mod tests {
             create_file_with_content(path, "stops.txt", stops_content);
             let mut collections = Collections::default();
             let mut handler = PathFileHandler::new(path.to_path_buf());
-            manage_stops(&mut collections, &mut handler).unwrap();
+            manage_stops(&mut collections, &mut handler, &DefaultIdGenerator).unwrap();
             assert_eq!(1, collections.stop_points.len());
             let stop_point = collections.stop_points.values().next().unwrap();
             assert_eq!("sp:01", stop_point.id);
@@ -797,6 +989,29 @@ mod tests {
             assert_eq!("Navitia:sp:01", stop_area.id);
         });
     }
+
+    #[test]
+    fn read_stop_points_with_no_parent_and_custom_id_generator() {
+        struct CustomIdGenerator;
+        impl IdGenerator for CustomIdGenerator {
+            fn stop_area_id(&self, stop_point_id: &str) -> String {
+                format!("area_of_{}", stop_point_id)
+            }
+        }
+        let stops_content =
+            "stop_id,stop_name,stop_code,stop_lat,stop_lon,location_type,parent_station\n\
+             sp:01,my stop name 1,stopcode,0.1,1.2,0,";
+
+        test_in_tmp_dir(|path| {
+            create_file_with_content(path, "stops.txt", stops_content);
+            let mut collections = Collections::default();
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            manage_stops(&mut collections, &mut handler, &CustomIdGenerator).unwrap();
+            let stop_point = collections.stop_points.get("sp:01").unwrap();
+            assert_eq!("area_of_sp:01", stop_point.stop_area_id);
+            assert!(collections.stop_areas.contains_id("area_of_sp:01"));
+        });
+    }
     #[test]
     fn ntfs_stop_times_precision() {
         test_in_tmp_dir(|path| {
@@ -875,6 +1090,40 @@ mod tests {
             );
         });
     }
+    #[test]
+    fn frequencies_with_invalid_values() {
+        test_in_tmp_dir(|path| {
+            let frequencies_content = "trip_id,start_time,end_time,headway_secs\n\
+                                       vj_1,08:00:00,09:00:00,0\n\
+                                       vj_1,10:00:00,11:00:00,-600\n\
+                                       vj_1,13:00:00,12:00:00,600\n\
+                                       vj_1,14:00:00,15:00:00,600";
+            create_file_with_content(path, "frequencies.txt", frequencies_content);
+
+            let mut collections = Collections::default();
+            let mut handler = PathFileHandler::new(path.to_path_buf());
+            manage_frequencies(&mut collections, &mut handler).unwrap();
+
+            assert_eq!(
+                vec![
+                    Frequency {
+                        vehicle_journey_id: "vj_1".to_string(),
+                        start_time: Time::new(13, 0, 0),
+                        end_time: Time::new(36, 0, 0),
+                        headway_secs: 600,
+                    },
+                    Frequency {
+                        vehicle_journey_id: "vj_1".to_string(),
+                        start_time: Time::new(14, 0, 0),
+                        end_time: Time::new(15, 0, 0),
+                        headway_secs: 600,
+                    }
+                ],
+                collections.frequencies.take()
+            );
+        });
+    }
+
     #[test]
     fn company_object_codes() {
         test_in_tmp_dir(|path| {
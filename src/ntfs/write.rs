@@ -16,6 +16,7 @@ use super::{Code, CommentLink, ObjectProperty, Result, Stop, StopLocationType, S
 use crate::model::Collections;
 use crate::ntfs::{has_fares_v1, has_fares_v2};
 use crate::objects::*;
+use crate::read_utils::expected_columns;
 use crate::NTFS_VERSION;
 use chrono::{DateTime, Duration, FixedOffset};
 use csv::Writer;
@@ -25,7 +26,7 @@ use rust_decimal::{prelude::ToPrimitive, Decimal};
 use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::path;
-use typed_index_collection::{Collection, CollectionWithId, Id};
+use typed_index_collection::{Collection, CollectionWithId, Id, Idx};
 
 pub fn write_feed_infos(
     path: &path::Path,
@@ -71,12 +72,15 @@ pub fn write_feed_infos(
     Ok(())
 }
 
+// The stop times of each vehicle journey are written as soon as the vehicle
+// journey is read, without collecting all the stop times first: the file is
+// grouped by trip_id (in the order of the collection), but not globally sorted.
 pub fn write_vehicle_journeys_and_stop_times(
     path: &path::Path,
     vehicle_journeys: &CollectionWithId<VehicleJourney>,
     stop_points: &CollectionWithId<StopPoint>,
     stop_time_headsigns: &HashMap<(String, u32), String>,
-    stop_time_ids: &HashMap<(String, u32), String>,
+    stop_time_ids: &HashMap<(Idx<VehicleJourney>, u32), String>,
 ) -> Result<()> {
     info!("Writing trips.txt and stop_times.txt");
     let trip_path = path.join("trips.txt");
@@ -114,9 +118,7 @@ pub fn write_vehicle_journeys_and_stop_times(
                     stop_headsign: stop_time_headsigns
                         .get(&(vehicle_journeys[vj_idx].id.clone(), st.sequence))
                         .cloned(),
-                    stop_time_id: stop_time_ids
-                        .get(&(vehicle_journeys[vj_idx].id.clone(), st.sequence))
-                        .cloned(),
+                    stop_time_id: stop_time_ids.get(&(vj_idx, st.sequence)).cloned(),
                     precision,
                 })
                 .with_context(|_| format!("Error reading {:?}", st_wtr))?;
@@ -549,6 +551,7 @@ pub fn write_stops(
                 geometry_id: sl.geometry_id.clone(),
                 level_id: sl.level_id.clone(),
                 platform_code: None,
+                name_alias: None,
             })?;
         }
         Ok(())
@@ -579,6 +582,7 @@ pub fn write_stops(
             geometry_id: st.geometry_id.clone(),
             level_id: st.level_id.clone(),
             platform_code: st.platform_code.clone(),
+            name_alias: st.name_alias.clone(),
         })
         .with_context(|_| format!("Error reading {:?}", path))?;
     }
@@ -599,6 +603,7 @@ pub fn write_stops(
             geometry_id: sa.geometry_id.clone(),
             level_id: sa.level_id.clone(),
             platform_code: None,
+            name_alias: None,
         })
         .with_context(|_| format!("Error reading {:?}", path))?;
     }
@@ -635,15 +640,19 @@ where
 
 fn write_stop_time_comment_links<W>(
     wtr: &mut csv::Writer<W>,
-    stop_time_ids: &HashMap<(String, u32), String>,
+    vehicle_journeys: &CollectionWithId<VehicleJourney>,
+    stop_time_ids: &HashMap<(Idx<VehicleJourney>, u32), String>,
     stop_time_comments: &HashMap<(String, u32), String>,
     path: &path::Path,
 ) -> Result<()>
 where
     W: ::std::io::Write,
 {
-    for (idx_sequence, id_comment) in stop_time_comments {
-        let st_id = &stop_time_ids[idx_sequence];
+    for ((vj_id, sequence), id_comment) in stop_time_comments {
+        let vj_idx = vehicle_journeys
+            .get_idx(vj_id)
+            .ok_or_else(|| format_err!("trip_id={} not found", vj_id))?;
+        let st_id = &stop_time_ids[&(vj_idx, *sequence)];
 
         wtr.serialize(CommentLink {
             object_id: st_id.to_string(),
@@ -703,6 +712,7 @@ pub fn write_comments(path: &path::Path, collections: &Collections) -> Result<()
 
     write_stop_time_comment_links(
         &mut cl_wtr,
+        &collections.vehicle_journeys,
         &collections.stop_time_ids,
         &collections.stop_time_comments,
         &comment_links_path,
@@ -814,6 +824,10 @@ pub fn write_object_properties(path: &path::Path, collections: &Collections) ->
         && collection_has_no_object_properties(&collections.lines)
         && collection_has_no_object_properties(&collections.routes)
         && collection_has_no_object_properties(&collections.vehicle_journeys)
+        && collections
+            .vehicle_journeys
+            .values()
+            .all(|vj| vj.occupancy.is_none())
     {
         return Ok(());
     }
@@ -833,9 +847,63 @@ pub fn write_object_properties(path: &path::Path, collections: &Collections) ->
         &collections.vehicle_journeys,
         &path,
     )?;
+    for vj in collections.vehicle_journeys.values() {
+        if let Some(occupancy) = vj.occupancy {
+            wtr.serialize(ObjectProperty {
+                object_id: vj.id.clone(),
+                object_type: ObjectType::VehicleJourney,
+                object_property_name: OCCUPANCY_PROPERTY.to_string(),
+                object_property_value: occupancy.as_str().to_string(),
+            })
+            .with_context(|_| format!("Error reading {:?}", path))?;
+        }
+    }
+
+    wtr.flush()
+        .with_context(|_| format!("Error reading {:?}", path))?;
+
+    Ok(())
+}
 
+// Write a file with the columns of `T` as header, replacing any existing file.
+// The NTFS objects are written with the same columns as they are read, which
+// is checked by the `empty_optional_files_headers` test.
+fn write_header_only<T>(path: &path::Path, file: &str) -> Result<()>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    let path = path.join(file);
+    info!("Writing empty {}", file);
+    let mut wtr =
+        csv::Writer::from_path(&path).with_context(|_| format!("Error reading {:?}", path))?;
+    wtr.write_record(expected_columns::<T>())
+        .with_context(|_| format!("Error reading {:?}", path))?;
     wtr.flush()
         .with_context(|_| format!("Error reading {:?}", path))?;
+    Ok(())
+}
 
+pub fn write_empty_optional_files(path: &path::Path) -> Result<()> {
+    write_header_only::<Comment>(path, "comments.txt")?;
+    write_header_only::<CommentLink>(path, "comment_links.txt")?;
+    write_header_only::<Code>(path, "object_codes.txt")?;
+    write_header_only::<ObjectProperty>(path, "object_properties.txt")?;
+    write_header_only::<Equipment>(path, "equipments.txt")?;
+    write_header_only::<TripProperty>(path, "trip_properties.txt")?;
+    write_header_only::<Geometry>(path, "geometries.txt")?;
+    write_header_only::<Transfer>(path, "transfers.txt")?;
+    write_header_only::<AdminStation>(path, "admin_stations.txt")?;
+    write_header_only::<Frequency>(path, "frequencies.txt")?;
+    write_header_only::<Ticket>(path, "tickets.txt")?;
+    write_header_only::<TicketUse>(path, "ticket_uses.txt")?;
+    write_header_only::<TicketPrice>(path, "ticket_prices.txt")?;
+    write_header_only::<TicketUsePerimeter>(path, "ticket_use_perimeters.txt")?;
+    write_header_only::<TicketUseRestriction>(path, "ticket_use_restrictions.txt")?;
+    write_header_only::<GridCalendar>(path, "grid_calendars.txt")?;
+    write_header_only::<GridExceptionDate>(path, "grid_exception_dates.txt")?;
+    write_header_only::<GridPeriod>(path, "grid_periods.txt")?;
+    write_header_only::<GridRelCalendarLine>(path, "grid_rel_calendar_line.txt")?;
+    write_header_only::<Pathway>(path, "pathways.txt")?;
+    write_header_only::<Level>(path, "levels.txt")?;
     Ok(())
 }
@@ -16,7 +16,7 @@
 
 #![allow(missing_docs)]
 
-use crate::{utils::*, AddPrefix, PrefixConfiguration};
+use crate::{utils::*, AddPrefix, DefaultIdGenerator, IdGenerator, PrefixConfiguration};
 use chrono::NaiveDate;
 use chrono_tz::Tz;
 use derivative::Derivative;
@@ -25,6 +25,7 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
+use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 use std::ops::{Add, Div, Rem, Sub};
 use std::str::FromStr;
@@ -372,6 +373,14 @@ pub struct Network {
     pub address: Option<String>,
     #[serde(rename = "network_sort_order")]
     pub sort_order: Option<u32>,
+    /// Written in the `network_email` column, an extension of the NTFS
+    /// keeping the GTFS `agency_email`.
+    #[serde(rename = "network_email")]
+    pub email: Option<String>,
+    /// Written in the `network_fare_url` column, an extension of the NTFS
+    /// keeping the GTFS `agency_fare_url`.
+    #[serde(rename = "network_fare_url")]
+    pub fare_url: Option<String>,
 }
 
 impl_id!(Network);
@@ -397,6 +406,35 @@ pub struct Rgb {
     pub blue: u8,
 }
 
+impl Rgb {
+    /// Relative luminance of the color, between 0 (black) and 1 (white), as
+    /// defined by the
+    /// [WCAG](https://www.w3.org/TR/WCAG21/#dfn-relative-luminance).
+    pub fn relative_luminance(&self) -> f64 {
+        fn linearize(component: u8) -> f64 {
+            let c = f64::from(component) / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        0.2126 * linearize(self.red)
+            + 0.7152 * linearize(self.green)
+            + 0.0722 * linearize(self.blue)
+    }
+
+    /// Contrast ratio between two colors, from 1 (same luminance) to 21
+    /// (black and white), as defined by the
+    /// [WCAG](https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio). Useful to
+    /// check that a `text_color` is readable on a `color`.
+    pub fn contrast_ratio(&self, other: &Rgb) -> f64 {
+        let (l1, l2) = (self.relative_luminance(), other.relative_luminance());
+        let (lighter, darker) = if l1 > l2 { (l1, l2) } else { (l2, l1) };
+        (lighter + 0.05) / (darker + 0.05)
+    }
+}
+
 impl std::fmt::Display for Rgb {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let color = format!("{:02X}{:02X}{:02X}", self.red, self.green, self.blue);
@@ -418,6 +456,7 @@ impl FromStr for Rgb {
     type Err = RgbError;
 
     fn from_str(color_hex: &str) -> Result<Self, Self::Err> {
+        let color_hex = color_hex.strip_prefix('#').unwrap_or(color_hex);
         let color_dec = u32::from_str_radix(color_hex, 16).map_err(|_err| RgbError::NotHexa)?;
 
         if color_dec >= 1 << 24 {
@@ -589,6 +628,81 @@ impl GetObjectType for Route {
     }
 }
 
+/// Name of the object property carrying the occupancy of a vehicle journey
+/// in the NTFS.
+pub const OCCUPANCY_PROPERTY: &str = "occupancy";
+
+/// Passenger occupancy of a vehicle, with the values of the GTFS-RT
+/// `VehiclePosition.OccupancyStatus` enumeration.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OccupancyStatus {
+    /// The vehicle is considered empty, few or no passengers are on board
+    Empty,
+    /// A large percentage of seats are available
+    ManySeatsAvailable,
+    /// A small percentage of seats are available
+    FewSeatsAvailable,
+    /// Only standing room is available
+    StandingRoomOnly,
+    /// Only standing room is available, for few passengers
+    CrushedStandingRoomOnly,
+    /// The vehicle is considered full
+    Full,
+    /// The vehicle is not accepting any more passengers
+    NotAcceptingPassengers,
+}
+
+impl OccupancyStatus {
+    /// Value of the status, as written in the NTFS object property.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OccupancyStatus::Empty => "EMPTY",
+            OccupancyStatus::ManySeatsAvailable => "MANY_SEATS_AVAILABLE",
+            OccupancyStatus::FewSeatsAvailable => "FEW_SEATS_AVAILABLE",
+            OccupancyStatus::StandingRoomOnly => "STANDING_ROOM_ONLY",
+            OccupancyStatus::CrushedStandingRoomOnly => "CRUSHED_STANDING_ROOM_ONLY",
+            OccupancyStatus::Full => "FULL",
+            OccupancyStatus::NotAcceptingPassengers => "NOT_ACCEPTING_PASSENGERS",
+        }
+    }
+}
+
+impl FromStr for OccupancyStatus {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let status = match value {
+            "EMPTY" => OccupancyStatus::Empty,
+            "MANY_SEATS_AVAILABLE" => OccupancyStatus::ManySeatsAvailable,
+            "FEW_SEATS_AVAILABLE" => OccupancyStatus::FewSeatsAvailable,
+            "STANDING_ROOM_ONLY" => OccupancyStatus::StandingRoomOnly,
+            "CRUSHED_STANDING_ROOM_ONLY" => OccupancyStatus::CrushedStandingRoomOnly,
+            "FULL" => OccupancyStatus::Full,
+            "NOT_ACCEPTING_PASSENGERS" => OccupancyStatus::NotAcceptingPassengers,
+            _ => return Err(format!("unknown occupancy status '{}'", value)),
+        };
+        Ok(status)
+    }
+}
+
+impl TryFrom<i32> for OccupancyStatus {
+    type Error = String;
+    /// Convert the numeric value of a GTFS-RT `OccupancyStatus`.
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        let status = match value {
+            0 => OccupancyStatus::Empty,
+            1 => OccupancyStatus::ManySeatsAvailable,
+            2 => OccupancyStatus::FewSeatsAvailable,
+            3 => OccupancyStatus::StandingRoomOnly,
+            4 => OccupancyStatus::CrushedStandingRoomOnly,
+            5 => OccupancyStatus::Full,
+            6 => OccupancyStatus::NotAcceptingPassengers,
+            _ => return Err(format!("unknown occupancy status {}", value)),
+        };
+        Ok(status)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct VehicleJourney {
     #[serde(rename = "trip_id")]
@@ -618,6 +732,8 @@ pub struct VehicleJourney {
     #[serde(skip)]
     pub stop_times: Vec<StopTime>,
     pub journey_pattern_id: Option<String>,
+    #[serde(skip)]
+    pub occupancy: Option<OccupancyStatus>,
 }
 impl Default for VehicleJourney {
     fn default() -> VehicleJourney {
@@ -638,6 +754,7 @@ impl Default for VehicleJourney {
             geometry_id: None,
             stop_times: vec![],
             journey_pattern_id: None,
+            occupancy: None,
         }
     }
 }
@@ -748,6 +865,8 @@ pub enum TimeError {
     WrongFormat,
     #[error("Minutes and Seconds should be in [0..59] range")]
     WrongValue,
+    #[error("Hours are too big to be represented")]
+    Overflow,
 }
 impl From<std::num::ParseIntError> for TimeError {
     fn from(_error: std::num::ParseIntError) -> Self {
@@ -813,7 +932,12 @@ impl FromStr for Time {
         if minutes > 59 || seconds > 59 {
             return Err(TimeError::WrongValue);
         }
-        Ok(Time::new(hours, minutes, seconds))
+        // hours may exceed 24 for services running past midnight
+        hours
+            .checked_mul(60 * 60)
+            .and_then(|h| h.checked_add(minutes * 60 + seconds))
+            .map(Time)
+            .ok_or(TimeError::Overflow)
     }
 }
 
@@ -877,6 +1001,20 @@ pub struct StopTime {
     pub precision: Option<StopTimePrecision>,
 }
 
+impl StopTime {
+    /// Time spent at the stop, between the arrival and the departure.
+    /// A departure time lower than the arrival time is considered to be on
+    /// the next day (e.g. arrival at 23:59:00 and departure at 00:01:00).
+    pub fn dwell(&self) -> chrono::Duration {
+        let dwell = if self.departure_time < self.arrival_time {
+            self.departure_time + Time::new(24, 0, 0) - self.arrival_time
+        } else {
+            self.departure_time - self.arrival_time
+        };
+        chrono::Duration::seconds(i64::from(dwell.total_seconds()))
+    }
+}
+
 impl Ord for StopTime {
     fn cmp(&self, other: &StopTime) -> Ordering {
         self.sequence.cmp(&other.sequence)
@@ -951,6 +1089,47 @@ impl From<Coord> for GeoPoint<f64> {
     }
 }
 
+/// Geographic extent of a set of coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl BoundingBox {
+    /// Smallest `BoundingBox` containing all the `coords`, `None` if there
+    /// is no coordinate
+    pub fn from_coords<'a, I>(coords: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a Coord>,
+    {
+        coords.into_iter().fold(None, |bbox, coord| {
+            Some(match bbox {
+                None => BoundingBox {
+                    min_lon: coord.lon,
+                    min_lat: coord.lat,
+                    max_lon: coord.lon,
+                    max_lat: coord.lat,
+                },
+                Some(bbox) => BoundingBox {
+                    min_lon: bbox.min_lon.min(coord.lon),
+                    min_lat: bbox.min_lat.min(coord.lat),
+                    max_lon: bbox.max_lon.max(coord.lon),
+                    max_lat: bbox.max_lat.max(coord.lat),
+                },
+            })
+        })
+    }
+
+    /// Check if the `coord` is inside the `BoundingBox` (borders included)
+    pub fn contains(&self, coord: &Coord) -> bool {
+        (self.min_lon..=self.max_lon).contains(&coord.lon)
+            && (self.min_lat..=self.max_lat).contains(&coord.lat)
+    }
+}
+
 // Mean Earth radius in meters
 const EARTH_RADIUS: f64 = 6_371_000.0;
 
@@ -1076,7 +1255,7 @@ impl_id!(StopArea);
 impl From<StopPoint> for StopArea {
     fn from(stop_point: StopPoint) -> Self {
         StopArea {
-            id: format!("Navitia:{}", stop_point.id),
+            id: DefaultIdGenerator.stop_area_id(&stop_point.id),
             name: stop_point.name,
             codes: KeysValues::default(),
             object_properties: PropertiesMap::default(),
@@ -1119,7 +1298,7 @@ impl GetObjectType for StopArea {
         ObjectType::StopArea
     }
 }
-#[derive(Derivative, Debug, PartialEq, Clone)]
+#[derive(Derivative, Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[derivative(Default)]
 pub enum StopType {
     #[derivative(Default)]
@@ -1134,6 +1313,7 @@ pub enum StopType {
 pub struct StopPoint {
     pub id: String,
     pub name: String,
+    pub name_alias: Option<String>,
     pub code: Option<String>,
     #[serde(skip)]
     pub codes: KeysValues,
@@ -1299,6 +1479,24 @@ impl AddPrefix for Level {
 }
 impl_id!(Level);
 
+/// A group of stops, as described in GTFS `areas.txt`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default, Clone)]
+pub struct Area {
+    #[serde(rename = "area_id", deserialize_with = "de_without_slashes")]
+    pub id: String,
+    pub area_name: Option<String>,
+}
+impl_id!(Area);
+
+/// Assignment of a stop to an `Area`, as described in GTFS `stop_areas.txt`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default, Clone)]
+pub struct StopArea2 {
+    #[serde(deserialize_with = "de_without_slashes")]
+    pub area_id: String,
+    #[serde(deserialize_with = "de_without_slashes")]
+    pub stop_id: String,
+}
+
 pub type Date = chrono::NaiveDate;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -1425,6 +1623,50 @@ impl AddPrefix for Comment {
     }
 }
 
+/// When an on-demand service has to be booked, see [BookingRule].
+#[derive(
+    Serialize, Deserialize, Debug, Derivative, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy,
+)]
+#[derivative(Default)]
+pub enum BookingType {
+    /// Real time booking
+    #[derivative(Default)]
+    #[serde(rename = "0")]
+    RealTime,
+    /// Up to same-day booking, with advance notice
+    #[serde(rename = "1")]
+    SameDay,
+    /// Up to prior day(s) booking
+    #[serde(rename = "2")]
+    PriorDays,
+}
+
+/// How to book an on-demand service, as described in GTFS-Flex
+/// `booking_rules.txt`. The stop times reference it from
+/// `Collections::stop_time_pickup_booking_rules` and
+/// `Collections::stop_time_drop_off_booking_rules`.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
+pub struct BookingRule {
+    #[serde(rename = "booking_rule_id")]
+    pub id: String,
+    #[serde(deserialize_with = "de_with_empty_default", default)]
+    pub booking_type: BookingType,
+    pub prior_notice_duration_min: Option<u32>,
+    pub prior_notice_last_day: Option<u32>,
+    pub message: Option<String>,
+    pub phone_number: Option<String>,
+    pub info_url: Option<String>,
+    pub booking_url: Option<String>,
+}
+
+impl_id!(BookingRule);
+
+impl AddPrefix for BookingRule {
+    fn prefix(&mut self, prefix_conf: &PrefixConfiguration) {
+        self.id = prefix_conf.schedule_prefix(self.id.as_str());
+    }
+}
+
 #[derive(
     Serialize, Deserialize, Debug, Derivative, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy,
 )]
@@ -1952,6 +2194,44 @@ mod tests {
         assert_eq!(255, rgb.blue);
     }
 
+    #[test]
+    fn rgb_deserialization_with_leading_hash() {
+        let rgb: Rgb = "#007DFF".parse().unwrap();
+        assert_eq!(
+            Rgb {
+                red: 0,
+                green: 125,
+                blue: 255
+            },
+            rgb
+        );
+        assert_eq!("007DFF", rgb.to_string());
+        assert!("##007DFF".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn rgb_contrast_ratio() {
+        let white = Rgb {
+            red: 255,
+            green: 255,
+            blue: 255,
+        };
+        let black = Rgb {
+            red: 0,
+            green: 0,
+            blue: 0,
+        };
+        assert_relative_eq!(21.0, white.contrast_ratio(&black));
+        assert_relative_eq!(21.0, black.contrast_ratio(&white));
+        assert_relative_eq!(1.0, white.contrast_ratio(&white));
+        let red = Rgb {
+            red: 255,
+            green: 0,
+            blue: 0,
+        };
+        assert_relative_eq!(4.0, red.contrast_ratio(&white), epsilon = 0.01);
+    }
+
     #[test]
     fn time_serialization() {
         let ser = |h, m, s| serde_json::to_value(&Time::new(h, m, s)).unwrap();
@@ -1968,6 +2248,12 @@ mod tests {
         assert_eq!(Time::new(13, 37, 0), de("13:37:00").unwrap());
         assert_eq!(Time::new(0, 0, 0), de("0:0:0").unwrap());
         assert_eq!(Time::new(25, 42, 42), de("25:42:42").unwrap());
+        assert_eq!(Time::new(26, 0, 0), de("26:00:00").unwrap());
+        assert_eq!(Time::new(48, 0, 0), de("48:00:00").unwrap());
+        assert_eq!(
+            2 * 24 * 60 * 60,
+            "48:00:00".parse::<Time>().unwrap().total_seconds()
+        );
         assert_eq!(Time::new(13, 37, 0), de("13:37:00.000").unwrap());
         assert_eq!(Time::new(13, 37, 0), de("13:37:00.999").unwrap()); // currently floor on ms (not strictly required)
 
@@ -1977,6 +2263,25 @@ mod tests {
         assert!(de("AA:00:00").is_err());
         assert!(de("00:AA:00").is_err());
         assert!(de("00:00:AA").is_err());
+        assert!(de("2000000:00:00").is_err());
+    }
+
+    #[test]
+    fn occupancy_status_conversions() {
+        assert_eq!(
+            Ok(OccupancyStatus::FewSeatsAvailable),
+            OccupancyStatus::try_from(2)
+        );
+        assert!(OccupancyStatus::try_from(7).is_err());
+        for value in 0..=6 {
+            let status = OccupancyStatus::try_from(value).unwrap();
+            assert_eq!(Ok(status), status.as_str().parse());
+            assert_eq!(
+                status.as_str(),
+                serde_json::to_value(&status).unwrap().as_str().unwrap()
+            );
+        }
+        assert!("UNKNOWN".parse::<OccupancyStatus>().is_err());
     }
 
     // distance between COORD1 and COORD2 is 357.64 from
@@ -1999,6 +2304,42 @@ mod tests {
         assert_relative_eq!(COORD2.distance_to(&COORD1), 357.644, epsilon = EPSILON);
     }
 
+    #[test]
+    fn stop_time_dwell() {
+        use typed_index_collection::CollectionWithId;
+        let stop_points = CollectionWithId::from(StopPoint::default());
+        let stop_point_idx = stop_points.get_idx(&StopPoint::default().id).unwrap();
+        let stop_time = |arrival_time: Time, departure_time: Time| StopTime {
+            stop_point_idx,
+            sequence: 0,
+            arrival_time,
+            departure_time,
+            boarding_duration: 0,
+            alighting_duration: 0,
+            pickup_type: 0,
+            drop_off_type: 0,
+            datetime_estimated: false,
+            local_zone_id: None,
+            precision: None,
+        };
+        assert_eq!(
+            chrono::Duration::zero(),
+            stop_time(Time::new(10, 0, 0), Time::new(10, 0, 0)).dwell()
+        );
+        assert_eq!(
+            chrono::Duration::seconds(90),
+            stop_time(Time::new(10, 0, 0), Time::new(10, 1, 30)).dwell()
+        );
+        assert_eq!(
+            chrono::Duration::minutes(2),
+            stop_time(Time::new(24, 59, 0), Time::new(25, 1, 0)).dwell()
+        );
+        assert_eq!(
+            chrono::Duration::minutes(2),
+            stop_time(Time::new(23, 59, 0), Time::new(0, 1, 0)).dwell()
+        );
+    }
+
     #[test]
     fn approx_distance() {
         assert_relative_eq!(COORD1.approx().sq_distance_to(&COORD1).sqrt(), 0.0);
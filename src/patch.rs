@@ -0,0 +1,207 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Overrides of some properties of the objects of a model, without modifying
+//! the input files (rename a line, fix the name of a stop, change a color, ...).
+//!
+//! A patch is usually read from a JSON file with [read_patch] and applied
+//! with [Collections::apply_patch](crate::model::Collections::apply_patch).
+//!
+//! ```json
+//! {
+//!   "lines": {
+//!     "line:1": { "name": "Airport shuttle", "color": "FF0000" }
+//!   },
+//!   "stop_points": {
+//!     "sp:1": { "name": "Main station", "coord": { "lon": 2.37, "lat": 48.84 } }
+//!   }
+//! }
+//! ```
+
+use crate::{
+    model::Collections,
+    objects::{Coord, Rgb},
+    Result,
+};
+use failure::{bail, ResultExt};
+use log::info;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs::File, path::Path};
+use typed_index_collection::CollectionWithId;
+
+/// Overrides of a `Network`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkPatch {
+    /// New name of the network
+    pub name: Option<String>,
+    /// New url of the network
+    pub url: Option<String>,
+}
+
+/// Overrides of a `Line`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinePatch {
+    /// New name of the line
+    pub name: Option<String>,
+    /// New code of the line
+    pub code: Option<String>,
+    /// New color of the line
+    pub color: Option<Rgb>,
+    /// New text color of the line
+    pub text_color: Option<Rgb>,
+}
+
+/// Overrides of a `Route`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutePatch {
+    /// New name of the route
+    pub name: Option<String>,
+}
+
+/// Overrides of a `StopArea` or a `StopPoint`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StopPatch {
+    /// New name of the stop
+    pub name: Option<String>,
+    /// New coordinates of the stop
+    pub coord: Option<Coord>,
+}
+
+/// Overrides of the objects of a model, by object type and by identifier
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPatch {
+    /// Overrides of the networks
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkPatch>,
+    /// Overrides of the lines
+    #[serde(default)]
+    pub lines: BTreeMap<String, LinePatch>,
+    /// Overrides of the routes
+    #[serde(default)]
+    pub routes: BTreeMap<String, RoutePatch>,
+    /// Overrides of the stop areas
+    #[serde(default)]
+    pub stop_areas: BTreeMap<String, StopPatch>,
+    /// Overrides of the stop points
+    #[serde(default)]
+    pub stop_points: BTreeMap<String, StopPatch>,
+}
+
+/// Read a `ModelPatch` from a JSON file.
+pub fn read_patch<P: AsRef<Path>>(path: P) -> Result<ModelPatch> {
+    let path = path.as_ref();
+    info!("Reading patch from {:?}", path);
+    let file = File::open(path).with_context(|_| format!("Error reading {:?}", path))?;
+    let patch =
+        serde_json::from_reader(file).with_context(|_| format!("Error reading {:?}", path))?;
+    Ok(patch)
+}
+
+fn unknown_ids<'a, T, P>(
+    object_type: &'a str,
+    collection: &'a CollectionWithId<T>,
+    patches: &'a BTreeMap<String, P>,
+) -> impl Iterator<Item = String> + 'a {
+    patches
+        .keys()
+        .filter(move |id| !collection.contains_id(id))
+        .map(move |id| format!("{} {:?}", object_type, id))
+}
+
+pub(crate) fn apply_patch(collections: &mut Collections, patch: ModelPatch) -> Result<()> {
+    let unknown_ids: Vec<String> = unknown_ids("network", &collections.networks, &patch.networks)
+        .chain(unknown_ids("line", &collections.lines, &patch.lines))
+        .chain(unknown_ids("route", &collections.routes, &patch.routes))
+        .chain(unknown_ids(
+            "stop area",
+            &collections.stop_areas,
+            &patch.stop_areas,
+        ))
+        .chain(unknown_ids(
+            "stop point",
+            &collections.stop_points,
+            &patch.stop_points,
+        ))
+        .collect();
+    if !unknown_ids.is_empty() {
+        bail!("unknown objects in the patch: {}", unknown_ids.join(", "));
+    }
+
+    for (id, network_patch) in patch.networks {
+        let mut network = collections.networks.get_mut(&id).unwrap();
+        if let Some(name) = network_patch.name {
+            network.name = name;
+        }
+        if let Some(url) = network_patch.url {
+            network.url = Some(url);
+        }
+    }
+    for (id, line_patch) in patch.lines {
+        let mut line = collections.lines.get_mut(&id).unwrap();
+        if let Some(name) = line_patch.name {
+            line.name = name;
+        }
+        if let Some(code) = line_patch.code {
+            line.code = Some(code);
+        }
+        if let Some(color) = line_patch.color {
+            line.color = Some(color);
+        }
+        if let Some(text_color) = line_patch.text_color {
+            line.text_color = Some(text_color);
+        }
+    }
+    for (id, route_patch) in patch.routes {
+        let mut route = collections.routes.get_mut(&id).unwrap();
+        if let Some(name) = route_patch.name {
+            route.name = name;
+        }
+    }
+    for (id, stop_patch) in patch.stop_areas {
+        let mut stop_area = collections.stop_areas.get_mut(&id).unwrap();
+        if let Some(name) = stop_patch.name {
+            stop_area.name = name;
+        }
+        if let Some(coord) = stop_patch.coord {
+            stop_area.coord = coord;
+        }
+    }
+    for (id, stop_patch) in patch.stop_points {
+        let mut stop_point = collections.stop_points.get_mut(&id).unwrap();
+        if let Some(name) = stop_patch.name {
+            stop_point.name = name;
+        }
+        if let Some(coord) = stop_patch.coord {
+            stop_point.coord = coord;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_unknown_field() {
+        let patch: std::result::Result<ModelPatch, _> =
+            serde_json::from_str(r#"{ "lines": { "default_line": { "colour": "FF0000" } } }"#);
+        assert!(patch.is_err());
+    }
+}
@@ -18,9 +18,8 @@ use crate::{
     Result,
 };
 use failure::{bail, format_err, ResultExt};
-use log::info;
-use serde::Deserialize;
-use skip_error::SkipError;
+use log::{debug, info, warn};
+use serde::{de::Visitor, Deserialize};
 use std::path;
 use std::path::{Path, PathBuf};
 use std::{collections::BTreeMap, io::Read};
@@ -94,14 +93,107 @@ pub fn read_config<P: AsRef<path::Path>>(
     Ok((contributor, dataset, feed_infos))
 }
 
-pub(crate) trait FileHandler
+const REQUIRED_CONFIG_FIELDS: &[(&str, &str)] = &[
+    ("contributor", "contributor_id"),
+    ("contributor", "contributor_name"),
+    ("dataset", "dataset_id"),
+];
+
+/// Same as [read_config], but fails with an error listing every missing
+/// required field of the configuration file (`contributor.contributor_id`,
+/// `contributor.contributor_name` and `dataset.dataset_id`), an empty value
+/// being considered as missing.
+pub fn read_config_strict<P: AsRef<path::Path>>(
+    config_path: Option<P>,
+) -> Result<(
+    objects::Contributor,
+    objects::Dataset,
+    BTreeMap<String, String>,
+)> {
+    let config_path = match config_path {
+        Some(config_path) => config_path,
+        None => return read_config(None::<P>),
+    };
+    let path = config_path.as_ref();
+    let json_config_file =
+        File::open(path).with_context(|_| format!("Error reading {:?}", path))?;
+    let config: serde_json::Value = serde_json::from_reader(json_config_file)
+        .with_context(|_| format!("Error reading {:?}", path))?;
+    let missing_fields: Vec<String> = REQUIRED_CONFIG_FIELDS
+        .iter()
+        .filter(|(object, field)| {
+            config
+                .get(object)
+                .and_then(|object| object.get(field))
+                .and_then(serde_json::Value::as_str)
+                .map_or(true, |value| value.trim().is_empty())
+        })
+        .map(|(object, field)| format!("{}.{}", object, field))
+        .collect();
+    if !missing_fields.is_empty() {
+        bail!(
+            "missing required fields in {:?}: {}",
+            path,
+            missing_fields.join(", ")
+        );
+    }
+    read_config(Some(config_path))
+}
+
+/// CSV dialect of the files of a dataset.
+///
+/// NTFS and GTFS formally require comma separated files with a header, which
+/// is the default. Other values make it possible to read the malformed files
+/// found in the wild (tab or semicolon separated files for example).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvConfig {
+    /// Field delimiter, `b','` by default
+    pub delimiter: u8,
+    /// If true (default), the first line of each file contains the names of
+    /// the columns. Otherwise the columns are read in the order of the fields.
+    pub has_headers: bool,
+    /// Quote character, `b'"'` by default
+    pub quote: u8,
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        CsvConfig {
+            delimiter: b',',
+            has_headers: true,
+            quote: b'"',
+        }
+    }
+}
+
+impl CsvConfig {
+    fn reader<R: Read>(&self, reader: R) -> csv::Reader<R> {
+        csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .quote(self.quote)
+            .from_reader(reader)
+    }
+}
+
+/// Give access to the files of a dataset by their names.
+///
+/// It is implemented on `&mut` references of the handler (see `PathFileHandler`
+/// and `ZipHandler`), the readers being able to borrow the handler.
+pub trait FileHandler
 where
     Self: std::marker::Sized,
 {
+    /// Type of the reader of a file
     type Reader: Read;
 
+    /// Return a reader on the file `name` if it exists, and its path.
     fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)>;
 
+    /// Return a reader on the file `name` and its path, or an error if
+    /// the file does not exist.
     fn get_file(self, name: &str) -> Result<(Self::Reader, PathBuf)> {
         let (reader, path) = self.get_file_if_exists(name)?;
         Ok((
@@ -110,17 +202,49 @@ where
         ))
     }
 
+    /// Name of the source of the dataset (directory, archive, ...), used in
+    /// the error messages.
     fn source_name(&self) -> &str;
+
+    /// CSV dialect of the files, the standard one by default.
+    fn csv_config(&self) -> CsvConfig {
+        CsvConfig::default()
+    }
+
+    /// Called with the columns of the file `file_name` which are ignored
+    /// when reading it, does nothing by default.
+    fn report_unknown_columns(self, _file_name: &str, _columns: Vec<String>) {}
 }
 
+/// Columns ignored when reading the files of a dataset, by file name.
+pub type UnknownColumns = BTreeMap<String, Vec<String>>;
+
 /// PathFileHandler is used to read files for a directory
-pub(crate) struct PathFileHandler<P: AsRef<Path>> {
+pub struct PathFileHandler<P: AsRef<Path>> {
     base_path: P,
+    csv_config: CsvConfig,
+    unknown_columns: UnknownColumns,
 }
 
 impl<P: AsRef<Path>> PathFileHandler<P> {
-    pub(crate) fn new(path: P) -> Self {
-        PathFileHandler { base_path: path }
+    /// Build a handler reading the files of the directory `path`.
+    pub fn new(path: P) -> Self {
+        PathFileHandler {
+            base_path: path,
+            csv_config: CsvConfig::default(),
+            unknown_columns: UnknownColumns::new(),
+        }
+    }
+
+    /// Read the files with the given CSV dialect.
+    pub fn with_csv_config(mut self, csv_config: CsvConfig) -> Self {
+        self.csv_config = csv_config;
+        self
+    }
+
+    /// Columns ignored in the files read so far.
+    pub fn unknown_columns(&self) -> &UnknownColumns {
+        &self.unknown_columns
     }
 }
 
@@ -145,6 +269,12 @@ impl<'a, P: AsRef<Path>> FileHandler for &'a mut PathFileHandler<P> {
             )
         })
     }
+    fn csv_config(&self) -> CsvConfig {
+        self.csv_config
+    }
+    fn report_unknown_columns(self, file_name: &str, columns: Vec<String>) {
+        self.unknown_columns.insert(file_name.to_string(), columns);
+    }
 }
 
 /// ZipHandler is a wrapper around a ZipArchive
@@ -153,25 +283,42 @@ impl<'a, P: AsRef<Path>> FileHandler for &'a mut PathFileHandler<P> {
 /// Unlike ZipArchive, it gives access to a file by its name not regarding its path in the ZipArchive
 /// It thus cannot be correct if there are 2 files with the same name in the archive,
 /// but for transport data if will make it possible to handle a zip with a sub directory
-pub(crate) struct ZipHandler<R: Seek + Read> {
+pub struct ZipHandler<R: Seek + Read> {
     archive: zip::ZipArchive<R>,
     archive_path: PathBuf,
     index_by_name: BTreeMap<String, usize>,
+    csv_config: CsvConfig,
+    unknown_columns: UnknownColumns,
 }
 
 impl<R> ZipHandler<R>
 where
     R: Seek + Read,
 {
-    pub(crate) fn new<P: AsRef<Path>>(r: R, path: P) -> Result<Self> {
+    /// Build a handler reading the files of the zip archive `r`, `path`
+    /// being the path of the archive.
+    pub fn new<P: AsRef<Path>>(r: R, path: P) -> Result<Self> {
         let mut archive = zip::ZipArchive::new(r)?;
         Ok(ZipHandler {
             index_by_name: Self::files_by_name(&mut archive),
             archive,
             archive_path: path.as_ref().to_path_buf(),
+            csv_config: CsvConfig::default(),
+            unknown_columns: UnknownColumns::new(),
         })
     }
 
+    /// Read the files with the given CSV dialect.
+    pub fn with_csv_config(mut self, csv_config: CsvConfig) -> Self {
+        self.csv_config = csv_config;
+        self
+    }
+
+    /// Columns ignored in the files read so far.
+    pub fn unknown_columns(&self) -> &UnknownColumns {
+        &self.unknown_columns
+    }
+
     fn files_by_name(archive: &mut zip::ZipArchive<R>) -> BTreeMap<String, usize> {
         (0..archive.len())
             .filter_map(|i| {
@@ -202,10 +349,88 @@ where
             .to_str()
             .unwrap_or_else(|| panic!("the path '{:?}' should be valid UTF-8", self.archive_path))
     }
+    fn csv_config(&self) -> CsvConfig {
+        self.csv_config
+    }
+    fn report_unknown_columns(self, file_name: &str, columns: Vec<String>) {
+        self.unknown_columns.insert(file_name.to_string(), columns);
+    }
+}
+
+// Deserializer only used to retrieve the names of the fields of a struct
+struct FieldNamesDeserializer<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> serde::Deserializer<'de> for FieldNamesDeserializer<'a> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(serde::de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        *self.0 = fields;
+        Err(serde::de::Error::custom("field names retrieved"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+// Names of the columns expected when deserializing a `O`.
+// Empty if `O` is not a plain struct (a struct with a flattened field for example).
+pub(crate) fn expected_columns<O>() -> &'static [&'static str]
+where
+    O: for<'de> serde::Deserialize<'de>,
+{
+    let mut fields: &'static [&'static str] = &[];
+    let _ = O::deserialize(FieldNamesDeserializer(&mut fields));
+    fields
+}
+
+// Unknown columns are ignored during the deserialization, but they are
+// reported to the file handler
+fn unknown_columns<O, R>(reader: &mut csv::Reader<R>, file_name: &str) -> Vec<String>
+where
+    O: for<'de> serde::Deserialize<'de>,
+    R: Read,
+{
+    let expected_columns = expected_columns::<O>();
+    if expected_columns.is_empty() {
+        return vec![];
+    }
+    let unknown_columns: Vec<String> = match reader.headers() {
+        Ok(headers) => headers
+            .iter()
+            .filter(|header| !expected_columns.contains(header))
+            .map(str::to_string)
+            .collect(),
+        Err(_) => vec![],
+    };
+    if !unknown_columns.is_empty() {
+        debug!(
+            "{}: unknown columns {:?} are ignored",
+            file_name, unknown_columns
+        );
+    }
+    unknown_columns
 }
 
 /// Read a vector of objects from a zip in a file_handler
-pub(crate) fn read_objects<H, O>(
+pub fn read_objects<H, O>(
     file_handler: &mut H,
     file_name: &str,
     required_file: bool,
@@ -214,33 +439,42 @@ where
     for<'a> &'a mut H: FileHandler,
     O: for<'de> serde::Deserialize<'de>,
 {
+    let csv_config = (&mut *file_handler).csv_config();
     let (reader, path) = file_handler.get_file_if_exists(file_name)?;
     let file_name = path.file_name();
     let basename = file_name.map_or(path.to_string_lossy(), |b| b.to_string_lossy());
 
-    match (reader, required_file) {
+    let (objects, unknown_columns) = match (reader, required_file) {
         (None, false) => {
             info!("Skipping {}", basename);
-            Ok(vec![])
+            return Ok(vec![]);
         }
         (None, true) => {
             bail!("file {:?} not found", path)
         }
         (Some(reader), _) => {
             info!("Reading {}", basename);
-            let mut rdr = csv::ReaderBuilder::new()
-                .flexible(true)
-                .trim(csv::Trim::All)
-                .from_reader(reader);
-            Ok(rdr
+            let mut rdr = csv_config.reader(reader);
+            let unknown_columns = if csv_config.has_headers {
+                unknown_columns::<O, _>(&mut rdr, &basename)
+            } else {
+                vec![]
+            };
+            let objects = rdr
                 .deserialize()
                 .collect::<Result<_, _>>()
-                .with_context(|_| format!("Error reading {:?}", path))?)
+                .with_context(|_| format!("Error reading {:?}", path))?;
+            (objects, unknown_columns)
         }
+    };
+    if !unknown_columns.is_empty() {
+        (&mut *file_handler).report_unknown_columns(&basename, unknown_columns);
     }
+    Ok(objects)
 }
 
-/// Read a vector of objects from a zip in a file_handler ignoring error
+/// Read a vector of objects from a zip in a file_handler, the invalid lines
+/// being logged, counted and ignored
 pub(crate) fn read_objects_loose<H, O>(
     file_handler: &mut H,
     file_name: &str,
@@ -250,39 +484,53 @@ where
     for<'a> &'a mut H: FileHandler,
     O: for<'de> serde::Deserialize<'de>,
 {
+    let csv_config = (&mut *file_handler).csv_config();
     let (reader, path) = file_handler.get_file_if_exists(file_name)?;
     let file_name = path.file_name();
     let basename = file_name.map_or(path.to_string_lossy(), |b| b.to_string_lossy());
 
-    match (reader, required_file) {
+    let (objects, unknown_columns) = match (reader, required_file) {
         (None, false) => {
             info!("Skipping {}", basename);
-            Ok(vec![])
+            return Ok(vec![]);
         }
         (None, true) => {
             bail!("file {:?} not found", path)
         }
         (Some(reader), _) => {
             info!("Reading {}", basename);
-            let mut rdr = csv::ReaderBuilder::new()
-                .flexible(true)
-                .trim(csv::Trim::All)
-                .from_reader(reader);
+            let mut rdr = csv_config.reader(reader);
+            let unknown_columns = if csv_config.has_headers {
+                unknown_columns::<O, _>(&mut rdr, &basename)
+            } else {
+                vec![]
+            };
+            let mut nb_skipped = 0;
             let objects = rdr
                 .deserialize()
-                .map(|object| object.with_context(|_| format!("Error reading {:?}", path)))
-                .skip_error_and_log(tracing::Level::WARN)
+                .filter_map(|object| match object {
+                    Ok(object) => Some(object),
+                    Err(e) => {
+                        warn!("Error reading {:?}, the line is ignored: {}", path, e);
+                        nb_skipped += 1;
+                        None
+                    }
+                })
                 .collect();
-            Ok(objects)
+            if nb_skipped > 0 {
+                warn!("{} invalid line(s) of {} ignored", nb_skipped, basename);
+            }
+            (objects, unknown_columns)
         }
+    };
+    if !unknown_columns.is_empty() {
+        (&mut *file_handler).report_unknown_columns(&basename, unknown_columns);
     }
+    Ok(objects)
 }
 
 /// Read a CollectionId from a zip in a file_handler
-pub(crate) fn read_collection<H, O>(
-    file_handler: &mut H,
-    file_name: &str,
-) -> Result<CollectionWithId<O>>
+pub fn read_collection<H, O>(file_handler: &mut H, file_name: &str) -> Result<CollectionWithId<O>>
 where
     for<'a> &'a mut H: FileHandler,
     O: for<'de> serde::Deserialize<'de> + Id<O>,
@@ -306,6 +554,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::*;
     use pretty_assertions::assert_eq;
     use std::io::Read;
 
@@ -344,4 +593,69 @@ mod tests {
             assert_eq!("world\n", world_str);
         }
     }
+
+    #[test]
+    fn read_objects_with_unknown_columns() {
+        let lines_content = "line_id,line_name,network_id,commercial_mode_id,vendor_note\n\
+                             line:1,Line 1,network:1,Bus,some note";
+        test_in_tmp_dir(|path| {
+            create_file_with_content(path, "lines.txt", lines_content);
+            let mut file_handler = PathFileHandler::new(path.to_path_buf());
+            let lines: Vec<objects::Line> =
+                read_objects(&mut file_handler, "lines.txt", true).unwrap();
+            assert_eq!(1, lines.len());
+            assert_eq!("line:1", lines[0].id);
+            assert_eq!("Line 1", lines[0].name);
+            assert_eq!("network:1", lines[0].network_id);
+            let unknown_columns = file_handler.unknown_columns();
+            assert_eq!(1, unknown_columns.len());
+            assert_eq!(vec!["vendor_note"], unknown_columns["lines.txt"]);
+        });
+    }
+
+    #[test]
+    fn read_objects_with_semicolon_delimiter() {
+        let lines_content = "line_id;line_name;network_id;commercial_mode_id\n\
+                             line:1;\"Line 1; the first one\";network:1;Bus";
+        test_in_tmp_dir(|path| {
+            create_file_with_content(path, "lines.txt", lines_content);
+            let mut file_handler =
+                PathFileHandler::new(path.to_path_buf()).with_csv_config(CsvConfig {
+                    delimiter: b';',
+                    ..Default::default()
+                });
+            let lines: Vec<objects::Line> =
+                read_objects(&mut file_handler, "lines.txt", true).unwrap();
+            assert_eq!(1, lines.len());
+            assert_eq!("line:1", lines[0].id);
+            assert_eq!("Line 1; the first one", lines[0].name);
+            assert_eq!("Bus", lines[0].commercial_mode_id);
+        });
+    }
+
+    #[test]
+    fn read_config_strict_with_missing_fields() {
+        let config_content = r#"{
+            "contributor": {
+                "contributor_id": "",
+                "contributor_name": "Contributor Name"
+            },
+            "dataset": {}
+        }"#;
+        test_in_tmp_dir(|path| {
+            create_file_with_content(path, "config.json", config_content);
+            let error = read_config_strict(Some(path.join("config.json"))).unwrap_err();
+            assert!(
+                format!("{}", error).ends_with(": contributor.contributor_id, dataset.dataset_id")
+            );
+        });
+    }
+
+    #[test]
+    fn read_config_strict_with_valid_config() {
+        let (contributor, dataset, _) =
+            read_config_strict(Some("./tests/fixtures/gtfs2ntfs/config.json")).unwrap();
+        assert!(!contributor.id.is_empty());
+        assert_eq!(contributor.id, dataset.contributor_id);
+    }
 }
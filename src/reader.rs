@@ -0,0 +1,59 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Building blocks to write custom readers of CSV based datasets.
+//!
+//! A dataset is accessed through a [`FileHandler`], either a directory
+//! ([`PathFileHandler`]), a zip archive ([`ZipHandler`]) or your own
+//! implementation, like the in-memory handler below.
+//!
+//! ```
+//! use std::{collections::HashMap, path::PathBuf};
+//! use transit_model::{
+//!     objects::Network,
+//!     reader::{read_collection, FileHandler},
+//!     Result,
+//! };
+//!
+//! struct InMemoryHandler(HashMap<String, String>);
+//!
+//! impl<'a> FileHandler for &'a mut InMemoryHandler {
+//!     type Reader = &'a [u8];
+//!     fn get_file_if_exists(self, name: &str) -> Result<(Option<Self::Reader>, PathBuf)> {
+//!         let content = self.0.get(name).map(|content| content.as_bytes());
+//!         Ok((content, PathBuf::from(name)))
+//!     }
+//!     fn source_name(&self) -> &str {
+//!         "in-memory"
+//!     }
+//! }
+//!
+//! # fn main() -> Result<()> {
+//! let mut handler = InMemoryHandler(
+//!     vec![(
+//!         "networks.txt".to_string(),
+//!         "network_id,network_name\nN1,My network\n".to_string(),
+//!     )]
+//!     .into_iter()
+//!     .collect(),
+//! );
+//! let networks = read_collection::<_, Network>(&mut handler, "networks.txt")?;
+//! assert_eq!("My network", networks.get("N1").unwrap().name);
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::read_utils::{
+    read_collection, read_objects, CsvConfig, FileHandler, PathFileHandler, ZipHandler,
+};
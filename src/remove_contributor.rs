@@ -0,0 +1,136 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Removal of a contributor and of its data.
+
+use crate::{model::Collections, Result};
+use failure::bail;
+use std::collections::HashSet;
+
+/// What to do with the data of a contributor removed with
+/// `Collections::remove_contributor`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContributorRemoval {
+    /// Remove the datasets of the contributor and their vehicle journeys.
+    DropData,
+    /// Move the datasets of the contributor to the contributor with the
+    /// given identifier.
+    Reassign(String),
+}
+
+/// Number of objects removed by `Collections::remove_contributor`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RemovedObjects {
+    /// Number of contributors removed
+    pub contributors: usize,
+    /// Number of datasets removed
+    pub datasets: usize,
+    /// Number of vehicle journeys removed
+    pub vehicle_journeys: usize,
+    /// Number of routes removed
+    pub routes: usize,
+    /// Number of lines removed
+    pub lines: usize,
+    /// Number of networks removed
+    pub networks: usize,
+    /// Number of stop points removed
+    pub stop_points: usize,
+    /// Number of stop areas removed
+    pub stop_areas: usize,
+}
+
+struct ObjectsCount(RemovedObjects);
+
+impl ObjectsCount {
+    fn new(c: &Collections) -> Self {
+        ObjectsCount(RemovedObjects {
+            contributors: c.contributors.len(),
+            datasets: c.datasets.len(),
+            vehicle_journeys: c.vehicle_journeys.len(),
+            routes: c.routes.len(),
+            lines: c.lines.len(),
+            networks: c.networks.len(),
+            stop_points: c.stop_points.len(),
+            stop_areas: c.stop_areas.len(),
+        })
+    }
+
+    fn removed(&self, after: &ObjectsCount) -> RemovedObjects {
+        let (before, after) = (&self.0, &after.0);
+        RemovedObjects {
+            contributors: before.contributors - after.contributors,
+            datasets: before.datasets - after.datasets,
+            vehicle_journeys: before.vehicle_journeys - after.vehicle_journeys,
+            routes: before.routes - after.routes,
+            lines: before.lines - after.lines,
+            networks: before.networks - after.networks,
+            stop_points: before.stop_points - after.stop_points,
+            stop_areas: before.stop_areas - after.stop_areas,
+        }
+    }
+}
+
+/// Remove a contributor and count the removed objects, see
+/// [Collections::remove_contributor].
+pub(crate) fn remove_contributor(
+    collections: &mut Collections,
+    contributor_id: &str,
+    policy: ContributorRemoval,
+) -> Result<RemovedObjects> {
+    if !collections.contributors.contains_id(contributor_id) {
+        bail!("contributor {} not found", contributor_id);
+    }
+    let before = ObjectsCount::new(collections);
+    match policy {
+        ContributorRemoval::DropData => {
+            let mut removed_datasets = HashSet::new();
+            collections.datasets.retain(|dataset| {
+                if dataset.contributor_id == contributor_id {
+                    removed_datasets.insert(dataset.id.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+            let stop_time_ids = collections.take_stop_time_ids();
+            collections
+                .vehicle_journeys
+                .retain(|vj| !removed_datasets.contains(&vj.dataset_id));
+            collections.restore_stop_time_ids(stop_time_ids);
+        }
+        ContributorRemoval::Reassign(other_id) => {
+            if other_id == contributor_id || !collections.contributors.contains_id(&other_id) {
+                bail!(
+                    "cannot reassign the datasets of contributor {} to contributor {}",
+                    contributor_id,
+                    other_id
+                );
+            }
+            let dataset_idxs: Vec<_> = collections
+                .datasets
+                .iter()
+                .filter(|(_, dataset)| dataset.contributor_id == contributor_id)
+                .map(|(idx, _)| idx)
+                .collect();
+            for dataset_idx in dataset_idxs {
+                collections.datasets.index_mut(dataset_idx).contributor_id = other_id.clone();
+            }
+        }
+    }
+    collections
+        .contributors
+        .retain(|contributor| contributor.id != contributor_id);
+    collections.sanitize()?;
+    Ok(before.removed(&ObjectsCount::new(collections)))
+}
@@ -0,0 +1,84 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Restriction of the data to some stop areas.
+
+use crate::{model::Collections, objects::StopPoint, Result};
+use failure::bail;
+use log::debug;
+use std::collections::HashSet;
+use typed_index_collection::{CollectionWithId, Idx};
+
+/// How `Collections::restrict_to_stop_areas` handles the vehicle journeys
+/// also stopping outside of the kept stop areas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartialJourneys {
+    /// Keep only the stop times in the stop areas, if they are contiguous.
+    /// Otherwise, the whole vehicle journey is kept.
+    Trim,
+    /// Keep the whole vehicle journey.
+    KeepWhole,
+}
+
+/// Keep only the vehicle journeys stopping in the stop areas, see
+/// [Collections::restrict_to_stop_areas].
+pub(crate) fn restrict_to_stop_areas(
+    collections: &mut Collections,
+    stop_area_ids: &[String],
+    partial_journeys: PartialJourneys,
+) -> Result<()> {
+    for stop_area_id in stop_area_ids {
+        if !collections.stop_areas.contains_id(stop_area_id) {
+            bail!("stop area {} not found", stop_area_id);
+        }
+    }
+    let stop_point_idxs: HashSet<Idx<StopPoint>> = collections
+        .stop_points
+        .iter()
+        .filter(|(_, stop_point)| stop_area_ids.contains(&stop_point.stop_area_id))
+        .map(|(idx, _)| idx)
+        .collect();
+    let stop_time_ids = collections.take_stop_time_ids();
+    let mut vehicle_journeys = collections.vehicle_journeys.take();
+    vehicle_journeys.retain(|vj| {
+        vj.stop_times
+            .iter()
+            .any(|stop_time| stop_point_idxs.contains(&stop_time.stop_point_idx))
+    });
+    if partial_journeys == PartialJourneys::Trim {
+        for vj in &mut vehicle_journeys {
+            let is_kept: Vec<bool> = vj
+                .stop_times
+                .iter()
+                .map(|stop_time| stop_point_idxs.contains(&stop_time.stop_point_idx))
+                .collect();
+            // unwraps are safe, the vehicle journey stops at least once
+            // in the stop areas
+            let first = is_kept.iter().position(|kept| *kept).unwrap();
+            let last = is_kept.iter().rposition(|kept| *kept).unwrap();
+            if is_kept[first..=last].iter().all(|kept| *kept) {
+                vj.stop_times.truncate(last + 1);
+                vj.stop_times.drain(..first);
+            } else {
+                debug!(
+                    "Vehicle Journey with ID {} leaves and comes back to the stop areas, it is kept whole",
+                    vj.id
+                );
+            }
+        }
+    }
+    collections.vehicle_journeys = CollectionWithId::new(vehicle_journeys)?;
+    collections.restore_stop_time_ids(stop_time_ids);
+    collections.sanitize()
+}
@@ -0,0 +1,106 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+//! Split of a stop area in several stop areas.
+
+use crate::{
+    model::Collections,
+    objects::{RestrictionType, StopArea},
+    Result,
+};
+use failure::bail;
+use std::collections::HashSet;
+
+/// Split a stop area by groups of stop points, see
+/// [Collections::split_stop_area].
+pub(crate) fn split_stop_area(
+    collections: &mut Collections,
+    stop_area_id: &str,
+    groups: Vec<(String, Vec<String>)>,
+) -> Result<()> {
+    let stop_area = match collections.stop_areas.get(stop_area_id) {
+        Some(stop_area) => stop_area.clone(),
+        None => bail!("stop area {} not found", stop_area_id),
+    };
+    let mut new_ids = HashSet::new();
+    let mut moved_stop_points = HashSet::new();
+    for (new_id, stop_point_ids) in &groups {
+        if collections.stop_areas.contains_id(new_id) || !new_ids.insert(new_id.as_str()) {
+            bail!("stop area {} already exists", new_id);
+        }
+        for stop_point_id in stop_point_ids {
+            match collections.stop_points.get(stop_point_id) {
+                Some(stop_point) if stop_point.stop_area_id == stop_area_id => {}
+                _ => bail!(
+                    "stop point {} doesn't belong to stop area {}",
+                    stop_point_id,
+                    stop_area_id
+                ),
+            }
+            if !moved_stop_points.insert(stop_point_id.as_str()) {
+                bail!("stop point {} is in several groups", stop_point_id);
+            }
+        }
+    }
+
+    for (new_id, stop_point_ids) in groups {
+        for stop_point_id in &stop_point_ids {
+            collections
+                .stop_points
+                .get_mut(stop_point_id)
+                .unwrap()
+                .stop_area_id = new_id.clone();
+        }
+        collections.stop_areas.push(StopArea {
+            id: new_id,
+            ..stop_area.clone()
+        })?;
+    }
+    // A reference to the original stop area can't be dispatched between the
+    // new ones, the stop area is kept as long as something refers to it
+    let is_used = collections
+        .stop_points
+        .values()
+        .any(|stop_point| stop_point.stop_area_id == stop_area_id)
+        || collections
+            .stop_locations
+            .values()
+            .any(|stop_location| stop_location.parent_id.as_deref() == Some(stop_area_id))
+        || collections
+            .routes
+            .values()
+            .any(|route| route.destination_id.as_deref() == Some(stop_area_id))
+        || collections.lines.values().any(|line| {
+            line.forward_direction.as_deref() == Some(stop_area_id)
+                || line.backward_direction.as_deref() == Some(stop_area_id)
+        })
+        || collections
+            .admin_stations
+            .values()
+            .any(|admin_station| admin_station.stop_id == stop_area_id)
+        || collections
+            .ticket_use_restrictions
+            .values()
+            .any(|restriction| {
+                restriction.restriction_type == RestrictionType::OriginDestination
+                    && (restriction.use_origin == stop_area_id
+                        || restriction.use_destination == stop_area_id)
+            });
+    if !is_used {
+        collections
+            .stop_areas
+            .retain(|stop_area| stop_area.id != stop_area_id);
+    }
+    Ok(())
+}
@@ -15,12 +15,12 @@
 //! See function generates_transfers
 
 use crate::{
-    model::Model,
+    model::{Collections, Model},
     objects::{Coord, StopPoint, Transfer},
     Result,
 };
-use log::{info, warn};
-use std::collections::HashMap;
+use log::{debug, info, warn};
+use std::collections::{HashMap, HashSet};
 use typed_index_collection::{Collection, CollectionWithId, Idx};
 
 type TransferMap = HashMap<(Idx<StopPoint>, Idx<StopPoint>), Transfer>;
@@ -47,23 +47,25 @@ fn make_transfers_map(
         .collect()
 }
 
-fn generate_transfers_from_sp(
+fn generate_transfers_from_sp<F>(
     transfers_map: &mut TransferMap,
-    model: &Model,
+    stop_points: &CollectionWithId<StopPoint>,
     max_distance: f64,
     walking_speed: f64,
     waiting_time: u32,
-    need_transfer: Option<NeedTransfer>,
-) {
+    need_transfer: F,
+) where
+    F: Fn(Idx<StopPoint>, Idx<StopPoint>) -> bool,
+{
     info!("Adding missing transfers from stop points.");
     let sq_max_distance = max_distance * max_distance;
-    for (idx1, sp1) in model.stop_points.iter() {
+    for (idx1, sp1) in stop_points.iter() {
         if sp1.coord == Coord::default() {
             warn!("Stop Point {} geolocation is (0, 0), no transfer from this StopPoint will be generated.", sp1.id);
             continue;
         }
         let approx = sp1.coord.approx();
-        for (idx2, sp2) in model.stop_points.iter() {
+        for (idx2, sp2) in stop_points.iter() {
             if sp2.coord == Coord::default() {
                 warn!("Stop Point {} geolocation is (0, 0), no transfer to this StopPoint will be generated.", sp2.id);
                 continue;
@@ -71,10 +73,8 @@ fn generate_transfers_from_sp(
             if transfers_map.contains_key(&(idx1, idx2)) {
                 continue;
             }
-            if let Some(ref f) = need_transfer {
-                if !f(model, idx1, idx2) {
-                    continue;
-                }
+            if !need_transfer(idx1, idx2) {
+                continue;
             }
             let sq_distance = approx.sq_distance_to(&sp2.coord);
             if sq_distance > sq_max_distance {
@@ -132,19 +132,215 @@ pub fn generates_transfers(
     let mut transfers_map = make_transfers_map(model.transfers.clone(), &model.stop_points);
     generate_transfers_from_sp(
         &mut transfers_map,
-        &model,
+        &model.stop_points,
         max_distance,
         walking_speed,
         waiting_time,
-        need_transfer,
+        |idx1, idx2| {
+            need_transfer
+                .as_ref()
+                .map_or(true, |f| f(&model, idx1, idx2))
+        },
     );
 
     let mut new_transfers: Vec<_> = transfers_map.into_iter().map(|(_, v)| v).collect();
-    new_transfers.sort_unstable_by(|t1, t2| {
-        (&t1.from_stop_id, &t1.to_stop_id).cmp(&(&t2.from_stop_id, &t2.to_stop_id))
-    });
+    sort_transfers(&mut new_transfers);
 
     let mut collections = model.into_collections();
     collections.transfers = Collection::new(new_transfers);
     Model::new(collections)
 }
+
+/// Same as [generates_transfers] on `Collections`, which may not be
+/// consistent yet: existing transfers are all kept as they are, including
+/// the ones referencing an unknown stop point, and the generated transfers
+/// are added after them.
+pub(crate) fn generate_missing_transfers(
+    collections: &mut Collections,
+    max_distance: f64,
+    walking_speed: f64,
+    waiting_time: u32,
+) {
+    info!("Generating transfers...");
+    let stop_points = &collections.stop_points;
+    let mut transfers_map: TransferMap = collections
+        .transfers
+        .values()
+        .filter_map(|t| {
+            Some((
+                (
+                    stop_points.get_idx(&t.from_stop_id)?,
+                    stop_points.get_idx(&t.to_stop_id)?,
+                ),
+                t.clone(),
+            ))
+        })
+        .collect();
+    let existing_transfers: HashSet<_> = transfers_map.keys().cloned().collect();
+    generate_transfers_from_sp(
+        &mut transfers_map,
+        stop_points,
+        max_distance,
+        walking_speed,
+        waiting_time,
+        |_, _| true,
+    );
+
+    let mut new_transfers: Vec<_> = transfers_map
+        .into_iter()
+        .filter(|(stop_point_idxs, _)| !existing_transfers.contains(stop_point_idxs))
+        .map(|(_, v)| v)
+        .collect();
+    sort_transfers(&mut new_transfers);
+    for transfer in new_transfers {
+        collections.transfers.push(transfer);
+    }
+}
+
+fn sort_transfers(transfers: &mut Vec<Transfer>) {
+    transfers.sort_unstable_by(|t1, t2| {
+        (&t1.from_stop_id, &t1.to_stop_id).cmp(&(&t2.from_stop_id, &t2.to_stop_id))
+    });
+}
+
+/// Add the mirrored transfer B→A of each transfer A→B which has none, see
+/// [Collections::make_transfers_symmetric].
+pub(crate) fn make_transfers_symmetric(collections: &mut Collections) {
+    let existing_transfers: HashSet<(String, String)> = collections
+        .transfers
+        .values()
+        .map(|t| (t.from_stop_id.clone(), t.to_stop_id.clone()))
+        .collect();
+    let mirrored_transfers: Vec<Transfer> = collections
+        .transfers
+        .values()
+        .filter(|t| !existing_transfers.contains(&(t.to_stop_id.clone(), t.from_stop_id.clone())))
+        .map(|t| Transfer {
+            from_stop_id: t.to_stop_id.clone(),
+            to_stop_id: t.from_stop_id.clone(),
+            min_transfer_time: t.min_transfer_time,
+            real_min_transfer_time: t.real_min_transfer_time,
+            equipment_id: t.equipment_id.clone(),
+        })
+        .collect();
+    debug!("{} mirrored transfers added", mirrored_transfers.len());
+    for transfer in mirrored_transfers {
+        collections.transfers.push(transfer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod make_transfers_symmetric {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn transfer(from: &str, to: &str, min: u32, real_min: u32) -> Transfer {
+            Transfer {
+                from_stop_id: from.into(),
+                to_stop_id: to.into(),
+                min_transfer_time: Some(min),
+                real_min_transfer_time: Some(real_min),
+                equipment_id: None,
+            }
+        }
+
+        #[test]
+        fn add_missing_reverse_transfers() {
+            let mut collections = Collections {
+                transfers: Collection::new(vec![
+                    transfer("sp1", "sp2", 60, 120),
+                    transfer("sp3", "sp4", 60, 60),
+                    transfer("sp4", "sp3", 180, 180),
+                ]),
+                ..Default::default()
+            };
+            collections.make_transfers_symmetric();
+
+            let transfers: Vec<_> = collections
+                .transfers
+                .values()
+                .map(|t| {
+                    (
+                        t.from_stop_id.as_str(),
+                        t.to_stop_id.as_str(),
+                        t.min_transfer_time,
+                        t.real_min_transfer_time,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![
+                    ("sp1", "sp2", Some(60), Some(120)),
+                    ("sp3", "sp4", Some(60), Some(60)),
+                    ("sp4", "sp3", Some(180), Some(180)),
+                    ("sp2", "sp1", Some(60), Some(120)),
+                ],
+                transfers
+            );
+        }
+    }
+
+    mod compute_transfers {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn stop_point(id: &str, lon: f64, lat: f64) -> StopPoint {
+            StopPoint {
+                id: id.into(),
+                coord: Coord { lon, lat },
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn generate_close_transfers() {
+            let mut collections = Collections {
+                stop_points: CollectionWithId::new(vec![
+                    stop_point("sp1", 2.37, 48.84),
+                    // about 111 meters north of sp1
+                    stop_point("sp2", 2.37, 48.841),
+                    // far away
+                    stop_point("sp3", 2.5, 48.9),
+                    // no geolocation
+                    stop_point("sp4", 0.0, 0.0),
+                ])
+                .unwrap(),
+                transfers: Collection::new(vec![Transfer {
+                    from_stop_id: "sp1".into(),
+                    to_stop_id: "sp2".into(),
+                    min_transfer_time: Some(300),
+                    real_min_transfer_time: Some(360),
+                    equipment_id: None,
+                }]),
+                ..Default::default()
+            };
+            collections.compute_transfers(200.0, 1.0);
+
+            let transfers: Vec<_> = collections
+                .transfers
+                .values()
+                .map(|t| {
+                    (
+                        t.from_stop_id.as_str(),
+                        t.to_stop_id.as_str(),
+                        t.min_transfer_time,
+                        t.real_min_transfer_time,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                vec![
+                    ("sp1", "sp2", Some(300), Some(360)),
+                    ("sp1", "sp1", Some(0), Some(0)),
+                    ("sp2", "sp1", Some(111), Some(111)),
+                    ("sp2", "sp2", Some(0), Some(0)),
+                    ("sp3", "sp3", Some(0), Some(0)),
+                ],
+                transfers
+            );
+        }
+    }
+}
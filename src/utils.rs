@@ -352,13 +352,8 @@ where
     }
     info!("Writing {}", file);
     let path = path.join(file);
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|_| format!("Error reading {:?}", path))?;
-    for obj in collection.values() {
-        wtr.serialize(obj)
-            .with_context(|_| format!("Error reading {:?}", path))?;
-    }
-    wtr.flush()
+    let file = fs::File::create(&path).with_context(|_| format!("Error reading {:?}", path))?;
+    write_objects_to_writer(collection.values(), file)
         .with_context(|_| format!("Error reading {:?}", path))?;
 
     Ok(())
@@ -377,18 +372,30 @@ where
     }
     info!("Writing {}", file);
     let path = path.join(file);
-    let mut wtr =
-        csv::Writer::from_path(&path).with_context(|_| format!("Error reading {:?}", path))?;
-    for obj in collection.values() {
-        wtr.serialize(obj)
-            .with_context(|_| format!("Error reading {:?}", path))?;
-    }
-    wtr.flush()
+    let file = fs::File::create(&path).with_context(|_| format!("Error reading {:?}", path))?;
+    write_objects_to_writer(collection.values(), file)
         .with_context(|_| format!("Error reading {:?}", path))?;
 
     Ok(())
 }
 
+/// Serializes the objects in CSV (with a header) into `writer`.
+pub fn write_objects_to_writer<'a, T, W>(
+    objects: impl IntoIterator<Item = &'a T>,
+    writer: W,
+) -> crate::Result<()>
+where
+    T: 'a + serde::Serialize,
+    W: Write,
+{
+    let mut wtr = csv::Writer::from_writer(writer);
+    for obj in objects {
+        wtr.serialize(obj)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -13,11 +13,19 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>
 
 //! Some utilities to set and/or calculate validity periods.
+//!
+//! A `Collections` can hold several datasets (for example after
+//! `Collections::try_merge`), each with its own validity period:
+//! `compute_datasets_validity_periods` computes them from the calendars of
+//! the vehicle journeys of each dataset, `Collections::restrict_period` clamps
+//! each of them to the restriction and `Collections::sanitize` only removes
+//! the datasets without vehicle journey. `compute_dataset_validity_period`
+//! and `set_dataset_validity_period` only change the dataset they are given.
 use crate::{
-    objects::{Calendar, Dataset, ValidityPeriod},
+    objects::{Calendar, Dataset, Date, ValidityPeriod, VehicleJourney},
     Result,
 };
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use typed_index_collection::CollectionWithId;
 
 fn get_validity_period(calendars: &CollectionWithId<Calendar>) -> Option<ValidityPeriod> {
@@ -50,7 +58,38 @@ pub fn compute_dataset_validity_period(
     Ok(())
 }
 
-/// Set the validity period of a dataset.
+/// Define the Validity Period of each dataset from the services of its own
+/// vehicle journeys.
+///
+/// Datasets without any vehicle journey (or whose vehicle journeys have no
+/// active date) keep their current validity period.
+pub fn compute_datasets_validity_periods(
+    datasets: &mut CollectionWithId<Dataset>,
+    vehicle_journeys: &CollectionWithId<VehicleJourney>,
+    calendars: &CollectionWithId<Calendar>,
+) {
+    let mut dates_by_dataset: HashMap<&str, BTreeSet<Date>> = HashMap::new();
+    for vj in vehicle_journeys.values() {
+        if let Some(calendar) = calendars.get(&vj.service_id) {
+            dates_by_dataset
+                .entry(vj.dataset_id.as_str())
+                .or_insert_with(BTreeSet::new)
+                .extend(calendar.dates.iter().cloned());
+        }
+    }
+    for (dataset_id, dates) in dates_by_dataset {
+        if let (Some(start_date), Some(end_date)) = (dates.iter().next(), dates.iter().next_back())
+        {
+            if let Some(mut dataset) = datasets.get_mut(dataset_id) {
+                dataset.start_date = *start_date;
+                dataset.end_date = *end_date;
+            }
+        }
+    }
+}
+
+/// Set the validity period of a dataset, extending its current period to
+/// include `service_validity_period`. The other datasets are not changed.
 ///
 /// Take also a look at the `compute_dataset_validity_period` function that
 /// can automatically calculate the validity period from the Services dates.
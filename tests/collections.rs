@@ -0,0 +1,1597 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use chrono::NaiveDate;
+use geo::{line_string, Geometry as GeoGeometry, MultiLineString, Point as GeoPoint};
+use pretty_assertions::assert_eq;
+use relational_types::IdxSet;
+use std::collections::BTreeSet;
+use transit_model::model::{
+    Collections, ContributorRemoval, InconsistencyError, Model, OvernightTimes, PartialJourneys,
+    RemovedObjects, SanitizeConfiguration, CALENDAR_SHIFT_COMMENT_ID,
+};
+use transit_model::objects::*;
+use transit_model::patch::ModelPatch;
+use transit_model::test_utils::*;
+use transit_model_builder::ModelBuilder;
+use typed_index_collection::{Collection, CollectionWithId, Id};
+
+#[test]
+fn sanitize_keeps_stop_area_equipment() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections.equipments = CollectionWithId::new(vec![
+        Equipment {
+            id: "eq:elevator".into(),
+            elevator: Availability::Available,
+            ..Default::default()
+        },
+        Equipment {
+            id: "eq:unused".into(),
+            ..Default::default()
+        },
+    ])
+    .unwrap();
+    collections.stop_areas.get_mut("sa:A").unwrap().equipment_id = Some("eq:elevator".into());
+
+    let model = Model::new(collections).unwrap();
+    assert_eq!(
+        vec!["eq:elevator"],
+        model
+            .equipments
+            .values()
+            .map(|e| e.id.as_str())
+            .collect::<Vec<_>>()
+    );
+    let sa_idx = model.stop_areas.get_idx("sa:A").unwrap();
+    let equipments: IdxSet<Equipment> = model.get_corresponding_from_idx(sa_idx);
+    assert_eq!(1, equipments.len());
+    let sa_idx = model.stop_areas.get_idx("sa:B").unwrap();
+    let equipments: IdxSet<Equipment> = model.get_corresponding_from_idx(sa_idx);
+    assert!(equipments.is_empty());
+}
+
+// `vj1` and `vj2` share a pattern, `vj3` skips `B` and `vj4` forbids the
+// pickup at `B`
+fn journeys_with_patterns() -> Collections {
+    ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00")
+                .st("C", "12:00:00", "12:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.st("A", "14:00:00", "14:01:00")
+                .st("B", "15:00:00", "15:01:00")
+                .st("C", "16:00:00", "16:01:00");
+        })
+        .vj("vj3", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("C", "12:00:00", "12:01:00");
+        })
+        .vj("vj4", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st_mut("B", "11:00:00", "11:01:00", |st| st.pickup_type = 1)
+                .st("C", "12:00:00", "12:01:00");
+        })
+        .build()
+        .into_collections()
+}
+
+#[test]
+fn journey_patterns_group_by_stop_points_and_pickup_drop_off() {
+    let collections = journeys_with_patterns();
+    let journey_patterns = collections.journey_patterns();
+    let patterns: Vec<Vec<&str>> = journey_patterns
+        .values()
+        .map(|vj_idxs| {
+            vj_idxs
+                .iter()
+                .map(|idx| collections.vehicle_journeys[*idx].id.as_str())
+                .collect()
+        })
+        .collect();
+    assert_eq!(vec![vec!["vj1", "vj2"], vec!["vj4"], vec!["vj3"]], patterns);
+    // drop off is forbidden at the first stop and pickup at the last one
+    let first_key = journey_patterns.keys().next().unwrap();
+    assert_eq!(
+        &vec![
+            ("A".to_string(), 0, 1),
+            ("B".to_string(), 0, 0),
+            ("C".to_string(), 1, 0)
+        ],
+        first_key
+    );
+}
+
+#[test]
+fn enhance_journey_pattern_ids_keeps_existing_ones() {
+    let mut collections = journeys_with_patterns();
+    collections
+        .vehicle_journeys
+        .get_mut("vj2")
+        .unwrap()
+        .journey_pattern_id = Some("existing".to_string());
+    collections.enhance_journey_pattern_ids();
+    let journey_pattern_id = |vj_id: &str| {
+        collections
+            .vehicle_journeys
+            .get(vj_id)
+            .unwrap()
+            .journey_pattern_id
+            .clone()
+    };
+    assert_eq!(
+        Some("journey_pattern:0".to_string()),
+        journey_pattern_id("vj1")
+    );
+    assert_eq!(Some("existing".to_string()), journey_pattern_id("vj2"));
+    assert_eq!(
+        Some("journey_pattern:2".to_string()),
+        journey_pattern_id("vj3")
+    );
+    assert_eq!(
+        Some("journey_pattern:1".to_string()),
+        journey_pattern_id("vj4")
+    );
+}
+
+// contributor `c1` provides `vj1` (on stops A and B) and contributor `c2`
+// provides `vj2` (on stops B and C)
+fn two_contributors() -> Collections {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.dataset("d1")
+                .route("r1")
+                .st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.dataset("d2")
+                .route("r2")
+                .st("B", "10:00:00", "10:01:00")
+                .st("C", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections.contributors = CollectionWithId::new(vec![
+        Contributor {
+            id: "c1".into(),
+            ..Default::default()
+        },
+        Contributor {
+            id: "c2".into(),
+            ..Default::default()
+        },
+    ])
+    .unwrap();
+    collections.datasets.get_mut("d1").unwrap().contributor_id = "c1".into();
+    collections.datasets.get_mut("d2").unwrap().contributor_id = "c2".into();
+    collections
+}
+
+#[test]
+fn remove_contributor_drops_its_data() {
+    let mut collections = two_contributors();
+    let removed = collections
+        .remove_contributor("c1", ContributorRemoval::DropData)
+        .unwrap();
+    assert_eq!(
+        RemovedObjects {
+            contributors: 1,
+            datasets: 1,
+            vehicle_journeys: 1,
+            routes: 1,
+            stop_points: 1,
+            stop_areas: 1,
+            ..Default::default()
+        },
+        removed
+    );
+    assert!(collections.contributors.get("c1").is_none());
+    assert!(collections.datasets.get("d1").is_none());
+    assert!(collections.vehicle_journeys.get("vj1").is_none());
+    assert!(collections.routes.get("r1").is_none());
+    assert!(collections.stop_points.get("A").is_none());
+    assert!(collections.stop_points.get("B").is_some());
+}
+
+#[test]
+fn remove_contributor_reassigns_its_datasets() {
+    let mut collections = two_contributors();
+    let removed = collections
+        .remove_contributor("c1", ContributorRemoval::Reassign("c2".into()))
+        .unwrap();
+    assert_eq!(
+        RemovedObjects {
+            contributors: 1,
+            ..Default::default()
+        },
+        removed
+    );
+    assert_eq!("c2", collections.datasets.get("d1").unwrap().contributor_id);
+    assert_eq!(2, collections.vehicle_journeys.len());
+}
+
+#[test]
+fn remove_contributor_cannot_reassign_to_unknown_contributor() {
+    let mut collections = two_contributors();
+    assert!(collections
+        .remove_contributor("c1", ContributorRemoval::Reassign("unknown".into()))
+        .is_err());
+}
+
+#[test]
+fn sanitize_prunes_stop_time_ids_of_unknown_stop_times() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj0", |vj| {
+            vj.st("A", "09:00:00", "09:01:00")
+                .st("B", "10:00:00", "10:01:00");
+        })
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    let vj0_idx = collections.vehicle_journeys.get_idx("vj0").unwrap();
+    let vj1_idx = collections.vehicle_journeys.get_idx("vj1").unwrap();
+    // `vj0` is removed by sanitize, and only the first stop time of `vj1` exists
+    collections
+        .vehicle_journeys
+        .index_mut(vj0_idx)
+        .stop_times
+        .clear();
+    for (vj_idx, sequence) in &[(vj0_idx, 0), (vj1_idx, 0), (vj1_idx, 5)] {
+        collections.stop_time_ids.insert(
+            (*vj_idx, *sequence),
+            format!("{}-{}", collections.vehicle_journeys[*vj_idx].id, sequence),
+        );
+    }
+    collections.sanitize().unwrap();
+
+    // the identifier follows the new index of `vj1`
+    let vj1_idx = collections.vehicle_journeys.get_idx("vj1").unwrap();
+    let stop_time_ids: Vec<_> = collections.stop_time_ids.iter().collect();
+    assert_eq!(vec![(&(vj1_idx, 0), &"vj1-0".to_string())], stop_time_ids);
+}
+
+#[test]
+fn sanitize_removes_pathways_with_an_unknown_endpoint() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    let pathway = |id: &str, from_stop_id: &str, to_stop_id: &str| Pathway {
+        id: id.into(),
+        from_stop_id: from_stop_id.into(),
+        from_stop_type: StopType::Point,
+        to_stop_id: to_stop_id.into(),
+        to_stop_type: StopType::Point,
+        ..Default::default()
+    };
+    collections.stop_locations = CollectionWithId::from(StopLocation {
+        id: "node".into(),
+        stop_type: StopType::GenericNode,
+        ..Default::default()
+    });
+    // there is no `entrance` stop location
+    collections.pathways = CollectionWithId::new(vec![
+        pathway("pw:AB", "A", "B"),
+        pathway("pw:node", "node", "A"),
+        pathway("pw:entrance", "entrance", "A"),
+        pathway("pw:removed", "A", "removed"),
+    ])
+    .unwrap();
+    collections.sanitize().unwrap();
+
+    let pathways: Vec<_> = collections
+        .pathways
+        .values()
+        .map(|pw| pw.id.as_str())
+        .collect();
+    assert_eq!(vec!["pw:AB", "pw:node"], pathways);
+}
+
+#[test]
+fn sort_by_id_keeps_the_stop_times_of_vehicle_journeys() {
+    // inserted in reverse order, `vj2` stopping at `C` before `A`
+    let mut collections = ModelBuilder::default()
+        .vj("vj2", |vj| {
+            vj.st("C", "10:00:00", "10:01:00")
+                .st("A", "11:00:00", "11:01:00");
+        })
+        .vj("vj1", |vj| {
+            vj.st("B", "10:00:00", "10:01:00")
+                .st("A", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections.sort_by_id();
+
+    let stop_point_ids: Vec<_> = collections
+        .stop_points
+        .values()
+        .map(|sp| sp.id.as_str())
+        .collect();
+    assert_eq!(vec!["A", "B", "C"], stop_point_ids);
+    let vj_ids: Vec<_> = collections
+        .vehicle_journeys
+        .values()
+        .map(|vj| vj.id.as_str())
+        .collect();
+    assert_eq!(vec!["vj1", "vj2"], vj_ids);
+    let stops_of = |vj_id: &str| -> Vec<String> {
+        collections
+            .vehicle_journeys
+            .get(vj_id)
+            .unwrap()
+            .stop_times
+            .iter()
+            .map(|st| collections.stop_points[st.stop_point_idx].id.clone())
+            .collect()
+    };
+    assert_eq!(vec!["C", "A"], stops_of("vj2"));
+    assert_eq!(vec!["B", "A"], stops_of("vj1"));
+}
+
+#[test]
+fn sanitize_removes_calendars_emptied_by_restrict_period() {
+    // `c2` has no date left in January 2020
+    let mut collections = ModelBuilder::default()
+        .calendar("c1", &["2020-01-01", "2020-01-02"])
+        .calendar("c2", &["2020-06-01"])
+        .vj("vj1", |vj| {
+            vj.calendar("c1")
+                .st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.calendar("c2")
+                .st("C", "10:00:00", "10:01:00")
+                .st("D", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections
+        .restrict_period(
+            NaiveDate::from_ymd(2020, 1, 1),
+            NaiveDate::from_ymd(2020, 1, 31),
+        )
+        .unwrap();
+    collections.sanitize().unwrap();
+
+    assert_eq!(
+        vec!["c1"],
+        collections
+            .calendars
+            .values()
+            .map(|c| c.id.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["vj1"],
+        collections
+            .vehicle_journeys
+            .values()
+            .map(|vj| vj.id.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec!["A", "B"],
+        collections
+            .stop_points
+            .values()
+            .map(|sp| sp.id.as_str())
+            .collect::<Vec<_>>()
+    );
+}
+
+// `default_line` is the line of `vj1`, stopping at `A` and `B`
+fn one_line() -> Collections {
+    ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections()
+}
+
+#[test]
+fn apply_patch_overrides_line_name_and_color() {
+    let mut collections = one_line();
+    let patch: ModelPatch = serde_json::from_str(
+        r#"{
+            "lines": {
+                "default_line": { "name": "Airport shuttle", "color": "FF0000" }
+            },
+            "stop_points": {
+                "A": { "name": "Main station" }
+            }
+        }"#,
+    )
+    .unwrap();
+    collections.apply_patch(patch).unwrap();
+
+    let line = collections.lines.get("default_line").unwrap();
+    assert_eq!("Airport shuttle", line.name);
+    assert_eq!(
+        Some(Rgb {
+            red: 255,
+            green: 0,
+            blue: 0
+        }),
+        line.color
+    );
+    assert_eq!(None, line.text_color);
+    assert_eq!(
+        "Main station",
+        collections.stop_points.get("A").unwrap().name
+    );
+}
+
+#[test]
+fn apply_patch_rejects_unknown_objects() {
+    let mut collections = one_line();
+    let patch: ModelPatch = serde_json::from_str(
+        r#"{
+            "lines": {
+                "default_line": { "name": "Airport shuttle" },
+                "unknown_line": { "name": "Unknown" }
+            },
+            "stop_areas": {
+                "unknown_stop_area": { "name": "Unknown" }
+            }
+        }"#,
+    )
+    .unwrap();
+    let error = collections.apply_patch(patch).unwrap_err();
+    assert_eq!(
+        "unknown objects in the patch: line \"unknown_line\", stop area \"unknown_stop_area\"",
+        format!("{}", error)
+    );
+    // nothing has been applied
+    assert_ne!(
+        "Airport shuttle",
+        collections.lines.get("default_line").unwrap().name
+    );
+}
+
+#[test]
+fn is_consistent_without_broken_reference() {
+    let model = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build();
+    assert_eq!(Vec::<InconsistencyError>::new(), model.is_consistent());
+}
+
+#[test]
+fn is_consistent_reports_broken_references() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections
+        .vehicle_journeys
+        .get_mut("vj1")
+        .unwrap()
+        .route_id = "unknown_route".into();
+    collections.stop_points.get_mut("A").unwrap().stop_area_id = "unknown_stop_area".into();
+    collections.transfers.push(Transfer {
+        from_stop_id: "A".into(),
+        to_stop_id: "unknown_stop_point".into(),
+        min_transfer_time: None,
+        real_min_transfer_time: None,
+        equipment_id: None,
+    });
+
+    let errors: Vec<String> = collections
+        .is_consistent()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        vec![
+            r#"VehicleJourney "vj1": route_id "unknown_route" not found"#,
+            r#"StopPoint "A": stop_area_id "unknown_stop_area" not found"#,
+            r#"Transfer "A-unknown_stop_point": to_stop_id "unknown_stop_point" not found"#,
+        ],
+        errors
+    );
+    // nothing has been modified
+    assert_eq!(
+        "unknown_route",
+        collections.vehicle_journeys.get("vj1").unwrap().route_id
+    );
+}
+
+#[test]
+fn report_broken_fares_and_stop_time_references() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.st("B", "12:00:00", "12:01:00")
+                .st("C", "13:00:00", "13:01:00");
+        })
+        .build()
+        .into_collections();
+    collections
+        .stop_time_headsigns
+        .insert(("vj1".into(), 42), "headsign".into());
+    collections
+        .stop_time_headsigns
+        .insert(("unknown_vj".into(), 0), "headsign".into());
+    // `vj2` is removed without updating the stop time identifiers keyed by its index
+    let vj2_idx = collections.vehicle_journeys.get_idx("vj2").unwrap();
+    collections
+        .stop_time_ids
+        .insert((vj2_idx, 3), "stop_time_id".into());
+    collections.vehicle_journeys.retain(|vj| vj.id != "vj2");
+    collections.ticket_uses = CollectionWithId::new(vec![TicketUse {
+        id: "ticket_use".into(),
+        ticket_id: "unknown_ticket".into(),
+        max_transfers: None,
+        boarding_time_limit: None,
+        alighting_time_limit: None,
+    }])
+    .unwrap();
+    collections.ticket_use_perimeters.push(TicketUsePerimeter {
+        ticket_use_id: "ticket_use".into(),
+        object_type: ObjectType::Line,
+        object_id: "unknown_line".into(),
+        perimeter_action: PerimeterAction::Included,
+    });
+    collections
+        .grid_rel_calendar_line
+        .push(GridRelCalendarLine {
+            grid_calendar_id: "unknown_grid_calendar".into(),
+            line_id: String::new(),
+            line_external_code: Some("external_code".into()),
+        });
+
+    let errors: Vec<String> = collections
+        .is_consistent()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        vec![
+            r#"StopTime "3": vehicle_journey_idx "3" not found"#,
+            r#"StopTime "unknown_vj-0": vehicle_journey_id "unknown_vj" not found"#,
+            r#"StopTime "vj1-42": sequence "42" not found"#,
+            r#"TicketUse "ticket_use": ticket_id "unknown_ticket" not found"#,
+            r#"TicketUsePerimeter "ticket_use": object_id "unknown_line" not found"#,
+            r#"GridRelCalendarLine "unknown_grid_calendar": grid_calendar_id "unknown_grid_calendar" not found"#,
+        ],
+        errors
+    );
+}
+
+// `vj1` (A to B) and `vj2` (B to C) both run on `default_line`
+fn line_with_two_journeys() -> Collections {
+    ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.st("B", "12:00:00", "12:01:00")
+                .st("C", "13:00:00", "13:01:00");
+        })
+        .build()
+        .into_collections()
+}
+
+#[test]
+fn line_geometry_merges_geometries_without_duplicated_segments() {
+    let mut collections = line_with_two_journeys();
+    // `g1` and `g2` share the segment (1, 0)-(2, 0), `g3` is not a linestring
+    collections.geometries = CollectionWithId::new(vec![
+        Geometry {
+            id: "g1".to_string(),
+            geometry: line_string![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 2.0, y: 0.0)].into(),
+        },
+        Geometry {
+            id: "g2".to_string(),
+            geometry: line_string![(x: 2.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0)].into(),
+        },
+        Geometry {
+            id: "g3".to_string(),
+            geometry: GeoPoint::new(0.0, 0.0).into(),
+        },
+    ])
+    .unwrap();
+    collections
+        .vehicle_journeys
+        .get_mut("vj1")
+        .unwrap()
+        .geometry_id = Some("g1".to_string());
+    collections
+        .vehicle_journeys
+        .get_mut("vj2")
+        .unwrap()
+        .geometry_id = Some("g2".to_string());
+    collections
+        .routes
+        .get_mut("default_route")
+        .unwrap()
+        .geometry_id = Some("g3".to_string());
+    let model = Model::new(collections).unwrap();
+
+    let geometry = model
+        .line_geometry(model.lines.get_idx("default_line").unwrap())
+        .unwrap();
+    assert_eq!("default_line", geometry.id);
+    assert_eq!(
+        GeoGeometry::MultiLineString(MultiLineString(vec![
+            line_string![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 2.0, y: 0.0)],
+            line_string![(x: 1.0, y: 0.0), (x: 1.0, y: 1.0)],
+        ])),
+        geometry.geometry
+    );
+}
+
+#[test]
+fn line_geometry_without_geometry() {
+    let model = Model::new(line_with_two_journeys()).unwrap();
+    assert_eq!(
+        None,
+        model.line_geometry(model.lines.get_idx("default_line").unwrap())
+    );
+}
+
+// the routes `r1` and `r2` of the lines `l1` and `l2` represent the same
+// service, `l1` having an object code
+fn lines_of_the_same_service() -> Collections {
+    let mut collections = ModelBuilder::default()
+        .route("r1", |route| route.line_id = "l1".to_string())
+        .route("r2", |route| route.line_id = "l2".to_string())
+        .vj("vj1", |vj| {
+            vj.route("r1")
+                .st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.route("r2")
+                .st("B", "12:00:00", "12:01:00")
+                .st("C", "13:00:00", "13:01:00");
+        })
+        .build()
+        .into_collections();
+    collections
+        .lines
+        .get_mut("l1")
+        .unwrap()
+        .codes
+        .insert(("source".to_string(), "L1".to_string()));
+    collections
+}
+
+#[test]
+fn merge_lines_moves_routes_into_target_line() {
+    let mut collections = lines_of_the_same_service();
+    collections.merge_lines("l1", "l2").unwrap();
+
+    assert!(!collections.lines.contains_id("l1"));
+    assert_eq!("l2", collections.routes.get("r1").unwrap().line_id);
+    assert_eq!("l2", collections.routes.get("r2").unwrap().line_id);
+    assert!(collections
+        .lines
+        .get("l2")
+        .unwrap()
+        .codes
+        .contains(&("source".to_string(), "L1".to_string())));
+    assert!(Model::new(collections).is_ok());
+}
+
+#[test]
+fn merge_lines_into_unknown_line() {
+    let mut collections = lines_of_the_same_service();
+    let error = collections.merge_lines("l1", "unknown").unwrap_err();
+    assert_eq!("line unknown not found", format!("{}", error));
+    assert!(collections.lines.contains_id("l1"));
+}
+
+#[test]
+fn merge_lines_from_line_without_route() {
+    let mut collections = lines_of_the_same_service();
+    collections
+        .lines
+        .push(Line {
+            id: "l3".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    let error = collections.merge_lines("l3", "l2").unwrap_err();
+    assert_eq!(
+        "line l3 has no route to merge into line l2",
+        format!("{}", error)
+    );
+}
+
+#[test]
+fn sanitize_with_calendar_coverage_removes_vehicle_journeys_without_active_date() {
+    // after the restriction to January, `c2` has no date left and `vj2` is
+    // the only vehicle journey of the line `l2`
+    let mut collections = ModelBuilder::default()
+        .calendar("c1", &["2020-01-01"])
+        .calendar("c2", &["2020-06-01"])
+        .route("r2", |route| route.line_id = "l2".to_string())
+        .vj("vj1", |vj| {
+            vj.calendar("c1")
+                .st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.calendar("c2")
+                .route("r2")
+                .st("C", "10:00:00", "10:01:00")
+                .st("D", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections
+        .restrict_period(
+            NaiveDate::from_ymd(2020, 1, 1),
+            NaiveDate::from_ymd(2020, 1, 31),
+        )
+        .unwrap();
+
+    let configuration = SanitizeConfiguration {
+        validate_calendar_coverage: true,
+    };
+    let removed = collections
+        .sanitize_with_configuration(&configuration)
+        .unwrap();
+    assert_eq!(vec!["vj2".to_string()], removed);
+    assert!(collections.vehicle_journeys.contains_id("vj1"));
+    assert!(!collections.vehicle_journeys.contains_id("vj2"));
+    assert!(!collections.routes.contains_id("r2"));
+    assert!(!collections.lines.contains_id("l2"));
+    assert!(!collections.stop_points.contains_id("C"));
+}
+
+#[test]
+fn frequency_corresponds_to_the_stop_points_of_its_vehicle_journey() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.st("C", "10:00:00", "10:01:00")
+                .st("D", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    // only `vj1` is repeated
+    collections.frequencies = Collection::new(vec![Frequency {
+        vehicle_journey_id: "vj1".to_string(),
+        start_time: Time::new(10, 0, 0),
+        end_time: Time::new(12, 0, 0),
+        headway_secs: 600,
+    }]);
+    let model = Model::new(collections).unwrap();
+
+    let (frequency_idx, _) = model.frequencies.iter().next().unwrap();
+    let stop_point_idxs: IdxSet<StopPoint> = model.get_corresponding_from_idx(frequency_idx);
+    let mut stop_point_ids: Vec<&str> = stop_point_idxs
+        .into_iter()
+        .map(|idx| model.stop_points[idx].id.as_str())
+        .collect();
+    stop_point_ids.sort_unstable();
+    assert_eq!(vec!["A", "B"], stop_point_ids);
+}
+
+// the stop points A, B and C have been wrongly merged in the stop area `sa:A`
+fn wrongly_merged_stop_area() -> Collections {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00")
+                .st("C", "12:00:00", "12:01:00");
+        })
+        .build()
+        .into_collections();
+    for stop_point_id in &["A", "B", "C"] {
+        collections
+            .stop_points
+            .get_mut(stop_point_id)
+            .unwrap()
+            .stop_area_id = "sa:A".to_string();
+    }
+    collections
+        .stop_areas
+        .retain(|stop_area| stop_area.id == "sa:A");
+    collections
+        .stop_areas
+        .get_mut("sa:A")
+        .unwrap()
+        .codes
+        .insert(("source".to_string(), "A".to_string()));
+    collections
+}
+
+#[test]
+fn split_stop_area_in_two_stop_areas() {
+    let mut collections = wrongly_merged_stop_area();
+    collections
+        .split_stop_area(
+            "sa:A",
+            vec![
+                ("sa:1".to_string(), vec!["A".to_string(), "B".to_string()]),
+                ("sa:2".to_string(), vec!["C".to_string()]),
+            ],
+        )
+        .unwrap();
+
+    let stop_area_ids: Vec<&str> = collections
+        .stop_areas
+        .values()
+        .map(|stop_area| stop_area.id.as_str())
+        .collect();
+    assert_eq!(vec!["sa:1", "sa:2"], stop_area_ids);
+    let stop_area_of = |id: &str| {
+        collections
+            .stop_points
+            .get(id)
+            .unwrap()
+            .stop_area_id
+            .clone()
+    };
+    assert_eq!("sa:1", stop_area_of("A"));
+    assert_eq!("sa:1", stop_area_of("B"));
+    assert_eq!("sa:2", stop_area_of("C"));
+    for stop_area in collections.stop_areas.values() {
+        assert!(stop_area
+            .codes
+            .contains(&("source".to_string(), "A".to_string())));
+    }
+    assert!(Model::new(collections).is_ok());
+}
+
+#[test]
+fn split_stop_area_keeps_the_original_stop_area_if_not_empty() {
+    let mut collections = wrongly_merged_stop_area();
+    collections
+        .split_stop_area("sa:A", vec![("sa:C".to_string(), vec!["C".to_string()])])
+        .unwrap();
+    assert!(collections.stop_areas.contains_id("sa:A"));
+    assert_eq!(
+        "sa:A",
+        collections.stop_points.get("A").unwrap().stop_area_id
+    );
+    assert_eq!(
+        "sa:C",
+        collections.stop_points.get("C").unwrap().stop_area_id
+    );
+}
+
+// each function makes an object other than a stop point refer to `sa:A`
+fn route_to_stop_area(collections: &mut Collections) {
+    let (route_idx, _) = collections.routes.iter().next().unwrap();
+    collections.routes.index_mut(route_idx).destination_id = Some("sa:A".to_string());
+}
+
+fn line_to_stop_area(collections: &mut Collections) {
+    let (line_idx, _) = collections.lines.iter().next().unwrap();
+    collections.lines.index_mut(line_idx).backward_direction = Some("sa:A".to_string());
+}
+
+fn admin_station_of_stop_area(collections: &mut Collections) {
+    collections.admin_stations.push(AdminStation {
+        admin_id: "admin:1".to_string(),
+        admin_name: "My city".to_string(),
+        stop_id: "sa:A".to_string(),
+    });
+}
+
+fn ticket_use_restriction_from_stop_area(collections: &mut Collections) {
+    collections
+        .ticket_use_restrictions
+        .push(TicketUseRestriction {
+            ticket_use_id: "ticket_use:1".to_string(),
+            restriction_type: RestrictionType::OriginDestination,
+            use_origin: "sa:A".to_string(),
+            use_destination: "sa:B".to_string(),
+        });
+}
+
+#[test]
+fn split_stop_area_keeps_the_original_stop_area_if_referenced() {
+    let add_references: [fn(&mut Collections); 4] = [
+        route_to_stop_area,
+        line_to_stop_area,
+        admin_station_of_stop_area,
+        ticket_use_restriction_from_stop_area,
+    ];
+    for add_reference in &add_references {
+        let mut collections = wrongly_merged_stop_area();
+        add_reference(&mut collections);
+        collections
+            .split_stop_area(
+                "sa:A",
+                vec![
+                    ("sa:1".to_string(), vec!["A".to_string(), "B".to_string()]),
+                    ("sa:2".to_string(), vec!["C".to_string()]),
+                ],
+            )
+            .unwrap();
+        assert!(collections.stop_areas.contains_id("sa:A"));
+        assert!(collections.stop_areas.contains_id("sa:2"));
+    }
+}
+
+#[test]
+fn split_stop_area_with_a_stop_point_of_another_stop_area() {
+    let mut collections = wrongly_merged_stop_area();
+    collections.stop_points.get_mut("C").unwrap().stop_area_id = "sa:C".to_string();
+    let error = collections
+        .split_stop_area("sa:A", vec![("sa:1".to_string(), vec!["C".to_string()])])
+        .unwrap_err();
+    assert_eq!(
+        "stop point C doesn't belong to stop area sa:A",
+        format!("{}", error)
+    );
+    assert!(!collections.stop_areas.contains_id("sa:1"));
+}
+
+// only the stop point `A` is geolocated and its stop area is named
+fn geolocated_stop_point() -> Model {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    collections.stop_points.get_mut("A").unwrap().coord = Coord {
+        lon: 2.37,
+        lat: 48.84,
+    };
+    collections.stop_areas.get_mut("sa:A").unwrap().name = "Area A".to_string();
+    Model::new(collections).unwrap()
+}
+
+#[test]
+fn stop_points_geojson() {
+    let geojson = geolocated_stop_point().stop_points_geojson();
+    assert_eq!("FeatureCollection", geojson["type"]);
+    assert_eq!(2, geojson["features"].as_array().unwrap().len());
+    assert_eq!(
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Point",
+                "coordinates": [2.37, 48.84],
+            },
+            "properties": {
+                "id": "A",
+                "name": "A",
+                "stop_area_id": "sa:A",
+            },
+        }),
+        geojson["features"][0]
+    );
+}
+
+#[test]
+fn stop_areas_geojson() {
+    let geojson = geolocated_stop_point().stop_areas_geojson();
+    assert_eq!("FeatureCollection", geojson["type"]);
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(2, features.len());
+    assert_eq!("Point", features[0]["geometry"]["type"]);
+    assert_eq!(
+        serde_json::json!({ "id": "sa:A", "name": "Area A" }),
+        features[0]["properties"]
+    );
+}
+
+#[test]
+fn sanitize_removes_links_to_unknown_comments() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    // `comment:removed` doesn't exist
+    collections.comments = CollectionWithId::new(vec![Comment {
+        id: "comment:1".to_string(),
+        name: "Some comment".to_string(),
+        ..Default::default()
+    }])
+    .unwrap();
+    for comment_id in &["comment:1", "comment:removed"] {
+        let comment_id = comment_id.to_string();
+        collections
+            .lines
+            .get_mut("default_line")
+            .unwrap()
+            .comment_links
+            .insert(comment_id.clone());
+        collections
+            .vehicle_journeys
+            .get_mut("vj1")
+            .unwrap()
+            .comment_links
+            .insert(comment_id.clone());
+        collections
+            .stop_points
+            .get_mut("A")
+            .unwrap()
+            .comment_links
+            .insert(comment_id);
+    }
+    collections
+        .stop_time_comments
+        .insert(("vj1".to_string(), 0), "comment:removed".to_string());
+
+    collections.sanitize().unwrap();
+
+    let expected: CommentLinksT = vec!["comment:1".to_string()].into_iter().collect();
+    assert_eq!(
+        expected,
+        collections.lines.get("default_line").unwrap().comment_links
+    );
+    assert_eq!(
+        expected,
+        collections
+            .vehicle_journeys
+            .get("vj1")
+            .unwrap()
+            .comment_links
+    );
+    assert_eq!(
+        expected,
+        collections.stop_points.get("A").unwrap().comment_links
+    );
+    assert!(collections.stop_time_comments.is_empty());
+    assert_eq!(1, collections.comments.len());
+}
+
+// `vj1` stops at A, B then C, `vj2` at D and E, and `vj3` leaves the stop
+// areas of A and B to stop at D in between
+fn journeys_around_stop_areas() -> Collections {
+    ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00")
+                .st("C", "12:00:00", "12:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.st("D", "10:00:00", "10:01:00")
+                .st("E", "11:00:00", "11:01:00");
+        })
+        .vj("vj3", |vj| {
+            vj.st("A", "13:00:00", "13:01:00")
+                .st("D", "14:00:00", "14:01:00")
+                .st("B", "15:00:00", "15:01:00");
+        })
+        .build()
+        .into_collections()
+}
+
+fn stop_points_of(collections: &Collections, vj_id: &str) -> Vec<String> {
+    collections
+        .vehicle_journeys
+        .get(vj_id)
+        .unwrap()
+        .stop_times
+        .iter()
+        .map(|stop_time| collections.stop_points[stop_time.stop_point_idx].id.clone())
+        .collect()
+}
+
+fn ids<T: Id<T>>(collection: &CollectionWithId<T>) -> Vec<&str> {
+    collection.values().map(|object| object.id()).collect()
+}
+
+#[test]
+fn restrict_to_stop_areas_trims_partial_journeys() {
+    let mut collections = journeys_around_stop_areas();
+    collections
+        .restrict_to_stop_areas(
+            &["sa:A".to_string(), "sa:B".to_string()],
+            PartialJourneys::Trim,
+        )
+        .unwrap();
+    assert_eq!(vec!["vj1", "vj3"], ids(&collections.vehicle_journeys));
+    assert_eq!(vec!["A", "B"], stop_points_of(&collections, "vj1"));
+    // not contiguous, the vehicle journey is kept whole
+    assert_eq!(vec!["A", "D", "B"], stop_points_of(&collections, "vj3"));
+    let mut stop_point_ids = ids(&collections.stop_points);
+    stop_point_ids.sort_unstable();
+    assert_eq!(vec!["A", "B", "D"], stop_point_ids);
+}
+
+#[test]
+fn restrict_to_stop_areas_keeps_whole_partial_journeys() {
+    let mut collections = journeys_around_stop_areas();
+    collections
+        .restrict_to_stop_areas(
+            &["sa:A".to_string(), "sa:B".to_string()],
+            PartialJourneys::KeepWhole,
+        )
+        .unwrap();
+    assert_eq!(vec!["vj1", "vj3"], ids(&collections.vehicle_journeys));
+    assert_eq!(vec!["A", "B", "C"], stop_points_of(&collections, "vj1"));
+    let mut stop_point_ids = ids(&collections.stop_points);
+    stop_point_ids.sort_unstable();
+    assert_eq!(vec!["A", "B", "C", "D"], stop_point_ids);
+}
+
+#[test]
+fn restrict_to_unknown_stop_area() {
+    let mut collections = journeys_around_stop_areas();
+    let error = collections
+        .restrict_to_stop_areas(&["sa:unknown".to_string()], PartialJourneys::Trim)
+        .unwrap_err();
+    assert_eq!("stop area sa:unknown not found", format!("{}", error));
+}
+
+fn next_journeys<'a>(model: &'a Model, vj_id: &str) -> Vec<&'a str> {
+    let vj_idx = model.vehicle_journeys.get_idx(vj_id).unwrap();
+    model
+        .next_journeys_in_block(vj_idx)
+        .into_iter()
+        .map(|idx| model.vehicle_journeys[idx].id.as_str())
+        .collect()
+}
+
+#[test]
+fn next_journeys_in_block_sharing_a_date() {
+    let model = ModelBuilder::default()
+        .calendar("c1", &["2020-01-01", "2020-01-02"])
+        .calendar("c2", &["2020-01-03"])
+        .vj("vj1", |vj| {
+            vj.block_id("block_1")
+                .calendar("c1")
+                .st("SP1", "10:00:00", "10:01:00")
+                .st("SP2", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.block_id("block_1")
+                .calendar("c1")
+                .st("SP3", "14:00:00", "14:01:00")
+                .st("SP4", "15:00:00", "15:01:00");
+        })
+        .vj("vj3", |vj| {
+            vj.block_id("block_1")
+                .calendar("c1")
+                .st("SP3", "12:00:00", "12:01:00")
+                .st("SP4", "13:00:00", "13:01:00");
+        })
+        // no common date
+        .vj("vj4", |vj| {
+            vj.block_id("block_1")
+                .calendar("c2")
+                .st("SP3", "12:00:00", "12:01:00")
+                .st("SP4", "13:00:00", "13:01:00");
+        })
+        // other block
+        .vj("vj5", |vj| {
+            vj.block_id("block_2")
+                .calendar("c1")
+                .st("SP3", "12:00:00", "12:01:00")
+                .st("SP4", "13:00:00", "13:01:00");
+        })
+        // no block
+        .vj("vj6", |vj| {
+            vj.calendar("c1")
+                .st("SP3", "12:00:00", "12:01:00")
+                .st("SP4", "13:00:00", "13:01:00");
+        })
+        .build();
+    assert_eq!(vec!["vj3", "vj2"], next_journeys(&model, "vj1"));
+    assert_eq!(vec!["vj2"], next_journeys(&model, "vj3"));
+    assert!(next_journeys(&model, "vj2").is_empty());
+    assert!(next_journeys(&model, "vj6").is_empty());
+}
+
+#[test]
+fn next_journeys_in_block_ignores_overlapping_journeys() {
+    // `vj2` starts before the end of `vj1`
+    let model = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.block_id("block_1")
+                .st("SP1", "10:00:00", "10:01:00")
+                .st("SP2", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.block_id("block_1")
+                .st("SP3", "10:30:00", "10:31:00")
+                .st("SP4", "12:00:00", "12:01:00");
+        })
+        .build();
+    assert!(next_journeys(&model, "vj1").is_empty());
+}
+
+// the line "1" has been split in 2 routes, imported as 2 lines
+fn line_split_in_two_routes() -> Collections {
+    let mut collections = ModelBuilder::default()
+        .route("r1", |route| route.line_id = "l1:forward".to_string())
+        .route("r2", |route| route.line_id = "l1:backward".to_string())
+        .route("r3", |route| route.line_id = "l2".to_string())
+        .vj("vj1", |vj| {
+            vj.route("r1")
+                .st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.route("r2")
+                .st("B", "12:00:00", "12:01:00")
+                .st("A", "13:00:00", "13:01:00");
+        })
+        .vj("vj3", |vj| {
+            vj.route("r3")
+                .st("B", "12:00:00", "12:01:00")
+                .st("C", "13:00:00", "13:01:00");
+        })
+        .build()
+        .into_collections();
+    for (line_id, code, name) in &[
+        ("l1:forward", "1", "Line 1 (A > B)"),
+        ("l1:backward", "1", "Line 1 (B > A)"),
+        ("l2", "2", "Line 2"),
+    ] {
+        let mut line = collections.lines.get_mut(line_id).unwrap();
+        line.code = Some(code.to_string());
+        line.name = name.to_string();
+    }
+    collections
+}
+
+#[test]
+fn merge_lines_by_code() {
+    let mut collections = line_split_in_two_routes();
+    collections
+        .merge_lines_by(|line| (line.network_id.clone(), line.code.clone()))
+        .unwrap();
+
+    let line_ids: BTreeSet<&str> = collections
+        .lines
+        .values()
+        .map(|line| line.id.as_str())
+        .collect();
+    assert_eq!(
+        vec!["l1:backward", "l2"],
+        line_ids.into_iter().collect::<Vec<_>>()
+    );
+    // the attributes of the kept line are unchanged
+    assert_eq!(
+        "Line 1 (B > A)",
+        collections.lines.get("l1:backward").unwrap().name
+    );
+    assert_eq!("l1:backward", collections.routes.get("r1").unwrap().line_id);
+    assert_eq!("l1:backward", collections.routes.get("r2").unwrap().line_id);
+    assert_eq!("l2", collections.routes.get("r3").unwrap().line_id);
+    assert!(Model::new(collections).is_ok());
+}
+
+#[test]
+fn merge_lines_by_ignores_lines_without_route() {
+    let mut collections = line_split_in_two_routes();
+    collections
+        .lines
+        .push(Line {
+            id: "l3".to_string(),
+            code: Some("2".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    collections
+        .merge_lines_by(|line| line.code.clone())
+        .unwrap();
+    assert!(collections.lines.contains_id("l2"));
+    assert!(collections.lines.contains_id("l3"));
+}
+
+#[test]
+fn sanitize_removes_lines_without_route_and_networks_without_line() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build()
+        .into_collections();
+    // `network:empty` only has `line:empty`, which has no route
+    collections
+        .networks
+        .push(Network {
+            id: "network:empty".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    collections
+        .lines
+        .push(Line {
+            id: "line:empty".into(),
+            network_id: "network:empty".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    collections.sanitize().unwrap();
+
+    assert!(collections.lines.contains_id("default_line"));
+    assert!(!collections.lines.contains_id("line:empty"));
+    assert!(!collections.networks.contains_id("network:empty"));
+}
+
+#[test]
+fn zero_dwell_stop_times_of_intermediate_stops() {
+    // `vj2` runs after midnight, its dwell at `B` spans midnight
+    let model = ModelBuilder::default()
+        .vj("vj2", |vj| {
+            vj.st("A", "23:50:00", "23:50:00")
+                .st("B", "23:59:00", "24:01:00")
+                .st("C", "24:10:00", "24:10:00")
+                .st("D", "24:20:00", "24:20:00");
+        })
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "10:10:00", "10:10:00")
+                .st("C", "10:20:00", "10:20:30")
+                .st("D", "10:30:00", "10:31:00");
+        })
+        .build();
+    assert_eq!(vec![("vj1", 1), ("vj2", 2)], model.zero_dwell_stop_times());
+}
+
+#[test]
+fn sanitize_deduplicates_equipments() {
+    let mut collections = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00")
+                .st("C", "12:00:00", "12:01:00");
+        })
+        .build()
+        .into_collections();
+    // `eq:1` and `eq:2` are identical elevators
+    let elevator = |id: &str| Equipment {
+        id: id.into(),
+        elevator: Availability::Available,
+        ..Default::default()
+    };
+    collections.equipments = CollectionWithId::new(vec![
+        elevator("eq:2"),
+        elevator("eq:1"),
+        Equipment {
+            id: "eq:3".into(),
+            sheltered: Availability::Available,
+            ..Default::default()
+        },
+    ])
+    .unwrap();
+    collections.stop_areas.get_mut("sa:A").unwrap().equipment_id = Some("eq:2".into());
+    collections.stop_points.get_mut("B").unwrap().equipment_id = Some("eq:2".into());
+    collections.stop_points.get_mut("C").unwrap().equipment_id = Some("eq:3".into());
+
+    collections.sanitize().unwrap();
+    assert_eq!(
+        vec!["eq:1", "eq:3"],
+        collections
+            .equipments
+            .values()
+            .map(|e| e.id.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some("eq:1"),
+        collections
+            .stop_areas
+            .get("sa:A")
+            .unwrap()
+            .equipment_id
+            .as_deref()
+    );
+    assert_eq!(
+        Some("eq:1"),
+        collections
+            .stop_points
+            .get("B")
+            .unwrap()
+            .equipment_id
+            .as_deref()
+    );
+    assert_eq!(
+        Some("eq:3"),
+        collections
+            .stop_points
+            .get("C")
+            .unwrap()
+            .equipment_id
+            .as_deref()
+    );
+}
+
+// `overnight` runs across midnight, `after_midnight` starts after midnight
+// and `day` doesn't run at night
+fn journeys_after_midnight() -> Collections {
+    ModelBuilder::default()
+        .vj("day", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("overnight", |vj| {
+            vj.st("A", "23:50:00", "23:55:00")
+                .st("B", "23:59:00", "24:01:00")
+                .st("C", "25:10:00", "25:10:00");
+        })
+        .vj("after_midnight", |vj| {
+            vj.st("A", "24:50:00", "24:55:00")
+                .st("C", "25:10:00", "25:10:00");
+        })
+        .build()
+        .into_collections()
+}
+
+fn times(collections: &Collections, vj_id: &str) -> Vec<(Time, Time)> {
+    collections
+        .vehicle_journeys
+        .get(vj_id)
+        .unwrap()
+        .stop_times
+        .iter()
+        .map(|st| (st.arrival_time, st.departure_time))
+        .collect()
+}
+
+#[test]
+fn canonicalize_times_keeps_overnight_times() {
+    let mut collections = journeys_after_midnight();
+    collections
+        .canonicalize_times(OvernightTimes::default())
+        .unwrap();
+    assert_eq!(
+        vec![
+            (Time::new(24, 50, 0), Time::new(24, 55, 0)),
+            (Time::new(25, 10, 0), Time::new(25, 10, 0)),
+        ],
+        times(&collections, "after_midnight")
+    );
+    assert_eq!(
+        "default_service",
+        collections
+            .vehicle_journeys
+            .get("after_midnight")
+            .unwrap()
+            .service_id
+    );
+    assert_eq!(0, collections.comments.len());
+}
+
+#[test]
+fn canonicalize_times_modulo_day() {
+    let mut collections = journeys_after_midnight();
+    collections
+        .canonicalize_times(OvernightTimes::ModuloDay)
+        .unwrap();
+    assert_eq!(
+        vec![
+            (Time::new(0, 50, 0), Time::new(0, 55, 0)),
+            (Time::new(1, 10, 0), Time::new(1, 10, 0)),
+        ],
+        times(&collections, "after_midnight")
+    );
+    let vj = collections.vehicle_journeys.get("after_midnight").unwrap();
+    assert_eq!("default_service:shifted_1", vj.service_id);
+    assert!(vj.comment_links.contains(CALENDAR_SHIFT_COMMENT_ID));
+    let calendar = collections.calendars.get(&vj.service_id).unwrap();
+    let expected_dates: BTreeSet<Date> = vec![Date::from_ymd(2020, 1, 2)].into_iter().collect();
+    assert_eq!(expected_dates, calendar.dates);
+    assert!(collections.comments.contains_id(CALENDAR_SHIFT_COMMENT_ID));
+
+    // running across midnight, the times can't be moved within the day
+    assert_eq!(
+        vec![
+            (Time::new(23, 50, 0), Time::new(23, 55, 0)),
+            (Time::new(23, 59, 0), Time::new(24, 1, 0)),
+            (Time::new(25, 10, 0), Time::new(25, 10, 0)),
+        ],
+        times(&collections, "overnight")
+    );
+    let vj = collections.vehicle_journeys.get("overnight").unwrap();
+    assert_eq!("default_service", vj.service_id);
+    assert!(vj.comment_links.is_empty());
+    let vj = collections.vehicle_journeys.get("day").unwrap();
+    assert_eq!("default_service", vj.service_id);
+    assert!(vj.comment_links.is_empty());
+    assert_eq!(
+        vec![
+            (Time::new(10, 0, 0), Time::new(10, 1, 0)),
+            (Time::new(11, 0, 0), Time::new(11, 1, 0)),
+        ],
+        times(&collections, "day")
+    );
+}
+
+#[test]
+fn canonicalize_times_modulo_day_ntfs_round_trip() {
+    let mut collections = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/")
+        .unwrap()
+        .into_collections();
+    let one_day = Time::new(24, 0, 0);
+    for stop_time in &mut collections
+        .vehicle_journeys
+        .get_mut("M1F1")
+        .unwrap()
+        .stop_times
+    {
+        stop_time.arrival_time = stop_time.arrival_time + one_day;
+        stop_time.departure_time = stop_time.departure_time + one_day;
+    }
+    collections.frequencies.push(Frequency {
+        vehicle_journey_id: "M1F1".to_string(),
+        start_time: Time::new(33, 0, 0),
+        end_time: Time::new(34, 0, 0),
+        headway_secs: 600,
+    });
+    let week_dates = collections.calendars.get("Week").unwrap().dates.clone();
+    collections
+        .canonicalize_times(OvernightTimes::ModuloDay)
+        .unwrap();
+    let model = Model::new(collections).unwrap();
+
+    test_in_tmp_dir(|path| {
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+        let model = transit_model::ntfs::read(path).unwrap();
+        let vj = model.vehicle_journeys.get("M1F1").unwrap();
+        assert_eq!(Time::new(9, 0, 0), vj.stop_times[0].arrival_time);
+        assert!(vj
+            .stop_times
+            .windows(2)
+            .all(|sts| sts[0].departure_time <= sts[1].arrival_time));
+        assert!(vj.comment_links.contains(CALENDAR_SHIFT_COMMENT_ID));
+        assert!(model.comments.contains_id(CALENDAR_SHIFT_COMMENT_ID));
+        let shifted_dates: BTreeSet<Date> = week_dates
+            .iter()
+            .map(|date| *date + chrono::Duration::days(1))
+            .collect();
+        assert_eq!(
+            shifted_dates,
+            model.calendars.get(&vj.service_id).unwrap().dates
+        );
+        assert_eq!(
+            vec![(Time::new(9, 0, 0), Time::new(10, 0, 0))],
+            model
+                .frequencies
+                .values()
+                .map(|frequency| (frequency.start_time, frequency.end_time))
+                .collect::<Vec<_>>()
+        );
+    });
+}
@@ -0,0 +1,125 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use pretty_assertions::assert_eq;
+use transit_model::{
+    model::{Collections, Model},
+    objects::Date,
+    test_utils::*,
+    AddPrefix, PrefixConfiguration,
+};
+use transit_model_builder::ModelBuilder;
+
+#[test]
+fn validity_period_per_dataset() {
+    test_in_tmp_dir(|path| {
+        let model = ModelBuilder::default()
+            .calendar("c1", &["2020-01-01", "2020-01-15"])
+            .calendar("c2", &["2020-03-01", "2020-04-30"])
+            .vj("vj1", |vj| {
+                vj.dataset("d1")
+                    .calendar("c1")
+                    .st("A", "10:00:00", "10:01:00")
+                    .st("B", "11:00:00", "11:01:00");
+            })
+            .vj("vj2", |vj| {
+                vj.dataset("d2")
+                    .calendar("c2")
+                    .st("C", "10:00:00", "10:01:00")
+                    .st("D", "11:00:00", "11:01:00");
+            })
+            .build();
+        let mut collections = model.into_collections();
+        collections.compute_datasets_validity_periods();
+        let model = Model::new(collections).unwrap();
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+
+        let model = transit_model::ntfs::read(path).unwrap();
+        let periods: Vec<_> = model
+            .datasets
+            .values()
+            .map(|d| (d.id.as_str(), d.start_date, d.end_date))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "d1",
+                    Date::from_ymd(2020, 1, 1),
+                    Date::from_ymd(2020, 1, 15)
+                ),
+                (
+                    "d2",
+                    Date::from_ymd(2020, 3, 1),
+                    Date::from_ymd(2020, 4, 30)
+                ),
+            ],
+            periods
+        );
+        assert_eq!(
+            Some("20200101"),
+            model.feed_infos.get("feed_start_date").map(String::as_str)
+        );
+    });
+}
+
+fn read_with_prefix(path: &str, prefix: &str) -> Collections {
+    let mut collections = transit_model::ntfs::read(path).unwrap().into_collections();
+    let mut prefix_conf = PrefixConfiguration::default();
+    prefix_conf.set_data_prefix(prefix);
+    collections.prefix(&prefix_conf);
+    collections
+}
+
+#[test]
+fn merge_datasets_of_two_fixtures() {
+    test_in_tmp_dir(|path| {
+        let mut collections = read_with_prefix("tests/fixtures/minimal_ntfs/", "minimal");
+        let other = read_with_prefix("tests/fixtures/transfers/mono_contributor/input/", "oif");
+        collections.try_merge(other).unwrap();
+        let model = Model::new(collections).unwrap();
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+
+        let model = transit_model::ntfs::read(path).unwrap();
+        let mut periods: Vec<_> = model
+            .datasets
+            .values()
+            .map(|d| (d.id.as_str(), d.start_date, d.end_date))
+            .collect();
+        periods.sort();
+        assert_eq!(
+            vec![
+                (
+                    "minimal:TGDS",
+                    Date::from_ymd(2018, 1, 1),
+                    Date::from_ymd(2018, 12, 31)
+                ),
+                (
+                    "oif:OIF:0",
+                    Date::from_ymd(2017, 6, 7),
+                    Date::from_ymd(2017, 7, 2)
+                ),
+            ],
+            periods
+        );
+        assert_eq!(2, model.contributors.len());
+        assert_eq!(
+            Some("20170607"),
+            model.feed_infos.get("feed_start_date").map(String::as_str)
+        );
+        assert_eq!(
+            Some("20181231"),
+            model.feed_infos.get("feed_end_date").map(String::as_str)
+        );
+    });
+}
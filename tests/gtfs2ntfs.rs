@@ -14,7 +14,9 @@
 
 use std::collections::BTreeMap;
 use transit_model::{
-    gtfs, ntfs,
+    gtfs,
+    model::Model,
+    ntfs,
     objects::{Contributor, Dataset},
     read_utils::read_config,
     test_utils::*,
@@ -38,6 +40,7 @@ fn test_gtfs() {
             on_demand_transport: false,
             on_demand_transport_comment: None,
             read_as_line: false,
+            ..Default::default()
         };
         let model = transit_model::gtfs::Reader::new(configuration)
             .parse(input_dir)
@@ -76,6 +79,24 @@ fn test_gtfs_physical_modes() {
     });
 }
 
+#[test]
+fn test_gtfs_co2_emissions_override() {
+    let mut co2_emissions = BTreeMap::new();
+    co2_emissions.insert("Bus".to_string(), 100.0);
+    let configuration = gtfs::Configuration {
+        co2_emissions,
+        ..Default::default()
+    };
+    let model = gtfs::Reader::new(configuration)
+        .parse("./tests/fixtures/gtfs2ntfs/physical_modes/input")
+        .unwrap();
+    let bus_co2 = model.physical_modes.get("Bus").unwrap().co2_emission;
+    approx::assert_relative_eq!(100.0, bus_co2.unwrap());
+    // modes not in the configuration keep the default value
+    let coach_co2 = model.physical_modes.get("Coach").unwrap().co2_emission;
+    approx::assert_relative_eq!(171.0, coach_co2.unwrap());
+}
+
 #[test]
 fn test_gtfs_remove_vjs_with_no_traffic() {
     test_in_tmp_dir(|path| {
@@ -143,6 +164,7 @@ fn test_minimal_gtfs_with_odt_comment() {
                 "Service à réservation {agency_name} {agency_phone}".to_string(),
             ),
             read_as_line: false,
+            ..Default::default()
         };
         let model = transit_model::gtfs::Reader::new(configuration)
             .parse(input_dir)
@@ -172,6 +194,7 @@ fn test_minimal_gtfs_frequencies_with_odt_comment() {
                 "Service à réservation {agency_name} {agency_phone}".to_string(),
             ),
             read_as_line: false,
+            ..Default::default()
         };
 
         let model = transit_model::gtfs::Reader::new(configuration)
@@ -219,3 +242,93 @@ fn test_minimal_gtfs_with_routes_as_lines_comments() {
         );
     });
 }
+
+#[test]
+fn test_gtfs_pickup_drop_off_types_round_trip() {
+    fn pickup_drop_off_types(model: &Model) -> Vec<(u8, u8)> {
+        model
+            .vehicle_journeys
+            .get("trip:1")
+            .unwrap()
+            .stop_times
+            .iter()
+            .map(|st| (st.pickup_type, st.drop_off_type))
+            .collect()
+    }
+    let expected = vec![(2, 1), (3, 2), (0, 3), (1, 0)];
+
+    test_in_tmp_dir(|path| {
+        let input_dir = "./tests/fixtures/gtfs2ntfs/pickup_drop_off/input";
+        let model = transit_model::gtfs::read(input_dir).unwrap();
+        assert_eq!(expected, pickup_drop_off_types(&model));
+
+        let ntfs_path = path.join("ntfs");
+        ntfs::write(&model, &ntfs_path, get_test_datetime()).unwrap();
+        let model = ntfs::read(&ntfs_path).unwrap();
+        assert_eq!(expected, pickup_drop_off_types(&model));
+
+        let gtfs_path = path.join("gtfs");
+        gtfs::write(model, &gtfs_path).unwrap();
+        let model = gtfs::read(&gtfs_path).unwrap();
+        assert_eq!(expected, pickup_drop_off_types(&model));
+    });
+}
+
+#[test]
+fn test_gtfs_platform_code_round_trip() {
+    fn platform_code(model: &Model) -> Option<&str> {
+        model
+            .stop_points
+            .get("stop:11")
+            .unwrap()
+            .platform_code
+            .as_deref()
+    }
+
+    test_in_tmp_dir(|path| {
+        let model = transit_model::gtfs::read("./tests/fixtures/gtfs").unwrap();
+        assert_eq!(Some("A"), platform_code(&model));
+
+        let ntfs_path = path.join("ntfs");
+        ntfs::write(&model, &ntfs_path, get_test_datetime()).unwrap();
+        let model = ntfs::read(&ntfs_path).unwrap();
+        assert_eq!(Some("A"), platform_code(&model));
+
+        let gtfs_path = path.join("gtfs");
+        gtfs::write(model, &gtfs_path).unwrap();
+        let model = gtfs::read(&gtfs_path).unwrap();
+        assert_eq!(Some("A"), platform_code(&model));
+    });
+}
+
+#[test]
+fn test_gtfs_agency_contacts_round_trip() {
+    fn contacts(model: &Model) -> (Option<&str>, Option<&str>, Option<&str>) {
+        let network = model.networks.get("2").unwrap();
+        (
+            network.phone.as_deref(),
+            network.email.as_deref(),
+            network.fare_url.as_deref(),
+        )
+    }
+    let expected = (
+        Some("0123456789"),
+        Some("contact@kisio.org"),
+        Some("http://kisio.org/fares"),
+    );
+
+    test_in_tmp_dir(|path| {
+        let model = transit_model::gtfs::read("./tests/fixtures/gtfs").unwrap();
+        assert_eq!(expected, contacts(&model));
+
+        let ntfs_path = path.join("ntfs");
+        ntfs::write(&model, &ntfs_path, get_test_datetime()).unwrap();
+        let model = ntfs::read(&ntfs_path).unwrap();
+        assert_eq!(expected, contacts(&model));
+
+        let gtfs_path = path.join("gtfs");
+        gtfs::write(model, &gtfs_path).unwrap();
+        let model = gtfs::read(&gtfs_path).unwrap();
+        assert_eq!(expected, contacts(&model));
+    });
+}
@@ -0,0 +1,49 @@
+// Copyright (C) 2017 Kisio Digital and/or its affiliates.
+//
+// This program is free software: you can redistribute it and/or modify it
+// under the terms of the GNU Affero General Public License as published by the
+// Free Software Foundation, version 3.
+
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU Affero General Public License for more
+// details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>
+
+use transit_model::{model, test_utils::*};
+
+#[test]
+fn json_round_trip() {
+    let model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs").unwrap();
+    let mut json = Vec::new();
+    model::to_json_writer(&model, &mut json).unwrap();
+    let read_model = model::from_json_reader(json.as_slice()).unwrap();
+
+    test_in_tmp_dir(|path| {
+        let expected_path = path.join("expected");
+        let output_path = path.join("output");
+        transit_model::ntfs::write(&model, &expected_path, get_test_datetime()).unwrap();
+        transit_model::ntfs::write(&read_model, &output_path, get_test_datetime()).unwrap();
+        compare_output_dir_with_expected(&output_path, None, &expected_path);
+    });
+}
+
+#[test]
+fn json_deserialized_model_equals_original() {
+    for path in &["tests/fixtures/minimal_ntfs", "tests/fixtures/ntfs"] {
+        let model = transit_model::ntfs::read(path).unwrap();
+        let mut json = Vec::new();
+        model::to_json_writer(&model, &mut json).unwrap();
+        let read_model = model::from_json_reader(json.as_slice()).unwrap();
+
+        let mut read_json = Vec::new();
+        model::to_json_writer(&read_model, &mut read_json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            String::from_utf8(read_json).unwrap()
+        );
+        assert!(transit_model::ntfs::diff(&model, &read_model).is_empty());
+    }
+}
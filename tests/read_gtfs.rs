@@ -13,11 +13,21 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>
 
 use pretty_assertions::assert_eq;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use transit_model::{
+    gtfs::{Configuration, Reader},
+    objects::{BookingType, ObjectType, RestrictionType},
+    test_utils::*,
+    Model, PrefixConfiguration,
+};
 
 #[test]
 fn simple_gtfs_reading() {
     let ntm = transit_model::gtfs::read("tests/fixtures/gtfs").unwrap();
     assert_eq!(ntm.stop_areas.len(), 2);
+    let dataset = ntm.datasets.values().next().unwrap();
+    assert_eq!(dataset.system, Some("gtfs".to_string()));
 }
 
 #[test]
@@ -69,3 +79,217 @@ fn directory_not_a_gtfs() {
     // reading a directory that does not contain the gtfs files will lead to an error
     let _ = transit_model::gtfs::read("tests/fixtures/netex_france").unwrap();
 }
+
+#[test]
+fn gtfs_fares_reading() {
+    let model = transit_model::gtfs::read("tests/fixtures/gtfs_fares").unwrap();
+    // fare:3 only applies to an unknown route and is ignored
+    assert_eq!(model.tickets.len(), 3);
+    let ticket_use = model.ticket_uses.get("fare:1").unwrap();
+    assert_eq!(ticket_use.max_transfers, None);
+    assert_eq!(ticket_use.boarding_time_limit, Some(3600));
+    let perimeters: Vec<_> = model
+        .ticket_use_perimeters
+        .values()
+        .map(|p| {
+            (
+                p.ticket_use_id.as_str(),
+                &p.object_type,
+                p.object_id.as_str(),
+            )
+        })
+        .collect();
+    // each route of fare:4 is only valid between its own zones
+    assert_eq!(
+        perimeters,
+        vec![
+            ("fare:1", &ObjectType::Line, "route:2"),
+            ("fare:2", &ObjectType::Network, "2"),
+            ("fare:4:0", &ObjectType::Line, "route:2"),
+            ("fare:4:1", &ObjectType::Line, "route:3"),
+        ]
+    );
+    let restrictions: Vec<_> = model
+        .ticket_use_restrictions
+        .values()
+        .map(|r| {
+            (
+                r.ticket_use_id.as_str(),
+                &r.restriction_type,
+                r.use_origin.as_str(),
+                r.use_destination.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        restrictions,
+        vec![
+            ("fare:2", &RestrictionType::Zone, "zone:1", "zone:2"),
+            ("fare:4:0", &RestrictionType::Zone, "zone:1", "zone:2"),
+            ("fare:4:1", &RestrictionType::Zone, "zone:2", "zone:1"),
+        ]
+    );
+    assert_eq!(
+        "fare:4",
+        model.ticket_uses.get("fare:4:1").unwrap().ticket_id
+    );
+    let price = model.ticket_prices.values().next().unwrap();
+    assert_eq!(price.currency, "EUR");
+    assert_eq!(price.price, dec!(1.90));
+}
+
+fn booking_rule_of<'a>(
+    booking_rules: &'a HashMap<(String, u32), String>,
+    vj_id: &str,
+    sequence: u32,
+) -> Option<&'a str> {
+    booking_rules
+        .get(&(vj_id.to_string(), sequence))
+        .map(String::as_str)
+}
+
+#[test]
+fn gtfs_flex_booking_rules_reading() {
+    let model = transit_model::gtfs::read("tests/fixtures/gtfs_flex").unwrap();
+    let same_day = model.booking_rules.get("same_day").unwrap();
+    assert_eq!(same_day.booking_type, BookingType::SameDay);
+    assert_eq!(same_day.prior_notice_duration_min, Some(30));
+    assert_eq!(same_day.phone_number.as_deref(), Some("0123456789"));
+    assert_eq!(same_day.info_url.as_deref(), Some("http://kisio.org/info"));
+    let prior_day = model.booking_rules.get("prior_day").unwrap();
+    assert_eq!(prior_day.booking_type, BookingType::PriorDays);
+    assert_eq!(prior_day.prior_notice_last_day, Some(1));
+    assert_eq!(
+        prior_day.message.as_deref(),
+        Some("Book the day before on our website")
+    );
+    assert_eq!(
+        prior_day.booking_url.as_deref(),
+        Some("http://kisio.org/booking")
+    );
+
+    let pickup = &model.stop_time_pickup_booking_rules;
+    assert_eq!(booking_rule_of(pickup, "trip:1", 0), Some("same_day"));
+    assert_eq!(booking_rule_of(pickup, "trip:1", 1), Some("same_day"));
+    assert_eq!(booking_rule_of(pickup, "trip:1", 2), None);
+    let drop_off = &model.stop_time_drop_off_booking_rules;
+    assert_eq!(booking_rule_of(drop_off, "trip:1", 0), None);
+    assert_eq!(booking_rule_of(drop_off, "trip:1", 1), Some("same_day"));
+    assert_eq!(booking_rule_of(drop_off, "trip:1", 2), Some("prior_day"));
+    assert_eq!(booking_rule_of(pickup, "trip:2", 0), None);
+    assert_eq!(booking_rule_of(drop_off, "trip:2", 0), None);
+    // the booking rules are not turned into comments
+    assert!(model.stop_time_comments.is_empty());
+}
+
+#[test]
+fn single_agency_without_id_reading() {
+    let model = transit_model::gtfs::read("tests/fixtures/gtfs_single_agency_without_id").unwrap();
+    assert_eq!(1, model.networks.len());
+    assert_eq!(1, model.companies.len());
+    let network_id = &model.networks.values().next().unwrap().id;
+    let company_id = &model.companies.values().next().unwrap().id;
+    assert_eq!(2, model.lines.len());
+    assert!(model.lines.values().all(|l| &l.network_id == network_id));
+    assert_eq!(2, model.vehicle_journeys.len());
+    assert!(model
+        .vehicle_journeys
+        .values()
+        .all(|vj| &vj.company_id == company_id));
+}
+
+// The stop time identifiers are keyed by vehicle journey index, which
+// depends on the order in which the vehicle journeys are read
+fn stop_time_ids_by_vj_id(model: &Model) -> HashMap<(String, u32), String> {
+    model
+        .stop_time_ids
+        .iter()
+        .map(|((vj_idx, sequence), stop_time_id)| {
+            (
+                (model.vehicle_journeys[*vj_idx].id.clone(), *sequence),
+                stop_time_id.clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn prefixed_stop_time_ids_round_trip() {
+    let mut prefix_conf = PrefixConfiguration::default();
+    prefix_conf.set_data_prefix("PRE");
+    let configuration = Configuration {
+        prefix_conf: Some(prefix_conf),
+        on_demand_transport_comment: Some("Call {agency_phone}".to_string()),
+        ..Default::default()
+    };
+    let model = Reader::new(configuration)
+        .parse("tests/fixtures/gtfs_flex")
+        .unwrap();
+    let vj_idx = model.vehicle_journeys.get_idx("PRE:trip:1").unwrap();
+    assert_eq!(
+        model.stop_time_ids.get(&(vj_idx, 2)).map(String::as_str),
+        Some("PRE:trip:1-2")
+    );
+    assert_eq!(
+        model
+            .stop_time_comments
+            .get(&("PRE:trip:1".to_string(), 2))
+            .map(String::as_str),
+        Some("PRE:ODT:1")
+    );
+
+    test_in_tmp_dir(|path| {
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+        let read_model = transit_model::ntfs::read(path).unwrap();
+        assert_eq!(
+            stop_time_ids_by_vj_id(&model),
+            stop_time_ids_by_vj_id(&read_model)
+        );
+        assert_eq!(model.stop_time_comments, read_model.stop_time_comments);
+    });
+}
+
+#[test]
+fn gtfs_with_custom_id_generator() {
+    struct CustomIdGenerator;
+    impl transit_model::IdGenerator for CustomIdGenerator {
+        fn frequency_vehicle_journey_id(&self, trip_id: &str, index: u32) -> String {
+            format!("{}:frequency:{}", trip_id, index)
+        }
+    }
+    let model = transit_model::gtfs::Reader::default()
+        .with_id_generator(CustomIdGenerator)
+        .parse("tests/fixtures/gtfs")
+        .unwrap();
+    assert!(model.vehicle_journeys.contains_id("trip:3:frequency:0"));
+    assert!(!model.vehicle_journeys.contains_id("trip:3-0"));
+}
+
+#[test]
+fn gtfs_with_tab_delimiter() {
+    let input_dir = std::path::Path::new("tests/fixtures/gtfs2ntfs/minimal/input");
+    transit_model::test_utils::test_in_tmp_dir(|path| {
+        for entry in std::fs::read_dir(input_dir).unwrap() {
+            let entry = entry.unwrap();
+            let content = std::fs::read_to_string(entry.path()).unwrap();
+            std::fs::write(path.join(entry.file_name()), content.replace(',', "\t")).unwrap();
+        }
+        let configuration = transit_model::gtfs::Configuration {
+            csv_config: transit_model::reader::CsvConfig {
+                delimiter: b'\t',
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let model = transit_model::gtfs::Reader::new(configuration)
+            .parse(path)
+            .unwrap();
+        let expected_model = transit_model::gtfs::read(input_dir).unwrap();
+        assert_eq!(
+            expected_model.vehicle_journeys.len(),
+            model.vehicle_journeys.len()
+        );
+        assert_eq!(expected_model.stop_points.len(), model.stop_points.len());
+        assert_eq!(expected_model.lines.len(), model.lines.len());
+    });
+}
@@ -19,6 +19,7 @@ use std::collections::HashMap;
 use transit_model::model::{Collections, GetCorresponding, Model};
 use transit_model::objects::*;
 use transit_model::test_utils::*;
+use transit_model::{AddPrefix, PrefixConfiguration};
 use typed_index_collection::{CollectionWithId, Id, Idx};
 
 fn get<T, U>(idx: Idx<T>, collection: &CollectionWithId<U>, objects: &Model) -> Vec<String>
@@ -92,6 +93,50 @@ fn minimal() {
     test_minimal_ntfs(&ntm);
 }
 
+#[test]
+fn minimal_stop_times_iteration() {
+    let ntm = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
+    assert_eq!(23, ntm.iter_stop_times().count());
+    let line_idx = ntm.lines.get_idx("RERA").unwrap();
+    let rera_stop_times: Vec<_> = ntm.iter_line_stop_times(line_idx).collect();
+    assert_eq!(11, rera_stop_times.len());
+    assert!(rera_stop_times.iter().all(|(vj_idx, _)| {
+        ["RERAF1", "RERAB1"].contains(&ntm.vehicle_journeys[*vj_idx].id.as_str())
+    }));
+}
+
+#[test]
+fn invalid_object_codes() {
+    use transit_model::ntfs::{ReadConfiguration, ValidationError};
+    let path = "tests/fixtures/ntfs_with_invalid_object_codes";
+    let errors =
+        transit_model::ntfs::validate_object_codes(path, &ReadConfiguration::default()).unwrap();
+    assert_eq!(
+        vec![
+            ValidationError::UnresolvedReference {
+                object_type: "stop_point".to_string(),
+                object_id: "unknown_stop_point".to_string(),
+            },
+            ValidationError::UnresolvedReference {
+                object_type: "trip".to_string(),
+                object_id: "unknown_trip".to_string(),
+            },
+            ValidationError::UnknownObjectType {
+                object_type: "line_group".to_string(),
+                object_id: "LG1".to_string(),
+            },
+            ValidationError::UnknownObjectType {
+                object_type: "vehicle".to_string(),
+                object_id: "M1F1".to_string(),
+            },
+        ],
+        errors
+    );
+
+    // an unsupported object_type makes the reading fail
+    assert!(transit_model::ntfs::read(path).is_err());
+}
+
 #[test]
 fn zipped_minimal() {
     let ntm = transit_model::ntfs::read("tests/fixtures/zipped_ntfs/minimal_ntfs.zip").unwrap();
@@ -174,6 +219,24 @@ fn test_minimal_platforms_stay_same() {
     });
 }
 
+#[test]
+fn test_minimal_company_contacts_stay_same() {
+    let ntm = transit_model::ntfs::read("tests/fixtures/ntfs2ntfs/platforms").unwrap();
+    let company = ntm.companies.get("TGC").unwrap();
+    assert_eq!(
+        Some("contact@the-great-company.com"),
+        company.mail.as_deref()
+    );
+    test_in_tmp_dir(|output_dir| {
+        transit_model::ntfs::write(&ntm, output_dir, get_test_datetime()).unwrap();
+        compare_output_dir_with_expected(
+            &output_dir,
+            Some(vec!["companies.txt"]),
+            "tests/fixtures/ntfs2ntfs/platforms",
+        );
+    });
+}
+
 #[test]
 fn test_minimal_fares_stay_same_with_empty_of_fares() {
     let ntm = transit_model::ntfs::read("tests/fixtures/ntfs2ntfs/empty_od_fares").unwrap();
@@ -468,3 +531,342 @@ fn ntfs_with_duplicated_objects_without_id() {
     assert_eq!(1, model.grid_periods.len());
     assert_eq!(2, model.grid_rel_calendar_line.len());
 }
+
+#[test]
+fn read_partial_stops_only() {
+    let model = transit_model::ntfs::read_partial(
+        "tests/fixtures/ntfs/",
+        transit_model::ntfs::ReadScope::StopsOnly,
+    )
+    .unwrap();
+    assert!(model.vehicle_journeys.is_empty());
+    assert!(model.calendars.is_empty());
+    assert!(model.frequencies.is_empty());
+    assert!(!model.stop_areas.is_empty());
+    assert!(!model.stop_points.is_empty());
+    assert!(!model.lines.is_empty());
+    assert!(!model.networks.is_empty());
+    // the relations are still built on the loaded objects
+    let stop_area_idx = model.stop_areas.iter().next().unwrap().0;
+    let stop_points: IdxSet<StopPoint> = model.get_corresponding_from_idx(stop_area_idx);
+    assert!(!stop_points.is_empty());
+}
+
+#[test]
+fn read_partial_no_stop_times() {
+    let model = transit_model::ntfs::read_partial(
+        "tests/fixtures/ntfs/",
+        transit_model::ntfs::ReadScope::NoStopTimes,
+    )
+    .unwrap();
+    assert!(!model.vehicle_journeys.is_empty());
+    assert!(model
+        .vehicle_journeys
+        .values()
+        .all(|vj| vj.stop_times.is_empty()));
+}
+
+#[test]
+fn occupancy_round_trip() {
+    let model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
+    let mut collections = model.into_collections();
+    collections
+        .vehicle_journeys
+        .get_mut("M1F1")
+        .unwrap()
+        .occupancy = Some(OccupancyStatus::StandingRoomOnly);
+    let model = Model::new(collections).unwrap();
+    test_in_tmp_dir(|path| {
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+        let object_properties =
+            std::fs::read_to_string(path.join("object_properties.txt")).unwrap();
+        assert!(object_properties.contains("trip,M1F1,occupancy,STANDING_ROOM_ONLY"));
+
+        let model = transit_model::ntfs::read(path).unwrap();
+        let vj = model.vehicle_journeys.get("M1F1").unwrap();
+        assert_eq!(Some(OccupancyStatus::StandingRoomOnly), vj.occupancy);
+        assert!(vj.object_properties.is_empty());
+        assert_eq!(None, model.vehicle_journeys.get("M1B1").unwrap().occupancy);
+    });
+}
+
+#[test]
+fn write_without_empty_optional_files() {
+    let model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
+    test_in_tmp_dir(|path| {
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+        assert!(!path.join("comments.txt").exists());
+        assert!(!path.join("transfers.txt").exists());
+    });
+}
+
+#[test]
+fn write_empty_optional_files() {
+    let model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
+    test_in_tmp_dir(|path| {
+        let configuration = transit_model::ntfs::WriteConfiguration {
+            write_empty_optional_files: true,
+        };
+        transit_model::ntfs::write_with_configuration(
+            &model,
+            path,
+            get_test_datetime(),
+            &configuration,
+        )
+        .unwrap();
+        assert_eq!(
+            "from_stop_id,to_stop_id,min_transfer_time,real_min_transfer_time,equipment_id\n",
+            std::fs::read_to_string(path.join("transfers.txt")).unwrap()
+        );
+        assert_eq!(
+            "comment_id,comment_type,comment_label,comment_name,comment_url\n",
+            std::fs::read_to_string(path.join("comments.txt")).unwrap()
+        );
+        // a non-empty file is written as usual
+        let lines = std::fs::read_to_string(path.join("lines.txt")).unwrap();
+        assert!(lines.lines().count() > 1);
+        // the written NTFS can be read back
+        transit_model::ntfs::read(path).unwrap();
+    });
+}
+
+#[test]
+fn empty_optional_files_headers() {
+    let configuration = transit_model::ntfs::WriteConfiguration {
+        write_empty_optional_files: true,
+    };
+    let full_model =
+        transit_model::ntfs::read("tests/fixtures/ntfs_complete_with_duplicated_ids").unwrap();
+    let minimal_model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
+    test_in_tmp_dir(|path| {
+        let full_path = path.join("full");
+        let empty_path = path.join("empty");
+        transit_model::ntfs::write(&full_model, &full_path, get_test_datetime()).unwrap();
+        transit_model::ntfs::write_with_configuration(
+            &minimal_model,
+            &empty_path,
+            get_test_datetime(),
+            &configuration,
+        )
+        .unwrap();
+        // the header of an empty file is the one written with objects
+        for file in &[
+            "comments.txt",
+            "comment_links.txt",
+            "object_codes.txt",
+            "equipments.txt",
+            "trip_properties.txt",
+            "geometries.txt",
+            "transfers.txt",
+            "admin_stations.txt",
+            "frequencies.txt",
+            "tickets.txt",
+            "ticket_uses.txt",
+            "ticket_prices.txt",
+            "ticket_use_perimeters.txt",
+            "ticket_use_restrictions.txt",
+            "grid_calendars.txt",
+            "grid_exception_dates.txt",
+            "grid_periods.txt",
+            "grid_rel_calendar_line.txt",
+            "pathways.txt",
+            "levels.txt",
+        ] {
+            let full_content = std::fs::read_to_string(full_path.join(file)).unwrap();
+            let empty_content = std::fs::read_to_string(empty_path.join(file)).unwrap();
+            assert_eq!(1, empty_content.lines().count(), "{}", file);
+            assert_eq!(
+                full_content.lines().next(),
+                empty_content.lines().next(),
+                "{}",
+                file
+            );
+        }
+
+        // the files of a previous export are replaced
+        transit_model::ntfs::write_with_configuration(
+            &minimal_model,
+            &full_path,
+            get_test_datetime(),
+            &configuration,
+        )
+        .unwrap();
+        let comments = std::fs::read_to_string(full_path.join("comments.txt")).unwrap();
+        assert_eq!(1, comments.lines().count());
+    });
+}
+
+#[test]
+fn rename_prefix() {
+    fn prefixed_collections(prefix: &str) -> Collections {
+        let mut collections = transit_model::ntfs::read("tests/fixtures/ntfs")
+            .unwrap()
+            .into_collections();
+        let mut prefix_conf = PrefixConfiguration::default();
+        prefix_conf.set_data_prefix(prefix);
+        prefix_conf.set_schedule_subprefix("WINTER");
+        collections.prefix(&prefix_conf);
+        collections
+    }
+
+    let mut renamed = prefixed_collections("OLD");
+    renamed.rename_prefix("OLD", "NEW");
+    let expected = prefixed_collections("NEW");
+    test_in_tmp_dir(|path| {
+        let renamed_path = path.join("renamed");
+        let expected_path = path.join("expected");
+        let renamed = Model::new(renamed).unwrap();
+        let expected = Model::new(expected).unwrap();
+        transit_model::ntfs::write(&renamed, &renamed_path, get_test_datetime()).unwrap();
+        transit_model::ntfs::write(&expected, &expected_path, get_test_datetime()).unwrap();
+        compare_output_dir_with_expected(&renamed_path, None, &expected_path);
+        assert!(!renamed
+            .distinct_prefixes()
+            .keys()
+            .any(|prefix| *prefix == "OLD"));
+    });
+}
+
+#[test]
+fn write_collection_to_writer() {
+    let model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs").unwrap();
+    let mut bytes = Vec::new();
+    transit_model::ntfs::write_collection_to_writer(model.lines.values(), &mut bytes).unwrap();
+    test_in_tmp_dir(|path| {
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+        let expected = std::fs::read(path.join("lines.txt")).unwrap();
+        assert_eq!(
+            String::from_utf8(expected).unwrap(),
+            String::from_utf8(bytes).unwrap()
+        );
+    });
+}
+
+#[test]
+fn diff_models() {
+    use transit_model::ntfs::{DiffEntry, DiffKind};
+    use transit_model_builder::ModelBuilder;
+
+    let old = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .vj("vj2", |vj| {
+            vj.route("r2")
+                .st("A", "12:00:00", "12:01:00")
+                .st("C", "13:00:00", "13:01:00");
+        })
+        .build();
+    let new = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:05:00", "11:06:00");
+        })
+        .vj("vj3", |vj| {
+            vj.route("r3")
+                .st("A", "12:00:00", "12:01:00")
+                .st("D", "13:00:00", "13:01:00");
+        })
+        .build();
+
+    assert!(transit_model::ntfs::diff(&old, &old).is_empty());
+    let entries: Vec<_> = transit_model::ntfs::diff(&old, &new)
+        .entries
+        .into_iter()
+        .map(
+            |DiffEntry {
+                 object_type,
+                 object_id,
+                 kind,
+             }| (object_type.as_str(), object_id, kind),
+        )
+        .collect();
+    let modified = |field: &str| DiffKind::Modified {
+        fields: vec![field.to_string()],
+    };
+    assert_eq!(
+        vec![
+            ("route", "r2".to_string(), DiffKind::Removed),
+            ("route", "r3".to_string(), DiffKind::Added),
+            ("trip", "vj1".to_string(), modified("stop_times")),
+            ("trip", "vj2".to_string(), DiffKind::Removed),
+            ("trip", "vj3".to_string(), DiffKind::Added),
+            ("stop_area", "sa:C".to_string(), DiffKind::Removed),
+            ("stop_area", "sa:D".to_string(), DiffKind::Added),
+            ("stop_point", "C".to_string(), DiffKind::Removed),
+            ("stop_point", "D".to_string(), DiffKind::Added),
+        ],
+        entries
+    );
+}
+
+#[test]
+fn diff_models_with_an_inserted_stop_point() {
+    use transit_model::ntfs::{DiffEntry, DiffKind};
+    use transit_model_builder::ModelBuilder;
+
+    let old = ModelBuilder::default()
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build();
+    // "Z" is the first stop point of the new model, shifting the index of
+    // the stop points of vj1
+    let new = ModelBuilder::default()
+        .vj("vj0", |vj| {
+            vj.route("r0")
+                .st("Z", "10:00:00", "10:01:00")
+                .st("A", "11:00:00", "11:01:00");
+        })
+        .vj("vj1", |vj| {
+            vj.st("A", "10:00:00", "10:01:00")
+                .st("B", "11:00:00", "11:01:00");
+        })
+        .build();
+
+    let entries: Vec<_> = transit_model::ntfs::diff(&old, &new)
+        .entries
+        .into_iter()
+        .map(
+            |DiffEntry {
+                 object_type,
+                 object_id,
+                 kind,
+             }| (object_type.as_str(), object_id, kind),
+        )
+        .collect();
+    assert_eq!(
+        vec![
+            ("route", "r0".to_string(), DiffKind::Added),
+            ("trip", "vj0".to_string(), DiffKind::Added),
+            ("stop_area", "sa:Z".to_string(), DiffKind::Added),
+            ("stop_point", "Z".to_string(), DiffKind::Added),
+        ],
+        entries
+    );
+}
+
+#[test]
+fn read_with_unknown_columns() {
+    test_in_tmp_dir(|path| {
+        let model = transit_model::ntfs::read("tests/fixtures/minimal_ntfs/").unwrap();
+        transit_model::ntfs::write(&model, path, get_test_datetime()).unwrap();
+        create_file_with_content(
+            path,
+            "lines.txt",
+            "line_id,line_name,network_id,commercial_mode_id,vendor_note\n\
+             M1,Metro 1,TGN,Metro,some note\n\
+             B42,Bus 42,TGN,Bus,\n\
+             RERA,RER A,TGN,RER,\n",
+        );
+        let (model, unknown_columns) = transit_model::ntfs::read_with_report(
+            path,
+            &transit_model::ntfs::ReadConfiguration::default(),
+        )
+        .unwrap();
+        assert_eq!("Metro 1", model.lines.get("M1").unwrap().name);
+        assert_eq!(vec!["vendor_note"], unknown_columns["lines.txt"]);
+    });
+}
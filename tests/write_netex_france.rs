@@ -56,6 +56,45 @@ fn test_write_netex_france_from_gtfs() {
     test_write_netex_france(model);
 }
 
+fn test_write_netex_france_stops(model: Model, expected_dir: &str) {
+    test_in_tmp_dir(|output_dir| {
+        let config = netex_france::WriteConfiguration::new("Participant")
+            .stop_provider("ProviderCode")
+            .current_datetime(get_test_datetime());
+        netex_france::write_stops(&model, output_dir.join("arrets.xml"), config).unwrap();
+        compare_output_dir_with_expected_content(&output_dir, None, expected_dir);
+    });
+}
+
+#[test]
+fn test_write_netex_france_stops_from_minimal_ntfs() {
+    let mut model = None;
+    test_in_tmp_dir(|input_dir| {
+        for entry in fs::read_dir("tests/fixtures/minimal_ntfs").unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, input_dir.join(path.file_name().unwrap())).unwrap();
+        }
+        // a stop area without any stop point
+        let mut stops = fs::read_to_string(input_dir.join("stops.txt")).unwrap();
+        stops.push_str("EMPTY,Empty stop area,48.85,2.35,1,\n");
+        fs::write(input_dir.join("stops.txt"), stops).unwrap();
+        model = Some(ntfs::read_partial(input_dir, ntfs::ReadScope::StopsOnly).unwrap());
+    });
+    test_write_netex_france_stops(
+        model.unwrap(),
+        "tests/fixtures/netex_france/output_stops/minimal",
+    );
+}
+
+#[test]
+fn test_write_netex_france_stops_with_accessibility() {
+    let model = ntfs::read("tests/fixtures/netex_france/input_ntfs").unwrap();
+    test_write_netex_france_stops(
+        model,
+        "tests/fixtures/netex_france/output_stops/accessibility",
+    );
+}
+
 #[test]
 #[cfg(feature = "xmllint")]
 fn validate_xml_schemas() {
@@ -91,4 +130,6 @@ fn validate_xml_schemas() {
     for network_path in network_paths {
         check_xml_in_folder(network_path);
     }
+    check_xml_in_folder("tests/fixtures/netex_france/output_stops/minimal/");
+    check_xml_in_folder("tests/fixtures/netex_france/output_stops/accessibility/");
 }